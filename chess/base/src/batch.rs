@@ -0,0 +1,139 @@
+use crate::{
+    game::Game,
+    pgn::{self, PgnGame},
+};
+use std::{
+    fmt::Display,
+    io::{self, Read},
+};
+
+/// Where a batch game ended up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub fen: String,
+    pub result: String,
+    /// The first illegal move, with its move number.
+    pub error: Option<String>,
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "fen: {}", self.fen)?;
+        write!(f, "result: {}", self.result)?;
+        if let Some(error) = &self.error {
+            write!(f, "\nerror: {error}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Plays one game's main line through the rules, stopping at the first bad move.
+#[must_use]
+pub fn play(pgn: &PgnGame) -> Report {
+    let mut game = match pgn.start_position() {
+        Ok(position) => Game::from_position(position),
+        Err(e) => {
+            return Report {
+                fen: String::new(),
+                result: "*".to_string(),
+                error: Some(format!("FEN: {e}")),
+            }
+        }
+    };
+
+    let mut error = None;
+    for pgn_move in &pgn.moves {
+        let number = pgn::move_number(game.position());
+        if let Err(e) = game.try_move(&pgn_move.san) {
+            error = Some(format!("move {number} {}: {e}", pgn_move.san));
+            break;
+        }
+    }
+
+    let result = game.outcome().map_or_else(
+        || pgn.result.clone(),
+        |outcome| format!("{} ({outcome})", outcome.result()),
+    );
+    Report {
+        fen: game.position().fen(),
+        result,
+        error,
+    }
+}
+
+/// Plays every game in `text`, which may be full PGN or a bare list of moves.
+pub fn run(text: &str) -> Result<Vec<Report>, String> {
+    Ok(PgnGame::parse_all(text)?.iter().map(play).collect())
+}
+
+/// Entry point for `--batch`; returns the process exit code.
+pub fn main(path: Option<&str>) -> i32 {
+    let text = match path {
+        None | Some("-") => {
+            let mut text = String::new();
+            io::stdin()
+                .read_to_string(&mut text)
+                .map(|_| text)
+                .map_err(|e| format!("Could not read stdin: {e}"))
+        }
+        Some(path) => {
+            std::fs::read_to_string(path).map_err(|e| format!("Could not read {path}: {e}"))
+        }
+    };
+
+    match text.and_then(|text| run(&text)) {
+        Ok(reports) => {
+            for (i, report) in reports.iter().enumerate() {
+                if reports.len() > 1 {
+                    println!("game {}", i + 1);
+                }
+                println!("{report}");
+            }
+            i32::from(reports.iter().any(|r| r.error.is_some()))
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            2
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_move_list() {
+        let reports = run("e2e4 e5 Nf3 Nc6").unwrap();
+        assert_eq!(
+            reports,
+            vec![Report {
+                fen: "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3".to_string(),
+                result: "*".to_string(),
+                error: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn reports_mate_and_illegal_moves() {
+        let reports = run("1. f3 e5 2. g4 Qh4# 0-1\n\n1. e4 e5 2. Ke3 Nc6 *").unwrap();
+        assert_eq!(reports[0].result, "0-1 (Checkmate, Black wins)");
+        assert_eq!(reports[0].error, None);
+
+        assert_eq!(
+            reports[1].fen,
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2"
+        );
+        assert_eq!(
+            reports[1].error.as_deref(),
+            Some("move 2. Ke3: Illegal move 'Ke3'")
+        );
+    }
+
+    #[test]
+    fn starts_from_fen_tag() {
+        let reports = run("[FEN \"7k/8/6K1/8/8/8/8/R7 w - - 0 1\"]\n\n1. Ra8# 1-0").unwrap();
+        assert_eq!(reports[0].result, "1-0 (Checkmate, White wins)");
+    }
+}
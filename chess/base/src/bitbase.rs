@@ -0,0 +1,781 @@
+use crate::{
+    board::{position::Position, square::Coord},
+    piece::{Color, Kind},
+};
+use std::{collections::HashMap, fmt::Display, path::Path};
+
+/// Directory tables are cached in when no other is given.
+pub const DIR: &str = "bitbases";
+
+const MAGIC: &[u8; 4] = b"CBB1";
+
+/// The endings we build tables for. The strong side always has a king plus
+/// these pieces, the weak side a bare king.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Material {
+    Kqk,
+    Krk,
+    Kpk,
+    Kbnk,
+}
+
+impl Material {
+    /// In generation order: KPK promotes into KQK and KRK.
+    pub const ALL: [Self; 4] = [Self::Kqk, Self::Krk, Self::Kpk, Self::Kbnk];
+
+    #[must_use]
+    pub const fn pieces(self) -> &'static [Kind] {
+        match self {
+            Self::Kqk => &[Kind::Queen],
+            Self::Krk => &[Kind::Rook],
+            Self::Kpk => &[Kind::Pawn],
+            Self::Kbnk => &[Kind::Bishop, Kind::Knight],
+        }
+    }
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Kqk => "KQK",
+            Self::Krk => "KRK",
+            Self::Kpk => "KPK",
+            Self::Kbnk => "KBNK",
+        }
+    }
+
+    /// Number of entries: side to move, both kings and each extra piece.
+    #[allow(clippy::cast_possible_truncation)]
+    const fn size(self) -> usize {
+        2 * 64usize.pow(2 + self.pieces().len() as u32)
+    }
+
+    /// Works out which table `position` belongs to and which side is strong.
+    #[must_use]
+    pub fn of(position: &Position) -> Option<(Self, Color)> {
+        let mut white = Vec::new();
+        let mut black = Vec::new();
+        for (_, piece) in position.board.pieces() {
+            if piece.kind() != Kind::King {
+                match piece.color() {
+                    Color::White => white.push(piece.kind()),
+                    Color::Black => black.push(piece.kind()),
+                }
+            }
+        }
+        let (strong, mut pieces) = match (white.is_empty(), black.is_empty()) {
+            (false, true) => (Color::White, white),
+            (true, false) => (Color::Black, black),
+            _ => return None,
+        };
+        pieces.sort_by_key(|&kind| match kind {
+            Kind::Bishop => 0,
+            _ => 1,
+        });
+        Self::ALL
+            .into_iter()
+            .find(|m| m.pieces() == pieces.as_slice())
+            .map(|m| (m, strong))
+    }
+}
+
+impl Display for Material {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Win, draw or loss for the side to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wdl {
+    Win,
+    Draw,
+    Loss,
+}
+
+// Entry values while generating; only the first three survive into tables.
+const DRAW: u8 = 0;
+const WIN: u8 = 1;
+const LOSS: u8 = 2;
+const ILLEGAL: u8 = 3;
+const UNKNOWN: u8 = 4;
+
+/// A table position: squares are `rank * 8 + file`, the strong side is White.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Setup {
+    white_to_move: bool,
+    wk: u8,
+    bk: u8,
+    pieces: [u8; 2],
+}
+
+impl Setup {
+    /// Table index, given how many pieces besides the kings there are.
+    fn index(self, count: usize) -> usize {
+        let mut index = usize::from(!self.white_to_move);
+        index = index * 64 + usize::from(self.wk);
+        index = index * 64 + usize::from(self.bk);
+        for &square in &self.pieces[..count] {
+            index = index * 64 + usize::from(square);
+        }
+        index
+    }
+}
+
+/// A move's result: a position in this table, or a value settled elsewhere.
+enum Successor {
+    Stays,
+    Settled(u8),
+}
+
+/// A packed win/draw/loss table, two bits per entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    material: Material,
+    bits: Vec<u8>,
+}
+
+impl Table {
+    fn get(&self, index: usize) -> u8 {
+        (self.bits[index / 4] >> (index % 4 * 2)) & 0b11
+    }
+
+    fn pack(material: Material, values: &[u8]) -> Self {
+        let mut bits = vec![0; values.len().div_ceil(4)];
+        for (i, &value) in values.iter().enumerate() {
+            let value = if value == UNKNOWN { DRAW } else { value };
+            bits[i / 4] |= value << (i % 4 * 2);
+        }
+        Self { material, bits }
+    }
+
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        let mut name = [b' '; 4];
+        name[..self.material.name().len()].copy_from_slice(self.material.name().as_bytes());
+        bytes.extend(name);
+        bytes.extend(&self.bits);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < 8 || &bytes[..4] != MAGIC {
+            return Err("Not a bitbase file".to_string());
+        }
+        let name = std::str::from_utf8(&bytes[4..8]).map_err(|e| e.to_string())?;
+        let material = Material::ALL
+            .into_iter()
+            .find(|m| m.name() == name.trim_end())
+            .ok_or_else(|| format!("Unknown bitbase '{name}'"))?;
+        let bits = bytes[8..].to_vec();
+        if bits.len() != material.size().div_ceil(4) {
+            return Err(format!("{material} bitbase has the wrong size"));
+        }
+        Ok(Self { material, bits })
+    }
+
+    /// Builds the table by retrograde analysis. `done` must already hold the
+    /// tables this one converts into (KQK and KRK for KPK).
+    #[must_use]
+    pub fn generate(material: Material, done: &Bitbases) -> Self {
+        Generator::new(material, done).run()
+    }
+}
+
+struct Generator<'a> {
+    material: Material,
+    kinds: &'static [Kind],
+    done: &'a Bitbases,
+    values: Vec<u8>,
+    counts: Vec<u8>,
+}
+
+impl<'a> Generator<'a> {
+    fn new(material: Material, done: &'a Bitbases) -> Self {
+        let size = material.size();
+        Self {
+            material,
+            kinds: material.pieces(),
+            done,
+            values: vec![UNKNOWN; size],
+            counts: vec![0; size],
+        }
+    }
+
+    fn run(mut self) -> Table {
+        let mut queue = Vec::new();
+        for index in 0..self.values.len() {
+            let setup = self.decode(index);
+            if !self.is_legal(setup) {
+                self.values[index] = ILLEGAL;
+            }
+        }
+
+        // Settle mates, stalemates and positions decided by leaving the table.
+        for index in 0..self.values.len() {
+            if self.values[index] == ILLEGAL {
+                continue;
+            }
+            let setup = self.decode(index);
+            let successors = self.successors(setup);
+            let mut settled = successors.iter().filter_map(|s| match s {
+                Successor::Settled(value) => Some(*value),
+                Successor::Stays => None,
+            });
+            if setup.white_to_move {
+                if successors.is_empty() {
+                    self.values[index] = DRAW;
+                } else if settled.any(|v| v == LOSS) {
+                    self.values[index] = WIN;
+                    queue.push(index);
+                }
+            } else if successors.is_empty() {
+                self.values[index] = if self.black_in_check(setup) {
+                    LOSS
+                } else {
+                    DRAW
+                };
+                if self.values[index] == LOSS {
+                    queue.push(index);
+                }
+            } else if settled.any(|v| v != WIN) {
+                self.values[index] = DRAW;
+            } else {
+                self.counts[index] = u8::try_from(successors.len()).unwrap_or(u8::MAX);
+            }
+        }
+
+        while let Some(index) = queue.pop() {
+            let setup = self.decode(index);
+            for predecessor in self.predecessors(setup) {
+                if self.values[predecessor] != UNKNOWN {
+                    continue;
+                }
+                if setup.white_to_move {
+                    // Black moved into a lost position; one fewer way out.
+                    self.counts[predecessor] -= 1;
+                    if self.counts[predecessor] == 0 {
+                        self.values[predecessor] = LOSS;
+                        queue.push(predecessor);
+                    }
+                } else {
+                    self.values[predecessor] = WIN;
+                    queue.push(predecessor);
+                }
+            }
+        }
+        Table::pack(self.material, &self.values)
+    }
+
+    fn encode(&self, setup: Setup) -> usize {
+        setup.index(self.kinds.len())
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn decode(&self, mut index: usize) -> Setup {
+        let mut pieces = [0; 2];
+        for square in pieces[..self.kinds.len()].iter_mut().rev() {
+            *square = (index % 64) as u8;
+            index /= 64;
+        }
+        let bk = (index % 64) as u8;
+        index /= 64;
+        let wk = (index % 64) as u8;
+        Setup {
+            white_to_move: index / 64 == 0,
+            wk,
+            bk,
+            pieces,
+        }
+    }
+
+    fn occupied(&self, setup: Setup) -> u64 {
+        let mut occupied = bit(setup.wk) | bit(setup.bk);
+        for &square in &setup.pieces[..self.kinds.len()] {
+            occupied |= bit(square);
+        }
+        occupied
+    }
+
+    fn is_legal(&self, setup: Setup) -> bool {
+        let pieces = &setup.pieces[..self.kinds.len()];
+        let count = 2 + pieces.len();
+        if self.occupied(setup).count_ones() as usize != count
+            || KING_ATTACKS[usize::from(setup.wk)] & bit(setup.bk) != 0
+        {
+            return false;
+        }
+        let pawn_on_edge = self
+            .kinds
+            .iter()
+            .zip(pieces)
+            .any(|(&kind, &square)| kind == Kind::Pawn && !(8..56).contains(&square));
+        // With White to move, Black must not be in check.
+        !pawn_on_edge && (!setup.white_to_move || !self.black_in_check(setup))
+    }
+
+    fn black_in_check(&self, setup: Setup) -> bool {
+        self.white_attacks(setup, setup.bk, self.occupied(setup), None)
+    }
+
+    /// Whether White's pieces, other than the one on `skip`, attack `target`.
+    fn white_attacks(&self, setup: Setup, target: u8, occupied: u64, skip: Option<u8>) -> bool {
+        if KING_ATTACKS[usize::from(setup.wk)] & bit(target) != 0 {
+            return true;
+        }
+        self.kinds.iter().zip(setup.pieces).any(|(&kind, square)| {
+            Some(square) != skip && attacks(kind, square, occupied) & bit(target) != 0
+        })
+    }
+
+    fn successors(&self, setup: Setup) -> Vec<Successor> {
+        let occupied = self.occupied(setup);
+        let mut successors = Vec::new();
+        if setup.white_to_move {
+            for to in squares(KING_ATTACKS[usize::from(setup.wk)] & !occupied) {
+                if KING_ATTACKS[usize::from(to)] & bit(setup.bk) == 0 {
+                    successors.push(Successor::Stays);
+                }
+            }
+            for (i, &kind) in self.kinds.iter().enumerate() {
+                let from = setup.pieces[i];
+                let targets = if kind == Kind::Pawn {
+                    pawn_pushes(from, occupied)
+                } else {
+                    attacks(kind, from, occupied) & !occupied
+                };
+                for to in squares(targets) {
+                    if kind == Kind::Pawn && to >= 56 {
+                        let mut next = Setup {
+                            white_to_move: false,
+                            ..setup
+                        };
+                        next.pieces[i] = to;
+                        successors.push(Successor::Settled(self.promote(next)));
+                    } else {
+                        successors.push(Successor::Stays);
+                    }
+                }
+            }
+        } else {
+            let without_king = occupied & !bit(setup.bk);
+            for to in
+                squares(KING_ATTACKS[usize::from(setup.bk)] & !KING_ATTACKS[usize::from(setup.wk)])
+            {
+                let captured = setup.pieces[..self.kinds.len()].contains(&to);
+                if to == setup.wk || self.white_attacks(setup, to, without_king, Some(to)) {
+                    continue;
+                }
+                // Taking leaves a bare king, or a lone minor piece: a draw.
+                successors.push(if captured {
+                    Successor::Settled(DRAW)
+                } else {
+                    Successor::Stays
+                });
+            }
+        }
+        successors
+    }
+
+    /// Black's value after a pawn promotes: the best of a queen or a rook.
+    fn promote(&self, next: Setup) -> u8 {
+        [Material::Kqk, Material::Krk]
+            .into_iter()
+            .filter_map(|material| {
+                let table = self.done.tables.get(&material)?;
+                let setup = Setup {
+                    pieces: [next.pieces[0], 0],
+                    ..next
+                };
+                Some(table.get(setup.index(1)))
+            })
+            .find(|&value| value == LOSS)
+            .unwrap_or(DRAW)
+    }
+
+    fn predecessors(&self, setup: Setup) -> Vec<usize> {
+        let occupied = self.occupied(setup);
+        let mut predecessors = Vec::new();
+        let mut push = |previous: Setup| {
+            let index = self.encode(previous);
+            if self.values[index] != ILLEGAL {
+                predecessors.push(index);
+            }
+        };
+
+        if setup.white_to_move {
+            for from in squares(KING_ATTACKS[usize::from(setup.bk)] & !occupied) {
+                push(Setup {
+                    white_to_move: false,
+                    bk: from,
+                    ..setup
+                });
+            }
+            return predecessors;
+        }
+
+        let before = Setup {
+            white_to_move: true,
+            ..setup
+        };
+        for from in squares(KING_ATTACKS[usize::from(setup.wk)] & !occupied) {
+            push(Setup { wk: from, ..before });
+        }
+        for (i, &kind) in self.kinds.iter().enumerate() {
+            let to = setup.pieces[i];
+            let origins = if kind == Kind::Pawn {
+                pawn_origins(to, occupied)
+            } else {
+                attacks(kind, to, occupied) & !occupied
+            };
+            for from in squares(origins) {
+                let mut previous = before;
+                previous.pieces[i] = from;
+                push(previous);
+            }
+        }
+        predecessors
+    }
+}
+
+const fn bit(square: u8) -> u64 {
+    1 << square
+}
+
+/// Iterates over the set squares of a bitboard.
+fn squares(mut board: u64) -> impl Iterator<Item = u8> {
+    std::iter::from_fn(move || {
+        if board == 0 {
+            return None;
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        let square = board.trailing_zeros() as u8;
+        board &= board - 1;
+        Some(square)
+    })
+}
+
+#[allow(clippy::cast_possible_truncation)]
+const fn step_attacks(steps: &[(i8, i8)]) -> [u64; 64] {
+    let mut table = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        let (file, rank) = ((square % 8) as i8, (square / 8) as i8);
+        let mut i = 0;
+        while i < steps.len() {
+            let (f, r) = (file + steps[i].0, rank + steps[i].1);
+            if f >= 0 && f < 8 && r >= 0 && r < 8 {
+                table[square] |= 1 << (r * 8 + f);
+            }
+            i += 1;
+        }
+        square += 1;
+    }
+    table
+}
+
+const KING_ATTACKS: [u64; 64] = step_attacks(&crate::board::movegen::KING_STEPS);
+const KNIGHT_ATTACKS: [u64; 64] = step_attacks(&crate::board::movegen::KNIGHT_STEPS);
+
+fn slide(square: u8, occupied: u64, directions: &[(i8, i8)]) -> u64 {
+    let mut targets = 0;
+    for &(df, dr) in directions {
+        let (mut file, mut rank) = (i16::from(square % 8), i16::from(square / 8));
+        loop {
+            file += i16::from(df);
+            rank += i16::from(dr);
+            if !(0..8).contains(&file) || !(0..8).contains(&rank) {
+                break;
+            }
+            let target = 1 << (rank * 8 + file);
+            targets |= target;
+            if occupied & target != 0 {
+                break;
+            }
+        }
+    }
+    targets
+}
+
+/// Squares a White piece on `square` attacks.
+fn attacks(kind: Kind, square: u8, occupied: u64) -> u64 {
+    use crate::board::movegen::{BISHOP_DIRECTIONS, ROOK_DIRECTIONS};
+    match kind {
+        Kind::Pawn => {
+            let file = square % 8;
+            let mut targets = 0;
+            if file > 0 {
+                targets |= bit(square + 7);
+            }
+            if file < 7 {
+                targets |= bit(square + 9);
+            }
+            targets
+        }
+        Kind::Knight => KNIGHT_ATTACKS[usize::from(square)],
+        Kind::Bishop => slide(square, occupied, &BISHOP_DIRECTIONS),
+        Kind::Rook => slide(square, occupied, &ROOK_DIRECTIONS),
+        Kind::Queen => {
+            slide(square, occupied, &BISHOP_DIRECTIONS) | slide(square, occupied, &ROOK_DIRECTIONS)
+        }
+        Kind::King => KING_ATTACKS[usize::from(square)],
+    }
+}
+
+const fn pawn_pushes(square: u8, occupied: u64) -> u64 {
+    let one = square + 8;
+    if occupied & bit(one) != 0 {
+        return 0;
+    }
+    if square / 8 == 1 && occupied & bit(one + 8) == 0 {
+        return bit(one) | bit(one + 8);
+    }
+    bit(one)
+}
+
+/// Squares a White pawn now on `square` could have been pushed from.
+const fn pawn_origins(square: u8, occupied: u64) -> u64 {
+    let rank = square / 8;
+    if rank < 2 || occupied & bit(square - 8) != 0 {
+        return 0;
+    }
+    if rank == 3 && occupied & bit(square - 16) == 0 {
+        return bit(square - 8) | bit(square - 16);
+    }
+    bit(square - 8)
+}
+
+/// The tables that have been loaded or generated so far.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Bitbases {
+    tables: HashMap<Material, Table>,
+}
+
+impl Bitbases {
+    /// Loads whichever tables are already stored in `dir`.
+    #[must_use]
+    pub fn open(dir: &str) -> Self {
+        let mut bitbases = Self::default();
+        for material in Material::ALL {
+            if let Ok(table) = std::fs::read(path(dir, material))
+                .map_err(|e| e.to_string())
+                .and_then(|bytes| Table::from_bytes(&bytes))
+            {
+                bitbases.tables.insert(material, table);
+            }
+        }
+        bitbases
+    }
+
+    #[must_use]
+    pub fn has(&self, material: Material) -> bool {
+        self.tables.contains_key(&material)
+    }
+
+    /// Makes sure `material` is available, generating it (and what it depends
+    /// on) if needed. New tables are saved to `dir` when one is given.
+    pub fn ensure(&mut self, material: Material, dir: Option<&str>) -> Result<(), String> {
+        if self.has(material) {
+            return Ok(());
+        }
+        if material == Material::Kpk {
+            self.ensure(Material::Kqk, dir)?;
+            self.ensure(Material::Krk, dir)?;
+        }
+        let table = Table::generate(material, self);
+        if let Some(dir) = dir {
+            std::fs::create_dir_all(dir).map_err(|e| format!("Could not create {dir}: {e}"))?;
+            let path = path(dir, material);
+            std::fs::write(&path, table.to_bytes())
+                .map_err(|e| format!("Could not write {}: {e}", path.display()))?;
+        }
+        self.tables.insert(material, table);
+        Ok(())
+    }
+
+    /// Looks `position` up, if it is one of the endings we have a table for.
+    #[must_use]
+    pub fn probe(&self, position: &Position) -> Option<Wdl> {
+        let (material, strong) = Material::of(position)?;
+        let table = self.tables.get(&material)?;
+
+        // Tables have the strong side as White, so mirror the board otherwise.
+        let square = |coord: Coord| {
+            let square = coord.rank.0 * 8 + coord.file.0;
+            match strong {
+                Color::White => square,
+                Color::Black => square ^ 0b11_1000,
+            }
+        };
+        let mut setup = Setup {
+            white_to_move: position.turn == strong,
+            wk: 0,
+            bk: 0,
+            pieces: [0; 2],
+        };
+        for (coord, piece) in position.board.pieces() {
+            match (piece.kind(), piece.color() == strong) {
+                (Kind::King, true) => setup.wk = square(coord),
+                (Kind::King, false) => setup.bk = square(coord),
+                (kind, _) => {
+                    let slot = material.pieces().iter().position(|&k| k == kind)?;
+                    setup.pieces[slot] = square(coord);
+                }
+            }
+        }
+
+        match table.get(setup.index(material.pieces().len())) {
+            WIN => Some(Wdl::Win),
+            LOSS => Some(Wdl::Loss),
+            DRAW => Some(Wdl::Draw),
+            _ => None,
+        }
+    }
+}
+
+fn path(dir: &str, material: Material) -> std::path::PathBuf {
+    Path::new(dir).join(format!("{}.bb", material.name()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probe(bitbases: &Bitbases, fen: &str) -> Option<Wdl> {
+        bitbases.probe(&Position::from_fen(fen).unwrap())
+    }
+
+    #[test]
+    fn material_signature() {
+        let kbnk = Position::from_fen("8/8/8/3k4/8/8/8/KNB5 w - - 0 1").unwrap();
+        assert_eq!(Material::of(&kbnk), Some((Material::Kbnk, Color::White)));
+        let kqk = Position::from_fen("8/8/8/3k4/3q4/8/8/K7 w - - 0 1").unwrap();
+        assert_eq!(Material::of(&kqk), Some((Material::Kqk, Color::Black)));
+        assert_eq!(Material::of(&Position::default()), None);
+    }
+
+    #[test]
+    fn heavy_piece_endings() {
+        let mut bitbases = Bitbases::default();
+        bitbases.ensure(Material::Kqk, None).unwrap();
+        bitbases.ensure(Material::Krk, None).unwrap();
+
+        assert_eq!(
+            probe(&bitbases, "8/8/8/3k4/8/8/8/KQ6 w - - 0 1"),
+            Some(Wdl::Win)
+        );
+        assert_eq!(
+            probe(&bitbases, "8/8/8/3k4/8/8/8/KQ6 b - - 0 1"),
+            Some(Wdl::Loss)
+        );
+        // The queen hangs next to the king.
+        assert_eq!(
+            probe(&bitbases, "8/8/8/8/8/2k5/1Q6/7K b - - 0 1"),
+            Some(Wdl::Draw)
+        );
+        // Black is the strong side here and White is mated.
+        assert_eq!(
+            probe(&bitbases, "8/8/8/8/8/2k5/1q6/K7 w - - 0 1"),
+            Some(Wdl::Loss)
+        );
+        // Stalemate.
+        assert_eq!(
+            probe(&bitbases, "k7/2Q5/1K6/8/8/8/8/8 b - - 0 1"),
+            Some(Wdl::Draw)
+        );
+        assert_eq!(
+            probe(&bitbases, "8/8/8/3k4/8/8/8/KR6 w - - 0 1"),
+            Some(Wdl::Win)
+        );
+
+        let table = &bitbases.tables[&Material::Krk];
+        assert_eq!(Table::from_bytes(&table.to_bytes()).as_ref(), Ok(table));
+    }
+
+    #[test]
+    fn pawn_endings() {
+        let mut bitbases = Bitbases::default();
+        bitbases.ensure(Material::Kpk, None).unwrap();
+
+        // A king on the sixth in front of its pawn wins whoever is to move.
+        assert_eq!(
+            probe(&bitbases, "4k3/8/4K3/4P3/8/8/8/8 w - - 0 1"),
+            Some(Wdl::Win)
+        );
+        assert_eq!(
+            probe(&bitbases, "4k3/8/4K3/4P3/8/8/8/8 b - - 0 1"),
+            Some(Wdl::Loss)
+        );
+        // Except with a rook pawn.
+        assert_eq!(
+            probe(&bitbases, "7k/8/7K/7P/8/8/8/8 w - - 0 1"),
+            Some(Wdl::Draw)
+        );
+        // A pawn that outruns the king, for Black.
+        assert_eq!(
+            probe(&bitbases, "8/8/8/8/k7/8/7p/K7 b - - 0 1"),
+            Some(Wdl::Win)
+        );
+    }
+
+    #[test]
+    fn reads_tables_from_disk() {
+        // Generating KBNK is slow, so the lookup is checked against a table
+        // with just one position marked as won.
+        let square = |name: &str| {
+            let coord = name.parse::<Coord>().unwrap();
+            coord.rank.0 * 8 + coord.file.0
+        };
+        let won = Setup {
+            white_to_move: true,
+            wk: square("a1"),
+            bk: square("d5"),
+            pieces: [square("c1"), square("b1")],
+        };
+        let mut values = vec![DRAW; Material::Kbnk.size()];
+        values[won.index(2)] = WIN;
+        let dir = std::env::temp_dir().join(format!("chess-bitbases-{}", std::process::id()));
+        let dir = dir.to_str().unwrap();
+        std::fs::create_dir_all(dir).unwrap();
+        let table = Table::pack(Material::Kbnk, &values);
+        std::fs::write(path(dir, Material::Kbnk), table.to_bytes()).unwrap();
+        let bitbases = Bitbases::open(dir);
+        std::fs::remove_dir_all(dir).unwrap();
+
+        assert!(bitbases.has(Material::Kbnk));
+        assert_eq!(
+            probe(&bitbases, "8/8/8/3k4/8/8/8/KNB5 w - - 0 1"),
+            Some(Wdl::Win)
+        );
+        // The same position with Black as the strong side.
+        assert_eq!(
+            probe(&bitbases, "knb5/8/8/8/3K4/8/8/8 b - - 0 1"),
+            Some(Wdl::Win)
+        );
+        assert_eq!(
+            probe(&bitbases, "8/8/8/3k4/8/8/8/KNB5 b - - 0 1"),
+            Some(Wdl::Draw)
+        );
+    }
+
+    #[test]
+    #[ignore = "generating KBNK takes a while; run with --ignored --release"]
+    fn bishop_and_knight_ending() {
+        let mut bitbases = Bitbases::default();
+        bitbases.ensure(Material::Kbnk, None).unwrap();
+
+        assert_eq!(
+            probe(&bitbases, "8/8/8/3k4/8/8/8/KNB5 w - - 0 1"),
+            Some(Wdl::Win)
+        );
+        assert_eq!(
+            probe(&bitbases, "8/8/8/3k4/8/8/8/KNB5 b - - 0 1"),
+            Some(Wdl::Loss)
+        );
+        // The bishop hangs, and a knight alone cannot mate.
+        assert_eq!(
+            probe(&bitbases, "8/8/8/8/8/8/2k5/K1B4N b - - 0 1"),
+            Some(Wdl::Draw)
+        );
+    }
+}
@@ -1,11 +1,17 @@
+pub mod chess960;
 mod macros;
+pub mod movegen;
 pub mod movement;
+pub mod position;
 pub mod square;
+pub mod validate;
+pub mod variant;
+pub mod zobrist;
 
-use self::movement::Notation;
+use self::square::Coord;
 use crate::{
     board,
-    piece::{self, Kind, Piece},
+    piece::{Kind, Piece},
 };
 use itertools::Itertools;
 use std::io::{stdout, Write};
@@ -15,22 +21,20 @@ use termion::{
     raw::IntoRawMode,
 };
 
-#[derive(Debug)]
+/// Screen columns and rows from one square to the next.
+pub const SPACE: (u16, u16) = (2, 1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Board(pub [Option<Piece>; 64]);
 
 impl Board {
-    const FILE_RANK_BAR: &str = "  a|b|c|d|e|f|g|h\r
- +———————————————+\r
-8|               |8\r
-7|               |7\r
-6|               |6\r
-5|               |5\r
-4|               |4\r
-3|               |3\r
-2|               |2\r
-1|               |1\r
- +———————————————+\r
-  a|b|c|d|e|f|g|h";
+    /// Every occupied square with its piece, from a8 to h1.
+    pub fn pieces(&self) -> impl Iterator<Item = (Coord, Piece)> + '_ {
+        self.0
+            .iter()
+            .enumerate()
+            .filter_map(|(i, o)| o.map(|p| (Coord::from_index(i), p)))
+    }
 
     const fn start_position(/*s: &str*/) -> Goto {
         /*
@@ -67,16 +71,46 @@ impl Board {
         Goto(3, 3)
     }
 
-    pub fn print_sidebar(row_space: u16) -> Goto {
+    /// The file letters, border and rank numbers around the squares, with
+    /// `row_space - 1` empty lines between ranks.
+    fn frame(row_space: u16, flipped: bool) -> String {
+        let files = if flipped {
+            "  h|g|f|e|d|c|b|a"
+        } else {
+            "  a|b|c|d|e|f|g|h"
+        };
+        let border = " +———————————————+";
+        let mut lines = vec![files.to_string(), border.to_string()];
+        let ranks = if flipped {
+            [1, 2, 3, 4, 5, 6, 7, 8]
+        } else {
+            [8, 7, 6, 5, 4, 3, 2, 1]
+        };
+        for (i, rank) in ranks.into_iter().enumerate() {
+            if i > 0 {
+                lines.extend((1..row_space).map(|_| " |               |".to_string()));
+            }
+            lines.push(format!("{rank}|               |{rank}"));
+        }
+        lines.extend([border.to_string(), files.to_string()]);
+        lines.join("\r\n")
+    }
+
+    /// Screen rows the frame takes with ranks `row_space` apart.
+    #[must_use]
+    pub const fn height(row_space: u16) -> u16 {
+        4 + 7 * row_space + 1
+    }
+
+    pub fn print_sidebar(row_space: u16, flipped: bool) -> Goto {
         let mut stdout = stdout().into_raw_mode().unwrap();
         write!(stdout, "{}{}", clear::All, cursor::Goto(1, 1)).unwrap();
 
-        write!(stdout, "{}\r\n", Self::FILE_RANK_BAR).unwrap();
+        write!(stdout, "{}\r\n", Self::frame(row_space, flipped)).unwrap();
         let end = stdout.cursor_pos().unwrap();
 
         let board_start = Self::start_position(); //Self::start_position(Self::FILE_RANK_BAR);
         let Goto(start_x, mut start_y) = board_start;
-        // TODO: range based on row_space
         for _ in 1..=8 {
             write!(stdout, "{}", cursor::Goto(start_x, start_y)).unwrap();
             write!(stdout, ". . . . . . . .").unwrap();
@@ -89,11 +123,16 @@ impl Board {
         board_start
     }
 
-    pub fn print_board(&self, board_start: Goto, space: (u16, u16)) {
+    /// Draws the pieces, seen from Black's side when `flipped`.
+    pub fn print_board(&self, board_start: Goto, space: (u16, u16), flipped: bool) {
         print!("{}", cursor::Save);
 
+        let mut squares = self.0;
+        if flipped {
+            squares.reverse();
+        }
         let Goto(mut x, mut y) = board_start; //(3_u16, 3_u16);
-        for row in &self.0.iter().chunks(8) {
+        for row in &squares.iter().chunks(8) {
             for o in row {
                 let p = o.map_or_else(|| ".".to_string(), |p| p.to_string());
                 print!("{}{p}", cursor::Goto(x, y));
@@ -106,29 +145,41 @@ impl Board {
         print!("{}", cursor::Restore);
     }
 
-    pub fn print(&self) {
-        let space = (2, 1);
-        let start = Self::print_sidebar(space.1);
-        self.print_board(start, space);
+    pub fn print(&self, flipped: bool) {
+        let start = Self::print_sidebar(SPACE.1, flipped);
+        self.print_board(start, SPACE, flipped);
         println!();
         stdout().flush().unwrap();
     }
 
-    pub fn move_piece(&mut self, color: piece::Color, notation: &str) -> Result<(), String> {
-        let mov = notation.parse::<Notation>()?;
-        let dest = (mov.rank, mov.file);
-
-        let piece = Piece::new(color, mov.piece);
-
-        let src = self
-            .0
-            .into_iter()
-            .filter_map(|o| if o.as_ref() == Some(&piece) { o } else { None })
-            .collect::<Vec<_>>();
-
-        dbg!(src);
+    /// The screen cell `print` draws `coord` in.
+    #[must_use]
+    pub fn cell(coord: Coord, flipped: bool) -> Goto {
+        let (file, rank) = (u16::from(coord.file.0), u16::from(coord.rank.0));
+        let (column, row) = if flipped {
+            (7 - file, rank)
+        } else {
+            (file, 7 - rank)
+        };
+        let Goto(x, y) = Self::start_position();
+        Goto(x + column * SPACE.0, y + row * SPACE.1)
+    }
 
-        Ok(())
+    /// The square drawn at screen column `x` and row `y`, the inverse of
+    /// `cell`. The gap to the right of a square counts as part of it.
+    #[must_use]
+    pub fn square_at(x: u16, y: u16, flipped: bool) -> Option<Coord> {
+        let Goto(start_x, start_y) = Self::start_position();
+        let column = x.checked_sub(start_x)? / SPACE.0;
+        let row = y.checked_sub(start_y)?;
+        if column > 7 || row % SPACE.1 != 0 || row / SPACE.1 > 7 {
+            return None;
+        }
+        let mut index = usize::from(row / SPACE.1 * 8 + column);
+        if flipped {
+            index = 63 - index;
+        }
+        Some(Coord::from_index(index))
     }
 }
 
@@ -151,14 +202,53 @@ impl Default for Board {
 mod tests {
     use crate::piece::Color;
 
-    use super::*;
+    use super::{position::Position, *};
+    use crate::piece;
 
     #[test]
     fn movement() {
-        let mut board = Board::default();
-        board.move_piece(Color::Black, "a3").unwrap();
+        let mut position = Position {
+            turn: Color::Black,
+            ..Default::default()
+        };
+        assert!(position.play("a3").is_err());
+        position.play("a6").unwrap();
 
-        // assert_eq!(board.move_piece("a1"), Ok((Kind::Pawn, 0, 0)));
+        let a6 = "a6".parse::<Coord>().unwrap();
+        assert_eq!(position.board.0[a6.index()], piece!(P));
+    }
+
+    #[test]
+    fn frame() {
+        assert_eq!(
+            Board::frame(1, false),
+            "  a|b|c|d|e|f|g|h\r
+ +———————————————+\r
+8|               |8\r
+7|               |7\r
+6|               |6\r
+5|               |5\r
+4|               |4\r
+3|               |3\r
+2|               |2\r
+1|               |1\r
+ +———————————————+\r
+  a|b|c|d|e|f|g|h"
+        );
+        let spaced = Board::frame(2, true);
+        let lines = spaced.split("\r\n").collect::<Vec<_>>();
+        assert_eq!(lines.len(), usize::from(Board::height(2)));
+        assert_eq!(
+            lines[..5],
+            [
+                "  h|g|f|e|d|c|b|a",
+                " +———————————————+",
+                "1|               |1",
+                " |               |",
+                "2|               |2"
+            ]
+        );
+        assert_eq!(Board::height(1), 12);
     }
 
     #[test]
@@ -166,4 +256,26 @@ mod tests {
         let Goto(x, y) = Board::start_position();
         assert_eq!((x, y), (3, 3));
     }
+
+    #[test]
+    fn screen_cells() {
+        let e2 = "e2".parse::<Coord>().unwrap();
+        let Goto(x, y) = Board::cell(e2, false);
+        assert_eq!((x, y), (11, 9));
+        let Goto(x, y) = Board::cell(e2, true);
+        assert_eq!((x, y), (9, 4));
+        for flipped in [false, true] {
+            for index in 0..64 {
+                let coord = Coord::from_index(index);
+                let Goto(x, y) = Board::cell(coord, flipped);
+                assert_eq!(Board::square_at(x, y, flipped), Some(coord));
+                assert_eq!(Board::square_at(x + 1, y, flipped), Some(coord));
+            }
+        }
+        // The frame and whatever lies past it.
+        assert_eq!(Board::square_at(2, 3, false), None);
+        assert_eq!(Board::square_at(19, 3, false), None);
+        assert_eq!(Board::square_at(3, 2, false), None);
+        assert_eq!(Board::square_at(3, 11, false), None);
+    }
 }
@@ -0,0 +1,142 @@
+use super::{
+    movement::CastleSide,
+    position::{Castling, Position},
+    square::Coord,
+    Board,
+};
+use crate::piece::{Color, Kind, Piece};
+
+/// Number of Fischer Random start positions.
+pub const COUNT: u16 = 960;
+
+/// Where the knights go among the five squares left after the bishops and
+/// queen are placed, in Scharnagl order.
+const KNIGHTS: [(usize, usize); 10] = [
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (1, 2),
+    (1, 3),
+    (1, 4),
+    (2, 3),
+    (2, 4),
+    (3, 4),
+];
+
+/// The back rank of start position `number` (0 to 959), from the a-file,
+/// using Scharnagl's numbering where 518 is the standard setup.
+#[must_use]
+pub fn back_rank(number: u16) -> Option<[Kind; 8]> {
+    if number >= COUNT {
+        return None;
+    }
+    let mut rank = [None; 8];
+    let number = usize::from(number);
+    // Light squared bishop on b, d, f or h, dark squared on a, c, e or g.
+    rank[number % 4 * 2 + 1] = Some(Kind::Bishop);
+    rank[number / 4 % 4 * 2] = Some(Kind::Bishop);
+
+    let empty = |rank: &[Option<Kind>; 8]| {
+        (0..8)
+            .filter(|&file| rank[file].is_none())
+            .collect::<Vec<_>>()
+    };
+    let rest = number / 16;
+    rank[empty(&rank)[rest % 6]] = Some(Kind::Queen);
+    let (first, second) = KNIGHTS[rest / 6];
+    let free = empty(&rank);
+    rank[free[first]] = Some(Kind::Knight);
+    rank[free[second]] = Some(Kind::Knight);
+
+    // The king always stands between the two rooks.
+    for (file, kind) in empty(&rank)
+        .into_iter()
+        .zip([Kind::Rook, Kind::King, Kind::Rook])
+    {
+        rank[file] = Some(kind);
+    }
+    Some(rank.map(|kind| kind.unwrap_or(Kind::Pawn)))
+}
+
+impl Position {
+    /// Chess960 start position `number`, with full castling rights.
+    pub fn chess960(number: u16) -> Result<Self, String> {
+        let pieces = back_rank(number)
+            .ok_or_else(|| format!("Chess960 positions are numbered 0 to {}", COUNT - 1))?;
+        let mut board = Board([None; 64]);
+        let mut castling = Castling::NONE;
+        for color in [Color::White, Color::Black] {
+            let home = color.home_rank();
+            let pawns = home.wrapping_add_signed(color.forward());
+            for (file, kind) in (0..8).zip(pieces) {
+                board.0[Coord::new(file, home).index()] = Some(Piece::new(color, kind));
+                board.0[Coord::new(file, pawns).index()] = Some(Piece::new(color, Kind::Pawn));
+            }
+            let rooks = (0..8).filter(|&file| pieces[usize::from(file)] == Kind::Rook);
+            for (file, side) in rooks.zip([CastleSide::Queen, CastleSide::King]) {
+                castling.set(color, side, Some(file));
+            }
+        }
+        Ok(Self {
+            board,
+            castling,
+            ..Self::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbering() {
+        assert_eq!(Position::chess960(518), Ok(Position::default()));
+        assert_eq!(
+            Position::chess960(97).unwrap().fen(),
+            "bqnbrnkr/pppppppp/8/8/8/8/PPPPPPPP/BQNBRNKR w KQkq - 0 1"
+        );
+        assert_eq!(
+            Position::chess960(0).unwrap().fen(),
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"
+        );
+        assert!(Position::chess960(COUNT).is_err());
+
+        let mut seen = std::collections::HashSet::new();
+        for number in 0..COUNT {
+            let rank = back_rank(number).unwrap();
+            assert!(seen.insert(rank));
+            let files = |kind| (0..8).filter(move |&f| rank[f] == kind);
+            let bishops = files(Kind::Bishop).collect::<Vec<_>>();
+            assert_eq!(bishops[0] % 2 + bishops[1] % 2, 1);
+            let king = files(Kind::King).next().unwrap();
+            assert!(files(Kind::Rook).any(|f| f < king) && files(Kind::Rook).any(|f| f > king));
+        }
+    }
+
+    #[test]
+    fn castling() {
+        // Shredder-FEN rook files and X-FEN letters name the same rights.
+        let fen = "bqnbrnkr/pppppppp/8/8/8/8/PPPPPPPP/BQNBRNKR w HEhe - 0 1";
+        let mut position = Position::from_fen(fen).unwrap();
+        assert_eq!(position.fen(), fen.replace("HEhe", "KQkq"));
+        assert!(position.is_chess960());
+        let inner = Position::from_fen("4k3/8/8/8/8/8/8/R1R1K3 w C - 0 1").unwrap();
+        assert_eq!(inner.fen(), "4k3/8/8/8/8/8/8/R1R1K3 w C - 0 1");
+
+        // The king on g1 castles kingside without moving, by taking its rook.
+        position.play("Ng3").unwrap();
+        position.play("Ng6").unwrap();
+        let castle = position.parse_move("O-O").unwrap();
+        assert_eq!(castle.to_string(), "g1h1");
+        assert_eq!(position.san(castle), "O-O");
+        assert!(!position.is_capture(castle));
+        assert!(position.parse_move("O-O-O").is_err());
+        position.make_move(castle);
+        assert_eq!(
+            position.fen(),
+            "bqnbr1kr/pppppppp/6n1/8/8/6N1/PPPPPPPP/BQNBRRK1 b kq - 3 2"
+        );
+    }
+}
@@ -0,0 +1,296 @@
+use super::{
+    movement::{CastleSide, Move},
+    position::{castle_files, Position},
+    square::Coord,
+};
+use crate::piece::{Color, Kind, Piece};
+
+pub const KNIGHT_STEPS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+pub const KING_STEPS: [(i8, i8); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+pub const ROOK_DIRECTIONS: [(i8, i8); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+pub const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, -1), (-1, 1)];
+
+const PROMOTIONS: [Kind; 4] = [Kind::Queen, Kind::Rook, Kind::Bishop, Kind::Knight];
+
+impl Position {
+    /// Whether any piece of color `by` attacks `target`.
+    #[must_use]
+    pub fn is_attacked(&self, target: Coord, by: Color) -> bool {
+        let holds = |coord: Option<Coord>, kinds: &[Kind]| {
+            coord
+                .and_then(|c| self.piece_at(c))
+                .is_some_and(|p| p.color() == by && kinds.contains(&p.kind()))
+        };
+
+        let pawn_rank = -by.forward();
+        if holds(target.offset(-1, pawn_rank), &[Kind::Pawn])
+            || holds(target.offset(1, pawn_rank), &[Kind::Pawn])
+        {
+            return true;
+        }
+        if KNIGHT_STEPS
+            .iter()
+            .any(|&(df, dr)| holds(target.offset(df, dr), &[Kind::Knight]))
+        {
+            return true;
+        }
+        if KING_STEPS
+            .iter()
+            .any(|&(df, dr)| holds(target.offset(df, dr), &[Kind::King]))
+        {
+            return true;
+        }
+
+        let slides = [
+            (ROOK_DIRECTIONS, [Kind::Rook, Kind::Queen]),
+            (BISHOP_DIRECTIONS, [Kind::Bishop, Kind::Queen]),
+        ];
+        slides.iter().any(|(directions, kinds)| {
+            directions.iter().any(|&(df, dr)| {
+                let mut square = target.offset(df, dr);
+                while let Some(coord) = square {
+                    if self.piece_at(coord).is_some() {
+                        return holds(square, kinds);
+                    }
+                    square = coord.offset(df, dr);
+                }
+                false
+            })
+        })
+    }
+
+    #[must_use]
+    pub fn king(&self, color: Color) -> Option<Coord> {
+        self.board
+            .pieces()
+            .find(|(_, p)| p.color() == color && p.kind() == Kind::King)
+            .map(|(coord, _)| coord)
+    }
+
+    /// Whether the side to move is in check.
+    #[must_use]
+    pub fn in_check(&self) -> bool {
+        !self.kings_touch()
+            && self
+                .king(self.turn)
+                .is_some_and(|king| self.is_attacked(king, self.turn.opposite()))
+    }
+
+    /// Every legal move for the side to move. There are none once a
+    /// variant's goal has been reached.
+    #[must_use]
+    pub fn legal_moves(&self) -> Vec<Move> {
+        if self.variant_end().is_some() {
+            return Vec::new();
+        }
+        let mut moves = self.pseudo_moves();
+        self.drop_moves(&mut moves);
+        moves.retain(|&mv| self.is_safe(mv, &self.after(mv)));
+        moves
+    }
+
+    /// Moves that follow piece movement rules but may leave the king in check.
+    fn pseudo_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::with_capacity(48);
+        for (from, piece) in self.board.pieces() {
+            if piece.color() != self.turn {
+                continue;
+            }
+            match piece.kind() {
+                Kind::Pawn => self.pawn_moves(from, &mut moves),
+                Kind::Knight => self.step_moves(from, &KNIGHT_STEPS, &mut moves),
+                Kind::King => {
+                    self.step_moves(from, &KING_STEPS, &mut moves);
+                    self.castle_moves(&mut moves);
+                }
+                Kind::Bishop => self.slide_moves(from, &BISHOP_DIRECTIONS, &mut moves),
+                Kind::Rook => self.slide_moves(from, &ROOK_DIRECTIONS, &mut moves),
+                Kind::Queen => {
+                    self.slide_moves(from, &BISHOP_DIRECTIONS, &mut moves);
+                    self.slide_moves(from, &ROOK_DIRECTIONS, &mut moves);
+                }
+            }
+        }
+        moves
+    }
+
+    fn is_enemy(&self, coord: Coord) -> bool {
+        self.piece_at(coord).is_some_and(|p| p.color() != self.turn)
+    }
+
+    fn step_moves(&self, from: Coord, steps: &[(i8, i8)], moves: &mut Vec<Move>) {
+        for &(df, dr) in steps {
+            if let Some(to) = from.offset(df, dr) {
+                if self.piece_at(to).is_none() || self.is_enemy(to) {
+                    moves.push(Move::new(from, to));
+                }
+            }
+        }
+    }
+
+    fn slide_moves(&self, from: Coord, directions: &[(i8, i8)], moves: &mut Vec<Move>) {
+        for &(df, dr) in directions {
+            let mut square = from.offset(df, dr);
+            while let Some(to) = square {
+                if self.piece_at(to).is_some() {
+                    if self.is_enemy(to) {
+                        moves.push(Move::new(from, to));
+                    }
+                    break;
+                }
+                moves.push(Move::new(from, to));
+                square = to.offset(df, dr);
+            }
+        }
+    }
+
+    fn pawn_moves(&self, from: Coord, moves: &mut Vec<Move>) {
+        let forward = self.turn.forward();
+        let last_rank = self.turn.opposite().home_rank();
+        let mut push = |to: Coord| {
+            if to.rank.0 == last_rank {
+                for kind in PROMOTIONS {
+                    moves.push(Move {
+                        promotion: Some(kind),
+                        ..Move::new(from, to)
+                    });
+                }
+            } else {
+                moves.push(Move::new(from, to));
+            }
+        };
+
+        if let Some(one) = from
+            .offset(0, forward)
+            .filter(|&c| self.piece_at(c).is_none())
+        {
+            push(one);
+            let start_rank = self.turn.home_rank().wrapping_add_signed(forward);
+            if from.rank.0 == start_rank {
+                if let Some(two) = one
+                    .offset(0, forward)
+                    .filter(|&c| self.piece_at(c).is_none())
+                {
+                    push(two);
+                }
+            }
+        }
+        for df in [-1, 1] {
+            if let Some(to) = from.offset(df, forward) {
+                if self.is_enemy(to) || Some(to) == self.en_passant {
+                    push(to);
+                }
+            }
+        }
+    }
+
+    /// Castling under Chess960 rules, which cover standard chess too: every
+    /// square the king and rook cross or land on must be empty apart from the
+    /// two of them, and the king may not start on, cross or land on an
+    /// attacked square.
+    fn castle_moves(&self, moves: &mut Vec<Move>) {
+        let rank = self.turn.home_rank();
+        let enemy = self.turn.opposite();
+        let span = |a: u8, b: u8| a.min(b)..=a.max(b);
+        for side in [CastleSide::King, CastleSide::Queen] {
+            let (Some(mv), Some(rook_from)) = (
+                self.castle_move(self.turn, side),
+                self.castling.rook(self.turn, side),
+            ) else {
+                continue;
+            };
+            if self.piece_at(Coord::new(rook_from, rank)) != Some(Piece::new(self.turn, Kind::Rook))
+            {
+                continue;
+            }
+            let king_from = mv.from.file.0;
+            let (king_to, rook_to) = castle_files(side);
+            if span(king_from, king_to)
+                .chain(span(rook_from, rook_to))
+                .any(|file| {
+                    file != king_from
+                        && file != rook_from
+                        && self.piece_at(Coord::new(file, rank)).is_some()
+                })
+            {
+                continue;
+            }
+            if span(king_from, king_to).any(|file| self.is_attacked(Coord::new(file, rank), enemy))
+            {
+                continue;
+            }
+            moves.push(mv);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts leaf nodes of the legal move tree, the standard movegen check.
+    fn perft(position: &Position, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        position
+            .legal_moves()
+            .into_iter()
+            .map(|mv| perft(&position.after(mv), depth - 1))
+            .sum()
+    }
+
+    #[test]
+    fn perft_start() {
+        let position = Position::default();
+        assert_eq!(perft(&position, 1), 20);
+        assert_eq!(perft(&position, 2), 400);
+        assert_eq!(perft(&position, 3), 8902);
+    }
+
+    #[test]
+    fn perft_kiwipete() {
+        let position = Position::from_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        assert_eq!(perft(&position, 1), 48);
+        assert_eq!(perft(&position, 2), 2039);
+    }
+
+    #[test]
+    fn perft_endgame() {
+        let position = Position::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
+        assert_eq!(perft(&position, 1), 14);
+        assert_eq!(perft(&position, 2), 191);
+        assert_eq!(perft(&position, 3), 2812);
+    }
+
+    #[test]
+    fn perft_chess960() {
+        let position =
+            Position::from_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9")
+                .unwrap();
+        assert_eq!(perft(&position, 1), 21);
+        assert_eq!(perft(&position, 2), 528);
+        assert_eq!(perft(&position, 3), 12189);
+    }
+}
@@ -1,53 +1,206 @@
-use super::square::{File, Rank, Square};
+use super::square::{Coord, File, Rank, Square};
+use crate::piece::Kind;
 use core::str::{self, FromStr};
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CastleSide {
+    King,
+    Queen,
+}
 
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct Notation {
-    pub piece: crate::piece::Kind,
+    pub piece: Kind,
     pub ambiguitie: Option<Square>,
     pub file: File,
     pub rank: Rank,
+    pub promotion: Option<Kind>,
+    pub castle: Option<CastleSide>,
 }
 
-impl Notation {}
+impl Notation {
+    #[must_use]
+    pub const fn destination(&self) -> Coord {
+        Coord {
+            file: self.file,
+            rank: self.rank,
+        }
+    }
+
+    /// Splits a trailing promotion (`e8=Q` or `e8Q`) off the notation.
+    fn split_promotion(s: &str) -> Result<(&str, Option<Kind>), String> {
+        if let Some((rest, piece)) = s.split_once('=') {
+            return Ok((rest, Some(piece.parse()?)));
+        }
+        let mut chars = s.chars().rev();
+        match (chars.next(), chars.next()) {
+            (Some(piece @ ('N' | 'B' | 'R' | 'Q')), Some(rank)) if rank.is_ascii_digit() => {
+                Ok((&s[..s.len() - 1], Some(piece.to_string().parse()?)))
+            }
+            _ => Ok((s, None)),
+        }
+    }
+}
 
 impl FromStr for Notation {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim_end_matches(['+', '#', '!', '?']);
+        let s = s.strip_suffix("e.p.").unwrap_or(s).trim();
+        match s {
+            "O-O" | "0-0" => {
+                return Ok(Self {
+                    piece: Kind::King,
+                    castle: Some(CastleSide::King),
+                    ..Default::default()
+                })
+            }
+            "O-O-O" | "0-0-0" => {
+                return Ok(Self {
+                    piece: Kind::King,
+                    castle: Some(CastleSide::Queen),
+                    ..Default::default()
+                })
+            }
+            _ => {}
+        }
+
+        let (s, promotion) = Self::split_promotion(s)?;
         let mut characters: [Option<char>; 4] = [None; 4];
-        s.chars()
+        let mut chars = s.chars().filter(|&c| c != 'x' && c != ':');
+        chars
+            .by_ref()
             .zip(characters.iter_mut())
             .for_each(|(c, ptr)| *ptr = Some(c));
+        if chars.next().is_some() {
+            Err("notation wrong length")?;
+        }
 
-        match characters {
-            [Some(file), Some(rank), None, None] => Ok(Self {
+        let notation = match characters {
+            [Some(file), Some(rank), None, None] => Self {
                 file: file.try_into()?,
                 rank: rank.try_into()?,
                 ..Default::default()
-            }),
-            [Some(piece), Some(file), Some(rank), None] => Ok(Self {
+            },
+            // Pawn capture such as `exd5`, with the 'x' already removed.
+            [Some(from @ 'a'..='h'), Some(file), Some(rank), None] => Self {
+                ambiguitie: Some(from.try_into()?),
+                file: file.try_into()?,
+                rank: rank.try_into()?,
+                ..Default::default()
+            },
+            [Some(piece), Some(file), Some(rank), None] => Self {
                 piece: piece.to_string().parse()?,
                 file: file.try_into()?,
                 rank: rank.try_into()?,
                 ..Default::default()
-            }),
+            },
 
-            [Some(piece), Some(ambiguitie), Some(file), Some(rank)] => Ok(Self {
+            [Some(piece), Some(ambiguitie), Some(file), Some(rank)] => Self {
                 piece: piece.to_string().parse()?,
                 ambiguitie: Some(ambiguitie.try_into()?),
                 file: file.try_into()?,
                 rank: rank.try_into()?,
-            }),
+                ..Default::default()
+            },
             _ => Err("notation wrong length")?,
+        };
+
+        if promotion.is_some() && notation.piece != Kind::Pawn {
+            Err("only pawns can promote")?;
+        }
+        Ok(Self {
+            promotion,
+            ..notation
+        })
+    }
+}
+
+/// A move in coordinate form, written like `e2e4` or `e7e8q`.
+///
+/// Castling is the king moving two files, en passant a pawn moving onto the
+/// en passant square. A Crazyhouse drop is written `N@f3` and has `from`
+/// equal to `to`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Move {
+    pub from: Coord,
+    pub to: Coord,
+    pub promotion: Option<Kind>,
+    pub drop: Option<Kind>,
+}
+
+impl Move {
+    #[must_use]
+    pub const fn new(from: Coord, to: Coord) -> Self {
+        Self {
+            from,
+            to,
+            promotion: None,
+            drop: None,
+        }
+    }
+
+    /// Puts a piece from the pocket on `to`.
+    #[must_use]
+    pub const fn drop(kind: Kind, to: Coord) -> Self {
+        Self {
+            from: to,
+            to,
+            promotion: None,
+            drop: Some(kind),
+        }
+    }
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(kind) = self.drop {
+            return write!(f, "{}@{}", kind.letter().unwrap_or('P'), self.to);
+        }
+        write!(f, "{}{}", self.from, self.to)?;
+        if let Some(promotion) = self.promotion.and_then(Kind::letter) {
+            write!(f, "{}", promotion.to_ascii_lowercase())?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Move {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((piece, square)) = s.split_once('@') {
+            let kind = match piece {
+                "" | "P" | "p" => Kind::Pawn,
+                piece => piece.parse()?,
+            };
+            if kind == Kind::King {
+                return Err("The king cannot be dropped".to_string());
+            }
+            return Ok(Self::drop(kind, square.parse()?));
+        }
+        if !s.is_ascii() || !(4..=5).contains(&s.len()) {
+            return Err(format!("Could not parse '{s}' into a move."));
         }
+        let promotion = match s.get(4..) {
+            Some("") | None => None,
+            Some(piece) => match piece.parse()? {
+                Kind::Pawn | Kind::King => Err(format!("Cannot promote to '{piece}'"))?,
+                kind => Some(kind),
+            },
+        };
+        Ok(Self {
+            promotion,
+            ..Self::new(s[..2].parse()?, s[2..4].parse()?)
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::piece::Kind;
 
     #[test]
     fn parse_notation_len2() {
@@ -58,6 +211,7 @@ mod tests {
                 ambiguitie: None,
                 file: File(6),
                 rank: Rank(4),
+                ..Default::default()
             })
         );
     }
@@ -71,6 +225,7 @@ mod tests {
                 ambiguitie: None,
                 file: File(6),
                 rank: Rank(4),
+                ..Default::default()
             })
         );
     }
@@ -84,6 +239,7 @@ mod tests {
                 ambiguitie: Some(Square('8')),
                 file: File(6),
                 rank: Rank(4),
+                ..Default::default()
             })
         );
 
@@ -94,7 +250,69 @@ mod tests {
                 ambiguitie: Some(Square('h')),
                 file: File(6),
                 rank: Rank(4),
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn parse_notation_capture() {
+        assert_eq!(
+            "exd5".parse::<Notation>(),
+            Ok(Notation {
+                piece: Kind::Pawn,
+                ambiguitie: Some(Square('e')),
+                file: File(3),
+                rank: Rank(4),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            "Nxf3+".parse::<Notation>(),
+            Ok(Notation {
+                piece: Kind::Knight,
+                file: File(5),
+                rank: Rank(2),
+                ..Default::default()
             })
         );
     }
+
+    #[test]
+    fn parse_notation_promotion_and_castle() {
+        let promotion = "exd8=Q#".parse::<Notation>().unwrap();
+        assert_eq!(promotion.promotion, Some(Kind::Queen));
+        assert_eq!(promotion.destination(), "d8".parse().unwrap());
+        assert_eq!(
+            "e8N".parse::<Notation>().unwrap().promotion,
+            Some(Kind::Knight)
+        );
+        assert!("Qe8=Q".parse::<Notation>().is_err());
+
+        assert_eq!(
+            "O-O".parse::<Notation>().unwrap().castle,
+            Some(CastleSide::King)
+        );
+        assert_eq!(
+            "0-0-0".parse::<Notation>().unwrap().castle,
+            Some(CastleSide::Queen)
+        );
+    }
+
+    #[test]
+    fn parse_move() {
+        let mv = "e7e8q".parse::<Move>().unwrap();
+        assert_eq!(mv.from, "e7".parse().unwrap());
+        assert_eq!(mv.to, "e8".parse().unwrap());
+        assert_eq!(mv.promotion, Some(Kind::Queen));
+        assert_eq!(mv.to_string(), "e7e8q");
+        assert!("e7e8k".parse::<Move>().is_err());
+        assert!("e2".parse::<Move>().is_err());
+
+        let drop = "N@f3".parse::<Move>().unwrap();
+        assert_eq!(drop, Move::drop(Kind::Knight, "f3".parse().unwrap()));
+        assert_eq!(drop.to_string(), "N@f3");
+        assert_eq!("@e4".parse::<Move>().unwrap().to_string(), "P@e4");
+        assert!("K@e4".parse::<Move>().is_err());
+    }
 }
@@ -0,0 +1,796 @@
+use super::{
+    movement::{CastleSide, Move, Notation},
+    square::Coord,
+    variant::{side, Pocket, Variant},
+    Board,
+};
+use crate::piece::{Color, Kind, Piece};
+
+/// Which castling moves are still allowed, and the file of the rook each one
+/// uses. In standard chess that is always the a- or h-file; Chess960 rooks
+/// can start anywhere on the back rank.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Castling([Option<u8>; 4]);
+
+impl Castling {
+    pub const NONE: Self = Self([None; 4]);
+    pub const ALL: Self = Self([Some(7), Some(0), Some(7), Some(0)]);
+
+    const fn slot(color: Color, side: CastleSide) -> usize {
+        match (color, side) {
+            (Color::White, CastleSide::King) => 0,
+            (Color::White, CastleSide::Queen) => 1,
+            (Color::Black, CastleSide::King) => 2,
+            (Color::Black, CastleSide::Queen) => 3,
+        }
+    }
+
+    #[must_use]
+    pub const fn has(self, color: Color, side: CastleSide) -> bool {
+        self.rook(color, side).is_some()
+    }
+
+    /// File of the rook that castles on `side`, while the right remains.
+    #[must_use]
+    pub const fn rook(self, color: Color, side: CastleSide) -> Option<u8> {
+        self.0[Self::slot(color, side)]
+    }
+
+    pub const fn set(&mut self, color: Color, side: CastleSide, rook: Option<u8>) {
+        self.0[Self::slot(color, side)] = rook;
+    }
+}
+
+/// Everything needed to continue a game from a given point: the pieces plus
+/// the state FEN records alongside them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Position {
+    pub board: Board,
+    pub turn: Color,
+    pub castling: Castling,
+    pub en_passant: Option<Coord>,
+    pub halfmove_clock: u16,
+    pub fullmove: u16,
+    pub variant: Variant,
+    /// Checks given by White and Black, for Three-check.
+    pub checks: [u8; 2],
+    /// Crazyhouse pieces in hand for White and Black.
+    pub pockets: [Pocket; 2],
+    /// Crazyhouse squares holding promoted pawns, by `Coord::index`; they go
+    /// back to the pocket as pawns when taken.
+    pub promoted: u64,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self {
+            board: Board::default(),
+            turn: Color::White,
+            castling: Castling::ALL,
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove: 1,
+            variant: Variant::Standard,
+            checks: [0; 2],
+            pockets: [Pocket::default(); 2],
+            promoted: 0,
+        }
+    }
+}
+
+impl Position {
+    /// Parses a FEN string. The move counters may be left off, and setups
+    /// no game could reach are refused.
+    pub fn from_fen(fen: &str) -> Result<Self, String> {
+        Self::parse_fen(fen)?.checked()
+    }
+
+    /// Hands the position back if it passes [`Position::validate`], or else
+    /// every problem found.
+    pub fn checked(self) -> Result<Self, String> {
+        let problems = self.validate();
+        if problems.is_empty() {
+            return Ok(self);
+        }
+        let problems = problems.iter().map(ToString::to_string).collect::<Vec<_>>();
+        Err(problems.join("; "))
+    }
+
+    /// Reads a FEN string as it stands, legal or not, for callers that
+    /// still have to set the variant before checking it.
+    pub fn parse_fen(fen: &str) -> Result<Self, String> {
+        let mut fields = fen.split_whitespace().collect::<Vec<_>>();
+        // Three-check FENs give the checks each side still needs, like `3+2`.
+        let remaining_checks = if fields.get(4).is_some_and(|field| field.contains('+')) {
+            Some(fields.remove(4))
+        } else {
+            None
+        };
+        if !(4..=6).contains(&fields.len()) {
+            return Err(format!("FEN needs 4 to 6 fields, found {}", fields.len()));
+        }
+
+        // Crazyhouse FENs add the pockets after the placement, like `[Nq]`.
+        let (placement, pocket) = match fields[0].split_once('[') {
+            Some((placement, pocket)) => (
+                placement,
+                Some(
+                    pocket
+                        .strip_suffix(']')
+                        .ok_or_else(|| format!("Unclosed pocket in '{}'", fields[0]))?,
+                ),
+            ),
+            None => (fields[0], None),
+        };
+        let mut board = Board([None; 64]);
+        let mut promoted = 0;
+        let ranks = placement.split('/').collect::<Vec<_>>();
+        if ranks.len() != 8 {
+            return Err(format!("FEN needs 8 ranks, found {}", ranks.len()));
+        }
+        for (row, pieces) in ranks.iter().enumerate() {
+            let mut column = 0;
+            for c in pieces.chars() {
+                if let Some(empty) = c.to_digit(10) {
+                    column += empty as usize;
+                } else if c == '~' {
+                    // Marks the piece before it as a promoted pawn.
+                    if (1..=8).contains(&column) {
+                        promoted |= 1u64 << (row * 8 + column - 1);
+                    }
+                } else {
+                    let piece = Piece::from_fen_char(c)
+                        .ok_or_else(|| format!("Unknown piece '{c}' in FEN"))?;
+                    if column < 8 {
+                        board.0[row * 8 + column] = Some(piece);
+                    }
+                    column += 1;
+                }
+            }
+            if column != 8 {
+                return Err(format!("FEN rank '{pieces}' does not have 8 squares"));
+            }
+        }
+
+        let turn = match fields[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            t => return Err(format!("Unknown side to move '{t}'")),
+        };
+        let castling = parse_castling(fields[2], &board)?;
+        let en_passant = match fields[3] {
+            "-" => None,
+            square => Some(square.parse()?),
+        };
+        let counter = |index: usize, default: u16| {
+            fields.get(index).map_or(Ok(default), |n| {
+                n.parse::<u16>()
+                    .map_err(|_| format!("Invalid move counter '{n}'"))
+            })
+        };
+        let variant = if pocket.is_some() {
+            Variant::Crazyhouse
+        } else if remaining_checks.is_some() {
+            Variant::ThreeCheck
+        } else {
+            Variant::Standard
+        };
+
+        Ok(Self {
+            board,
+            turn,
+            castling,
+            en_passant,
+            halfmove_clock: counter(4, 0)?,
+            fullmove: counter(5, 1)?,
+            variant,
+            checks: remaining_checks.map_or(Ok([0; 2]), parse_checks)?,
+            pockets: parse_pocket(pocket.unwrap_or_default())?,
+            promoted,
+        })
+    }
+
+    /// FEN, with the pockets or check counts of Crazyhouse and Three-check.
+    #[must_use]
+    pub fn fen(&self) -> String {
+        let mut placement = String::new();
+        for (row, pieces) in self.board.0.chunks(8).enumerate() {
+            if row > 0 {
+                placement.push('/');
+            }
+            let mut empty = 0;
+            for (column, square) in pieces.iter().enumerate() {
+                match square {
+                    Some(piece) => {
+                        if empty > 0 {
+                            placement.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        placement.push(piece.fen_char());
+                        if self.promoted & 1 << (row * 8 + column) != 0 {
+                            placement.push('~');
+                        }
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                placement.push_str(&empty.to_string());
+            }
+        }
+        if self.variant == Variant::Crazyhouse {
+            placement = format!(
+                "{placement}[{}{}]",
+                self.pockets[0].letters(Color::White),
+                self.pockets[1].letters(Color::Black)
+            );
+        }
+
+        let turn = match self.turn {
+            Color::White => 'w',
+            Color::Black => 'b',
+        };
+        let mut en_passant = self
+            .en_passant
+            .map_or_else(|| "-".to_string(), |c| c.to_string());
+        if self.variant == Variant::ThreeCheck {
+            en_passant = format!(
+                "{en_passant} {}+{}",
+                3u8.saturating_sub(self.checks[0]),
+                3u8.saturating_sub(self.checks[1])
+            );
+        }
+        format!(
+            "{placement} {turn} {} {en_passant} {} {}",
+            self.castling_field(),
+            self.halfmove_clock,
+            self.fullmove
+        )
+    }
+
+    /// The FEN castling field: `KQkq` where that is unambiguous, otherwise
+    /// the rook's file as in X-FEN.
+    fn castling_field(&self) -> String {
+        let mut field = String::new();
+        for color in [Color::White, Color::Black] {
+            for side in [CastleSide::King, CastleSide::Queen] {
+                let Some(file) = self.castling.rook(color, side) else {
+                    continue;
+                };
+                let c = if outer_rook(&self.board, color, side) == Some(file) {
+                    match side {
+                        CastleSide::King => 'K',
+                        CastleSide::Queen => 'Q',
+                    }
+                } else {
+                    char::from(b'A' + file)
+                };
+                field.push(match color {
+                    Color::White => c,
+                    Color::Black => c.to_ascii_lowercase(),
+                });
+            }
+        }
+        if field.is_empty() {
+            field.push('-');
+        }
+        field
+    }
+
+    /// Whether castling here follows Chess960 rules: some right has its king
+    /// or rook off the standard squares.
+    #[must_use]
+    pub fn is_chess960(&self) -> bool {
+        [Color::White, Color::Black].into_iter().any(|color| {
+            [CastleSide::King, CastleSide::Queen]
+                .into_iter()
+                .any(|side| self.castling.has(color, side) && !self.standard_castle(color, side))
+        })
+    }
+
+    fn standard_castle(&self, color: Color, side: CastleSide) -> bool {
+        let rook = match side {
+            CastleSide::King => 7,
+            CastleSide::Queen => 0,
+        };
+        self.castling.rook(color, side) == Some(rook)
+            && self.king(color) == Some(Coord::new(4, color.home_rank()))
+    }
+
+    /// The move that castles on `side`, if the right remains. Standard
+    /// castling is the king moving two files; Chess960 castling is written
+    /// as the king taking its own rook, since the king may move one file or
+    /// none at all.
+    #[must_use]
+    pub fn castle_move(&self, color: Color, side: CastleSide) -> Option<Move> {
+        let rook = self.castling.rook(color, side)?;
+        let rank = color.home_rank();
+        let king = self.king(color).filter(|king| king.rank.0 == rank)?;
+        let to = if self.standard_castle(color, side) {
+            castle_files(side).0
+        } else {
+            rook
+        };
+        Some(Move::new(king, Coord::new(to, rank)))
+    }
+
+    /// The castling side if `mv` is a castling move.
+    #[must_use]
+    pub fn castle_side(&self, mv: Move) -> Option<CastleSide> {
+        let piece = self.piece_at(mv.from)?;
+        if piece.kind() != Kind::King || mv.promotion.is_some() {
+            return None;
+        }
+        [CastleSide::King, CastleSide::Queen]
+            .into_iter()
+            .find(|&side| self.castle_move(piece.color(), side) == Some(mv))
+    }
+
+    #[must_use]
+    pub const fn piece_at(&self, coord: Coord) -> Option<Piece> {
+        self.board.0[coord.index()]
+    }
+
+    /// Applies a move without checking that it is legal.
+    pub fn make_move(&mut self, mv: Move) {
+        let color = self.turn;
+        if let Some(kind) = mv.drop {
+            if !self.pockets[side(color)].take(kind) {
+                return;
+            }
+            self.board.0[mv.to.index()] = Some(Piece::new(color, kind));
+            self.halfmove_clock += 1;
+            self.en_passant = None;
+        } else {
+            let Some(piece) = self.piece_at(mv.from) else {
+                return;
+            };
+            self.move_piece(piece, mv);
+        }
+
+        if color == Color::Black {
+            self.fullmove += 1;
+        }
+        self.turn.flip();
+        if self.variant == Variant::ThreeCheck && self.in_check() {
+            self.checks[side(color)] += 1;
+        }
+    }
+
+    fn move_piece(&mut self, piece: Piece, mv: Move) {
+        let color = piece.color();
+        let from_promoted = self.promoted & 1 << mv.from.index() != 0;
+        let castle = self.castle_side(mv);
+        // A Chess960 castle lands the king on its own rook; that is no capture.
+        let captured = self.piece_at(mv.to).filter(|_| castle.is_none());
+
+        self.halfmove_clock += 1;
+        if piece.kind() == Kind::Pawn || captured.is_some() {
+            self.halfmove_clock = 0;
+        }
+
+        let taken =
+            if piece.kind() == Kind::Pawn && Some(mv.to) == self.en_passant && captured.is_none() {
+                let square = Coord {
+                    file: mv.to.file,
+                    rank: mv.from.rank,
+                };
+                self.board.0[square.index()].take()
+            } else {
+                captured
+            };
+
+        let castle = castle.and_then(|side| Some((side, self.castling.rook(color, side)?)));
+        self.board.0[mv.from.index()] = None;
+        match castle {
+            Some((side, rook_file)) => {
+                let rank = mv.from.rank.0;
+                let (king_to, rook_to) = castle_files(side);
+                let rook = self.board.0[Coord::new(rook_file, rank).index()].take();
+                self.board.0[Coord::new(rook_to, rank).index()] = rook;
+                self.board.0[Coord::new(king_to, rank).index()] = Some(piece);
+            }
+            None => {
+                self.board.0[mv.to.index()] =
+                    Some(mv.promotion.map_or(piece, |kind| Piece::new(color, kind)));
+            }
+        }
+
+        if piece.kind() == Kind::King {
+            self.castling.set(color, CastleSide::King, None);
+            self.castling.set(color, CastleSide::Queen, None);
+        }
+        for corner in [mv.from, mv.to] {
+            for side_color in [Color::White, Color::Black] {
+                if corner.rank.0 != side_color.home_rank() {
+                    continue;
+                }
+                for side in [CastleSide::King, CastleSide::Queen] {
+                    if self.castling.rook(side_color, side) == Some(corner.file.0) {
+                        self.castling.set(side_color, side, None);
+                    }
+                }
+            }
+        }
+
+        self.en_passant = None;
+        if piece.kind() == Kind::Pawn && mv.from.rank.0.abs_diff(mv.to.rank.0) == 2 {
+            self.en_passant = mv.from.offset(0, color.forward());
+        }
+        self.after_variant_move(mv, taken, from_promoted);
+    }
+
+    /// Returns the position after `mv`, leaving `self` untouched.
+    #[must_use]
+    pub fn after(&self, mv: Move) -> Self {
+        let mut next = self.clone();
+        next.make_move(mv);
+        next
+    }
+
+    #[must_use]
+    pub fn is_capture(&self, mv: Move) -> bool {
+        (self.piece_at(mv.to).is_some() && self.castle_side(mv).is_none())
+            || (Some(mv.to) == self.en_passant
+                && self.piece_at(mv.from).map(Piece::kind) == Some(Kind::Pawn)
+                && mv.from.file != mv.to.file)
+    }
+
+    /// Parses a move in either algebraic (`Nf3`) or coordinate (`g1f3`) form
+    /// and checks it is legal here.
+    pub fn parse_move(&self, text: &str) -> Result<Move, String> {
+        let text = text.trim();
+        let legal = self.legal_moves();
+        if let Ok(mv) = text.trim_end_matches(['+', '#', '!', '?']).parse::<Move>() {
+            if legal.contains(&mv) {
+                return Ok(mv);
+            }
+        }
+
+        let notation = text.parse::<Notation>()?;
+        let mut candidates = legal.into_iter().filter(|&mv| self.matches(&notation, mv));
+        match (candidates.next(), candidates.next()) {
+            (Some(mv), None) => Ok(mv),
+            (Some(_), Some(_)) => Err(format!("Ambiguous move '{text}'")),
+            (None, _) => Err(format!("Illegal move '{text}'")),
+        }
+    }
+
+    fn matches(&self, notation: &Notation, mv: Move) -> bool {
+        let Some(piece) = self.piece_at(mv.from) else {
+            return false;
+        };
+        if let Some(side) = notation.castle {
+            return self.castle_side(mv) == Some(side);
+        }
+        if piece.kind() != notation.piece
+            || mv.to != notation.destination()
+            || self.castle_side(mv).is_some()
+        {
+            return false;
+        }
+        let promotion = match (mv.promotion, notation.promotion) {
+            (Some(_), None) => Some(Kind::Queen),
+            (_, promotion) => promotion,
+        };
+        if mv.promotion != promotion {
+            return false;
+        }
+        notation.ambiguitie.is_none_or(|square| {
+            if square.is_file() {
+                mv.from.file.to_char() == *square
+            } else {
+                square.is_rank() && mv.from.rank.to_char() == *square
+            }
+        })
+    }
+
+    /// Parses and plays a move, returning it on success.
+    pub fn play(&mut self, text: &str) -> Result<Move, String> {
+        let mv = self.parse_move(text)?;
+        self.make_move(mv);
+        Ok(mv)
+    }
+
+    /// Standard algebraic notation for a legal move, e.g. `Nbd7+`.
+    #[must_use]
+    pub fn san(&self, mv: Move) -> String {
+        let mut san = match (self.piece_at(mv.from), self.castle_side(mv)) {
+            (_, Some(CastleSide::King)) => "O-O".to_string(),
+            (_, Some(CastleSide::Queen)) => "O-O-O".to_string(),
+            (Some(piece), None) if mv.drop.is_none() => self.san_body(piece, mv),
+            _ => mv.to_string(),
+        };
+
+        let next = self.after(mv);
+        if next.in_check() {
+            san.push(if next.legal_moves().is_empty() {
+                '#'
+            } else {
+                '+'
+            });
+        }
+        san
+    }
+
+    fn san_body(&self, piece: Piece, mv: Move) -> String {
+        let mut san = String::new();
+        let capture = self.is_capture(mv);
+        if let Some(letter) = piece.kind().letter() {
+            san.push(letter);
+            let rivals = self
+                .legal_moves()
+                .into_iter()
+                .filter(|other| {
+                    other.to == mv.to
+                        && other.from != mv.from
+                        && self.piece_at(other.from) == Some(piece)
+                })
+                .collect::<Vec<_>>();
+            if !rivals.is_empty() {
+                if rivals.iter().all(|other| other.from.file != mv.from.file) {
+                    san.push(mv.from.file.to_char());
+                } else if rivals.iter().all(|other| other.from.rank != mv.from.rank) {
+                    san.push(mv.from.rank.to_char());
+                } else {
+                    san.push_str(&mv.from.to_string());
+                }
+            }
+        } else if capture {
+            san.push(mv.from.file.to_char());
+        }
+        if capture {
+            san.push('x');
+        }
+        san.push_str(&mv.to.to_string());
+        if let Some(letter) = mv.promotion.and_then(Kind::letter) {
+            san.push('=');
+            san.push(letter);
+        }
+        san
+    }
+
+    #[must_use]
+    pub fn is_checkmate(&self) -> bool {
+        self.in_check() && self.legal_moves().is_empty()
+    }
+
+    #[must_use]
+    pub fn is_stalemate(&self) -> bool {
+        !self.in_check() && self.legal_moves().is_empty()
+    }
+
+    /// Neither side has enough material left to ever give mate.
+    #[must_use]
+    pub fn insufficient_material(&self) -> bool {
+        // Variants win in other ways, or bring pieces back.
+        if self.variant != Variant::Standard {
+            return false;
+        }
+        let mut minors = Vec::new();
+        for (coord, piece) in self.board.pieces() {
+            match piece.kind() {
+                Kind::King => {}
+                Kind::Knight | Kind::Bishop => minors.push((coord, piece.kind())),
+                _ => return false,
+            }
+        }
+        match minors.as_slice() {
+            [] | [_] => true,
+            bishops if bishops.iter().all(|(_, kind)| *kind == Kind::Bishop) => {
+                let shade = |c: &Coord| (c.file.0 + c.rank.0) % 2;
+                bishops
+                    .iter()
+                    .all(|(c, _)| shade(c) == shade(&bishops[0].0))
+            }
+            _ => false,
+        }
+    }
+
+    /// The same position for repetition purposes, ignoring the move counters.
+    /// In Crazyhouse a promoted piece goes back to the pocket as a pawn when
+    /// taken, so it differs from a piece that was there all along.
+    #[must_use]
+    pub fn same_as(&self, other: &Self) -> bool {
+        self.board == other.board
+            && self.turn == other.turn
+            && self.castling == other.castling
+            && self.en_passant == other.en_passant
+            && self.checks == other.checks
+            && self.pockets == other.pockets
+            && (self.variant != Variant::Crazyhouse || self.promoted == other.promoted)
+    }
+}
+
+/// Files the king and rook end up on after castling on `side`, which are the
+/// same in standard chess and Chess960.
+pub const fn castle_files(side: CastleSide) -> (u8, u8) {
+    match side {
+        CastleSide::King => (6, 5),
+        CastleSide::Queen => (2, 3),
+    }
+}
+
+/// The rook furthest out on `side` of the king on the home rank, which is
+/// the one a plain `K` or `Q` castling field refers to.
+fn outer_rook(board: &Board, color: Color, side: CastleSide) -> Option<u8> {
+    let rank = color.home_rank();
+    let rook = Piece::new(color, Kind::Rook);
+    let king = (0..8).find(|&file| {
+        board.0[Coord::new(file, rank).index()] == Some(Piece::new(color, Kind::King))
+    })?;
+    let mut files = (0..8).filter(|&file| board.0[Coord::new(file, rank).index()] == Some(rook));
+    match side {
+        CastleSide::King => files.filter(|&file| file > king).max(),
+        CastleSide::Queen => files.find(|&file| file < king),
+    }
+}
+
+/// Reads Crazyhouse pocket letters such as `NPp`.
+fn parse_pocket(letters: &str) -> Result<[Pocket; 2], String> {
+    let mut pockets = [Pocket::default(); 2];
+    for c in letters.chars() {
+        let piece =
+            Piece::from_fen_char(c).ok_or_else(|| format!("Unknown piece '{c}' in pocket"))?;
+        pockets[side(piece.color())].add(piece.kind());
+    }
+    Ok(pockets)
+}
+
+/// Turns the Three-check field of checks still needed, like `3+2`, into the
+/// checks each side has given.
+fn parse_checks(field: &str) -> Result<[u8; 2], String> {
+    let invalid = || format!("Invalid check count '{field}'");
+    let (white, black) = field.split_once('+').ok_or_else(invalid)?;
+    let given = |n: &str| {
+        n.parse::<u8>()
+            .ok()
+            .filter(|&n| n <= 3)
+            .map(|n| 3 - n)
+            .ok_or_else(invalid)
+    };
+    Ok([given(white)?, given(black)?])
+}
+
+/// Reads a FEN castling field: `KQkq`, X-FEN, or Shredder-FEN's rook files.
+fn parse_castling(field: &str, board: &Board) -> Result<Castling, String> {
+    let mut castling = Castling::NONE;
+    if field == "-" {
+        return Ok(castling);
+    }
+    let invalid = || format!("Invalid castling field '{field}'");
+    for c in field.chars() {
+        let color = if c.is_ascii_uppercase() {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let rank = color.home_rank();
+        let (side, file) = match c.to_ascii_uppercase() {
+            'K' => (CastleSide::King, outer_rook(board, color, CastleSide::King)),
+            'Q' => (
+                CastleSide::Queen,
+                outer_rook(board, color, CastleSide::Queen),
+            ),
+            letter @ 'A'..='H' => {
+                let file = letter as u8 - b'A';
+                let king = (0..8)
+                    .find(|&f| {
+                        board.0[Coord::new(f, rank).index()] == Some(Piece::new(color, Kind::King))
+                    })
+                    .ok_or_else(invalid)?;
+                let side = if file > king {
+                    CastleSide::King
+                } else {
+                    CastleSide::Queen
+                };
+                let rook =
+                    board.0[Coord::new(file, rank).index()] == Some(Piece::new(color, Kind::Rook));
+                (side, Some(file).filter(|_| rook))
+            }
+            _ => return Err(invalid()),
+        };
+        // A right without its rook in place is dropped, as most readers do.
+        castling.set(color, side, file);
+    }
+    Ok(castling)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    fn play_all(position: &mut Position, moves: &[&str]) {
+        for mv in moves {
+            position.play(mv).unwrap();
+        }
+    }
+
+    #[test]
+    fn fen_round_trip() {
+        let position = Position::from_fen(START_FEN).unwrap();
+        assert_eq!(position, Position::default());
+        assert_eq!(position.fen(), START_FEN);
+
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        assert_eq!(Position::from_fen(fen).unwrap().fen(), fen);
+        assert!(Position::from_fen("8/8/8 w - -").is_err());
+        assert!(
+            Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq -").is_err()
+        );
+    }
+
+    #[test]
+    fn play_and_san() {
+        let mut position = Position::default();
+        play_all(
+            &mut position,
+            &["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Bxc6", "dxc6", "O-O"],
+        );
+        assert_eq!(
+            position.fen(),
+            "r1bqkbnr/1pp2ppp/p1p5/4p3/4P3/5N2/PPPP1PPP/RNBQ1RK1 b kq - 1 5"
+        );
+        assert!(position.play("Ke2").is_err());
+        assert!(position.play("e4").is_err());
+    }
+
+    #[test]
+    fn en_passant_and_promotion() {
+        let mut position = Position::from_fen("4k3/1P6/8/8/3p4/8/4P3/4K3 w - - 0 1").unwrap();
+        play_all(&mut position, &["e4"]);
+        assert_eq!(position.en_passant, Some("e3".parse().unwrap()));
+        let mv = position.parse_move("dxe3").unwrap();
+        assert_eq!(position.san(mv), "dxe3");
+        position.make_move(mv);
+        assert_eq!(position.piece_at("e4".parse().unwrap()), None);
+
+        let mv = position.parse_move("b8=N").unwrap();
+        assert_eq!(mv.promotion, Some(Kind::Knight));
+        assert_eq!(position.san(position.parse_move("b8").unwrap()), "b8=Q+");
+    }
+
+    #[test]
+    fn disambiguation() {
+        let position = Position::from_fen("4k3/8/8/8/8/8/8/RN2K2R w - - 0 1").unwrap();
+        let mv = position.parse_move("Rf1").unwrap();
+        assert_eq!(position.san(mv), "Rf1");
+        assert!(position.parse_move("Rd1").is_err());
+        let position = Position::from_fen("3k4/8/8/8/8/8/4K3/R6R w - - 0 1").unwrap();
+        assert!(position
+            .parse_move("Rd1")
+            .unwrap_err()
+            .starts_with("Ambiguous"));
+        assert_eq!(position.san(position.parse_move("Rad1").unwrap()), "Rad1+");
+        let position = Position::from_fen("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(position.san(position.parse_move("R1a3").unwrap()), "R1a3");
+    }
+
+    #[test]
+    fn mate_and_draws() {
+        let mut position = Position::default();
+        play_all(&mut position, &["f3", "e5", "g4", "Qh4#"]);
+        assert!(position.is_checkmate());
+
+        let stalemate = Position::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert!(stalemate.is_stalemate());
+
+        assert!(Position::from_fen("8/8/4k3/8/8/2B5/4K3/8 w - - 0 1")
+            .unwrap()
+            .insufficient_material());
+        assert!(!Position::from_fen("8/8/4k3/8/8/2R5/4K3/8 w - - 0 1")
+            .unwrap()
+            .insufficient_material());
+    }
+
+    #[test]
+    fn repetition_tells_promoted_pieces_apart() {
+        let promoted = Position::from_fen("Q~3k3/8/8/8/8/8/8/4K3[] b - - 0 1").unwrap();
+        let queen = Position::from_fen("Q3k3/8/8/8/8/8/8/4K3[] b - - 0 1").unwrap();
+        assert_eq!(promoted.variant, Variant::Crazyhouse);
+        assert!(promoted.same_as(&promoted.clone()));
+        assert!(!promoted.same_as(&queen));
+    }
+}
@@ -1,12 +1,14 @@
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Square(pub(crate) char);
 
 impl Square {
-    fn is_rank(&self) -> bool {
+    #[must_use]
+    pub fn is_rank(self) -> bool {
         self.0.is_numeric()
     }
 
-    fn is_file(&self) -> bool {
+    #[must_use]
+    pub fn is_file(self) -> bool {
         self.0.is_alphabetic()
     }
 }
@@ -29,8 +31,16 @@ impl std::ops::Deref for Square {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct File(pub(crate) u8);
+
+impl File {
+    #[must_use]
+    pub const fn to_char(self) -> char {
+        (b'a' + self.0) as char
+    }
+}
+
 impl TryFrom<char> for File {
     type Error = String;
 
@@ -49,8 +59,16 @@ impl TryFrom<char> for File {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Rank(pub(crate) u8);
+
+impl Rank {
+    #[must_use]
+    pub const fn to_char(self) -> char {
+        (b'1' + self.0) as char
+    }
+}
+
 impl TryFrom<char> for Rank {
     type Error = String;
 
@@ -66,6 +84,67 @@ impl TryFrom<char> for Rank {
     }
 }
 
+/// A single square on the board, e.g. `e4`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Coord {
+    pub file: File,
+    pub rank: Rank,
+}
+
+impl Coord {
+    #[must_use]
+    pub const fn new(file: u8, rank: u8) -> Self {
+        Self {
+            file: File(file),
+            rank: Rank(rank),
+        }
+    }
+
+    /// Index into `Board`, which stores rank 8 first.
+    #[must_use]
+    pub const fn index(self) -> usize {
+        (7 - self.rank.0 as usize) * 8 + self.file.0 as usize
+    }
+
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub const fn from_index(index: usize) -> Self {
+        Self::new((index % 8) as u8, 7 - (index / 8) as u8)
+    }
+
+    /// The square `df` files and `dr` ranks away, if it is still on the board.
+    #[must_use]
+    pub const fn offset(self, df: i8, dr: i8) -> Option<Self> {
+        let file = self.file.0.cast_signed() + df;
+        let rank = self.rank.0.cast_signed() + dr;
+        if file < 0 || file > 7 || rank < 0 || rank > 7 {
+            return None;
+        }
+        Some(Self::new(file.cast_unsigned(), rank.cast_unsigned()))
+    }
+}
+
+impl std::fmt::Display for Coord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.file.to_char(), self.rank.to_char())
+    }
+}
+
+impl std::str::FromStr for Coord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some(file), Some(rank), None) => Ok(Self {
+                file: file.try_into()?,
+                rank: rank.try_into()?,
+            }),
+            _ => Err(format!("Could not parse '{s}' into a square.")),
+        }
+    }
+}
+
 // impl TryFrom<char> for Square {
 //     type Error = String;
 
@@ -114,6 +193,17 @@ mod tests {
         assert!(Rank::try_from('a').is_err());
     }
 
+    #[test]
+    fn coord_index() {
+        assert_eq!("a8".parse::<Coord>().unwrap().index(), 0);
+        assert_eq!("h1".parse::<Coord>().unwrap().index(), 63);
+        assert_eq!("e4".parse::<Coord>().unwrap().index(), 36);
+        assert_eq!(Coord::from_index(36).to_string(), "e4");
+        assert_eq!(Coord::new(0, 0).offset(-1, 0), None);
+        assert_eq!(Coord::new(0, 0).offset(1, 1), Some(Coord::new(1, 1)));
+        assert!("e9".parse::<Coord>().is_err());
+    }
+
     #[test]
     fn sqaure() {
         assert_eq!(Square::try_from('a'), Ok(Square('a')));
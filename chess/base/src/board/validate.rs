@@ -0,0 +1,347 @@
+//! Checks that a setup could come up in a game. A `Board` holds any 64
+//! squares, so positions typed in as FEN or put together in the editor are
+//! run through here before they are played from.
+
+use super::{
+    movegen::{BISHOP_DIRECTIONS, KING_STEPS, KNIGHT_STEPS, ROOK_DIRECTIONS},
+    movement::CastleSide,
+    position::Position,
+    square::Coord,
+    variant::Variant,
+};
+use crate::piece::{Color, Kind};
+use std::fmt::Display;
+
+/// Something about a position that no game could lead to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Problem {
+    /// A side with no king or more than one.
+    KingCount(Color, usize),
+    KingsTouch,
+    PawnOnBackRank(Coord),
+    TooManyPawns(Color, usize),
+    TooManyPieces(Color, usize),
+    /// More queens, rooks, bishops or knights than pawns could have become.
+    TooManyPromoted(Color),
+    /// The side that just moved left its king in check.
+    OpponentInCheck,
+    TooManyCheckers(usize),
+    /// Two pieces give check, though no single move could reveal both.
+    ImpossibleDoubleCheck,
+    /// A castling right without the king and rook in place for it.
+    CastlingRight(Color, CastleSide),
+    /// An en passant square with no pawn that just moved two squares past it.
+    EnPassant(Coord),
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::KingCount(color, 0) => write!(f, "{color} has no king"),
+            Self::KingCount(color, n) => write!(f, "{color} has {n} kings"),
+            Self::KingsTouch => write!(f, "The kings stand next to each other"),
+            Self::PawnOnBackRank(coord) => write!(f, "There is a pawn on {coord}"),
+            Self::TooManyPawns(color, n) => write!(f, "{color} has {n} pawns"),
+            Self::TooManyPieces(color, n) => write!(f, "{color} has {n} pieces"),
+            Self::TooManyPromoted(color) => {
+                write!(f, "{color} has more promoted pieces than missing pawns")
+            }
+            Self::OpponentInCheck => write!(f, "The side not to move is in check"),
+            Self::TooManyCheckers(n) => write!(f, "The king is in check from {n} pieces"),
+            Self::ImpossibleDoubleCheck => write!(f, "No move could give this double check"),
+            Self::CastlingRight(color, side) => {
+                let side = match side {
+                    CastleSide::King => "kingside",
+                    CastleSide::Queen => "queenside",
+                };
+                write!(
+                    f,
+                    "{color} may castle {side} without the king and rook for it"
+                )
+            }
+            Self::EnPassant(coord) => write!(
+                f,
+                "En passant on {coord} does not follow a pawn's double step"
+            ),
+        }
+    }
+}
+
+/// How many of each piece a side starts with, beyond which the rest must
+/// have been promoted.
+const START: [(Kind, usize); 4] = [
+    (Kind::Knight, 2),
+    (Kind::Bishop, 2),
+    (Kind::Rook, 2),
+    (Kind::Queen, 1),
+];
+
+impl Position {
+    /// Everything wrong with the setup, or nothing if it could be reached.
+    #[must_use]
+    pub fn validate(&self) -> Vec<Problem> {
+        let mut problems = Vec::new();
+        for color in [Color::White, Color::Black] {
+            let kings = self.count(color, Kind::King);
+            if kings != 1 {
+                problems.push(Problem::KingCount(color, kings));
+            }
+            // Crazyhouse drops bring back pieces of any kind.
+            if self.variant != Variant::Crazyhouse {
+                self.check_material(color, &mut problems);
+            }
+        }
+        for (coord, piece) in self.board.pieces() {
+            if piece.kind() == Kind::Pawn && matches!(coord.rank.0, 0 | 7) {
+                problems.push(Problem::PawnOnBackRank(coord));
+            }
+        }
+        if problems
+            .iter()
+            .all(|problem| !matches!(problem, Problem::KingCount(..)))
+        {
+            self.check_kings(&mut problems);
+        }
+        self.check_castling(&mut problems);
+        self.check_en_passant(&mut problems);
+        problems
+    }
+
+    fn count(&self, color: Color, kind: Kind) -> usize {
+        self.board
+            .pieces()
+            .filter(|(_, piece)| piece.color() == color && piece.kind() == kind)
+            .count()
+    }
+
+    fn check_material(&self, color: Color, problems: &mut Vec<Problem>) {
+        let pieces = self
+            .board
+            .pieces()
+            .filter(|(_, piece)| piece.color() == color)
+            .count();
+        let pawns = self.count(color, Kind::Pawn);
+        let promoted = START
+            .iter()
+            .map(|&(kind, start)| self.count(color, kind).saturating_sub(start))
+            .sum::<usize>();
+        if pieces > 16 {
+            problems.push(Problem::TooManyPieces(color, pieces));
+        } else if pawns > 8 {
+            problems.push(Problem::TooManyPawns(color, pawns));
+        } else if pawns + promoted > 8 {
+            problems.push(Problem::TooManyPromoted(color));
+        }
+    }
+
+    /// Checks the kings' squares and the checks on them, once each side is
+    /// known to have exactly one.
+    fn check_kings(&self, problems: &mut Vec<Problem>) {
+        // Atomic kings may stand side by side, and then neither is in check.
+        if self.variant == Variant::Atomic && self.kings_touch() {
+            return;
+        }
+        let (Some(king), Some(other)) = (self.king(self.turn), self.king(self.turn.opposite()))
+        else {
+            return;
+        };
+        if king.file.0.abs_diff(other.file.0) <= 1 && king.rank.0.abs_diff(other.rank.0) <= 1 {
+            problems.push(Problem::KingsTouch);
+            return;
+        }
+        if self.is_attacked(other, self.turn) {
+            problems.push(Problem::OpponentInCheck);
+        }
+        let checkers = self
+            .board
+            .pieces()
+            .filter(|(from, piece)| {
+                piece.color() != self.turn && self.attacks(*from, piece.kind(), king)
+            })
+            .map(|(_, piece)| piece.kind())
+            .collect::<Vec<_>>();
+        let slides = |kind: &Kind| matches!(kind, Kind::Bishop | Kind::Rook | Kind::Queen);
+        match checkers.len() {
+            0 | 1 => {}
+            2 if checkers.iter().any(slides) => {}
+            2 => problems.push(Problem::ImpossibleDoubleCheck),
+            n => problems.push(Problem::TooManyCheckers(n)),
+        }
+    }
+
+    /// Whether the `kind` on `from` attacks `target`, with the pieces in
+    /// between in the way.
+    fn attacks(&self, from: Coord, kind: Kind, target: Coord) -> bool {
+        let steps = |steps: &[(i8, i8)]| {
+            steps
+                .iter()
+                .any(|&(df, dr)| from.offset(df, dr) == Some(target))
+        };
+        let slides = |directions: &[(i8, i8)]| {
+            directions.iter().any(|&(df, dr)| {
+                let mut square = from.offset(df, dr);
+                while let Some(coord) = square {
+                    if coord == target {
+                        return true;
+                    }
+                    if self.piece_at(coord).is_some() {
+                        return false;
+                    }
+                    square = coord.offset(df, dr);
+                }
+                false
+            })
+        };
+        match kind {
+            Kind::Pawn => {
+                let forward = self
+                    .piece_at(from)
+                    .map_or(0, |piece| piece.color().forward());
+                steps(&[(-1, forward), (1, forward)])
+            }
+            Kind::Knight => steps(&KNIGHT_STEPS),
+            Kind::King => steps(&KING_STEPS),
+            Kind::Bishop => slides(&BISHOP_DIRECTIONS),
+            Kind::Rook => slides(&ROOK_DIRECTIONS),
+            Kind::Queen => slides(&BISHOP_DIRECTIONS) || slides(&ROOK_DIRECTIONS),
+        }
+    }
+
+    fn check_castling(&self, problems: &mut Vec<Problem>) {
+        for color in [Color::White, Color::Black] {
+            let rank = color.home_rank();
+            let king = self.king(color).filter(|king| king.rank.0 == rank);
+            for side in [CastleSide::King, CastleSide::Queen] {
+                let Some(file) = self.castling.rook(color, side) else {
+                    continue;
+                };
+                let rook = self
+                    .piece_at(Coord::new(file, rank))
+                    .is_some_and(|piece| piece.color() == color && piece.kind() == Kind::Rook);
+                let placed = king.is_some_and(|king| match side {
+                    CastleSide::King => file > king.file.0,
+                    CastleSide::Queen => file < king.file.0,
+                });
+                if !(rook && placed) {
+                    problems.push(Problem::CastlingRight(color, side));
+                }
+            }
+        }
+    }
+
+    fn check_en_passant(&self, problems: &mut Vec<Problem>) {
+        let Some(square) = self.en_passant else {
+            return;
+        };
+        // The pawn that moved belongs to the side not to move.
+        let mover = self.turn.opposite();
+        let rank = match mover {
+            Color::White => 2,
+            Color::Black => 5,
+        };
+        let pawn = square
+            .offset(0, mover.forward())
+            .and_then(|coord| self.piece_at(coord))
+            .is_some_and(|piece| piece.color() == mover && piece.kind() == Kind::Pawn);
+        let start_empty = square
+            .offset(0, -mover.forward())
+            .is_some_and(|coord| self.piece_at(coord).is_none());
+        if square.rank.0 != rank || !pawn || !start_empty || self.piece_at(square).is_some() {
+            problems.push(Problem::EnPassant(square));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::position::Castling;
+
+    fn problems(fen: &str) -> Vec<Problem> {
+        Position::parse_fen(fen).unwrap().validate()
+    }
+
+    #[test]
+    fn accepts_real_positions() {
+        assert_eq!(Position::default().validate(), []);
+        let mut position = Position::default();
+        for san in ["e4", "c5", "e5", "d5"] {
+            position.play(san).unwrap();
+        }
+        assert_eq!(position.validate(), []);
+        // Double check by a knight and a rook, and a promoted third queen.
+        assert_eq!(problems("4k3/8/3N4/8/8/8/8/4R1K1 b - -"), []);
+        assert_eq!(problems("4k3/8/8/8/8/8/QQQ5/4K3 w - -"), []);
+        assert_eq!(problems("4k3/8/8/8/8/8/8/4R1K1 b - -"), []);
+    }
+
+    #[test]
+    fn finds_the_problems() {
+        assert_eq!(
+            problems("8/8/8/8/8/8/8/4K3 w - -"),
+            [Problem::KingCount(Color::Black, 0)]
+        );
+        assert_eq!(
+            problems("k1k5/8/8/8/8/8/8/4K3 w - -"),
+            [Problem::KingCount(Color::Black, 2)]
+        );
+        assert_eq!(
+            problems("4k3/8/8/8/8/8/8/P3K3 w - -"),
+            [Problem::PawnOnBackRank("a1".parse().unwrap())]
+        );
+        assert_eq!(
+            problems("4k3/8/8/8/8/PPPPPPPP/PPPPPPPP/4K3 w - -"),
+            [Problem::TooManyPieces(Color::White, 17)]
+        );
+        assert_eq!(
+            problems("4k3/8/8/8/2P5/PPPPPPPP/8/4K3 w - -"),
+            [Problem::TooManyPawns(Color::White, 9)]
+        );
+        assert_eq!(
+            problems("4k3/8/8/8/8/QQ6/PPPPPPPP/4K3 w - -"),
+            [Problem::TooManyPromoted(Color::White)]
+        );
+        assert_eq!(
+            problems("4k3/8/8/8/8/8/8/4R1K1 w - -"),
+            [Problem::OpponentInCheck]
+        );
+        assert_eq!(problems("8/8/8/8/8/3k4/4K3/8 w - -"), [Problem::KingsTouch]);
+        assert_eq!(
+            problems("4k3/5P2/3N4/8/8/8/8/6K1 b - -"),
+            [Problem::ImpossibleDoubleCheck]
+        );
+        assert_eq!(
+            problems("4k3/3P4/3N1N2/8/8/8/8/6K1 b - -"),
+            [Problem::TooManyCheckers(3)]
+        );
+        // Reading FEN drops rights without their rook, so set them here.
+        let castling = Position {
+            castling: Castling::ALL,
+            ..Position::parse_fen("4k3/8/8/8/8/8/8/R3K3 w - -").unwrap()
+        };
+        assert_eq!(
+            castling.validate(),
+            [
+                Problem::CastlingRight(Color::White, CastleSide::King),
+                Problem::CastlingRight(Color::Black, CastleSide::King),
+                Problem::CastlingRight(Color::Black, CastleSide::Queen)
+            ]
+        );
+        assert_eq!(problems("4k3/8/8/8/4P3/8/8/4K3 b - e3"), []);
+        assert_eq!(
+            problems("4k3/8/8/8/4P3/8/8/4K3 w - e3"),
+            [Problem::EnPassant("e3".parse().unwrap())]
+        );
+        assert_eq!(
+            problems("4k3/8/8/8/8/8/8/4K3 b - e3"),
+            [Problem::EnPassant("e3".parse().unwrap())]
+        );
+    }
+
+    #[test]
+    fn fen_import_rejects_illegal_setups() {
+        let error = Position::from_fen("4k3/8/8/8/8/8/8/P3K2k w - - 0 1").unwrap_err();
+        assert_eq!(error, "Black has 2 kings; There is a pawn on a1");
+        assert!(Position::from_fen("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1").is_err());
+    }
+}
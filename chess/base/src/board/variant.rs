@@ -0,0 +1,380 @@
+use super::{
+    movegen::KING_STEPS,
+    movement::{CastleSide, Move},
+    position::Position,
+    square::Coord,
+};
+use crate::piece::{Color, Kind, Piece};
+use std::{fmt::Display, str::FromStr};
+
+/// The rule set a position is played under. Chess960 is not one of these:
+/// it only changes the setup and castling, which every variant handles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Variant {
+    #[default]
+    Standard,
+    /// Giving a third check wins.
+    ThreeCheck,
+    /// Bringing the king to d4, e4, d5 or e5 wins.
+    KingOfTheHill,
+    /// Captures blow up the capturing piece and every non-pawn next to it.
+    Atomic,
+    /// Captured pieces change sides and can be dropped back on the board.
+    Crazyhouse,
+}
+
+impl Variant {
+    /// Name as written in a PGN `Variant` tag.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Standard => "Standard",
+            Self::ThreeCheck => "Three-check",
+            Self::KingOfTheHill => "King of the Hill",
+            Self::Atomic => "Atomic",
+            Self::Crazyhouse => "Crazyhouse",
+        }
+    }
+}
+
+impl Display for Variant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Variant {
+    type Err = String;
+
+    /// Accepts the PGN names and the usual short forms, ignoring case,
+    /// spaces and dashes.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let key = s
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '_'))
+            .collect::<String>()
+            .to_lowercase();
+        match key.as_str() {
+            "standard" | "chess" => Ok(Self::Standard),
+            "threecheck" | "3check" => Ok(Self::ThreeCheck),
+            "kingofthehill" | "koth" => Ok(Self::KingOfTheHill),
+            "atomic" => Ok(Self::Atomic),
+            "crazyhouse" | "zh" => Ok(Self::Crazyhouse),
+            _ => Err(format!("Unknown variant '{s}'")),
+        }
+    }
+}
+
+/// How a variant's own win condition was met.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariantEnd {
+    ThreeChecks,
+    KingOfTheHill,
+    Explosion,
+}
+
+impl Display for VariantEnd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ThreeChecks => write!(f, "Third check"),
+            Self::KingOfTheHill => write!(f, "King of the hill"),
+            Self::Explosion => write!(f, "King exploded"),
+        }
+    }
+}
+
+/// Crazyhouse pieces in hand, counted by kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Pocket([u8; 5]);
+
+impl Pocket {
+    const KINDS: [Kind; 5] = [
+        Kind::Pawn,
+        Kind::Knight,
+        Kind::Bishop,
+        Kind::Rook,
+        Kind::Queen,
+    ];
+
+    fn slot(kind: Kind) -> Option<usize> {
+        Self::KINDS.iter().position(|&k| k == kind)
+    }
+
+    #[must_use]
+    pub fn count(self, kind: Kind) -> u8 {
+        Self::slot(kind).map_or(0, |slot| self.0[slot])
+    }
+
+    pub fn add(&mut self, kind: Kind) {
+        if let Some(slot) = Self::slot(kind) {
+            self.0[slot] = self.0[slot].saturating_add(1);
+        }
+    }
+
+    /// Takes one `kind` out, returning whether there was one.
+    pub fn take(&mut self, kind: Kind) -> bool {
+        match Self::slot(kind) {
+            Some(slot) if self.0[slot] > 0 => {
+                self.0[slot] -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Every kind held at least once.
+    pub fn kinds(self) -> impl Iterator<Item = Kind> {
+        Self::KINDS
+            .into_iter()
+            .filter(move |&kind| self.count(kind) > 0)
+    }
+
+    /// The pocket in FEN style, one letter per piece, e.g. `NPP`.
+    #[must_use]
+    pub fn letters(self, color: Color) -> String {
+        let mut letters = String::new();
+        for kind in Self::KINDS.into_iter().rev() {
+            for _ in 0..self.count(kind) {
+                letters.push(Piece::new(color, kind).fen_char());
+            }
+        }
+        letters
+    }
+}
+
+/// Slot of `color` in per-side arrays such as `Position::pockets`.
+#[must_use]
+pub const fn side(color: Color) -> usize {
+    match color {
+        Color::White => 0,
+        Color::Black => 1,
+    }
+}
+
+const HILL: [(u8, u8); 4] = [(3, 3), (4, 3), (3, 4), (4, 4)];
+
+impl Position {
+    /// The winner if the variant's own goal has been reached.
+    #[must_use]
+    pub fn variant_end(&self) -> Option<(Color, VariantEnd)> {
+        let colors = [Color::White, Color::Black];
+        match self.variant {
+            Variant::Standard | Variant::Crazyhouse => None,
+            Variant::ThreeCheck => colors
+                .into_iter()
+                .find(|&color| self.checks[side(color)] >= 3)
+                .map(|color| (color, VariantEnd::ThreeChecks)),
+            Variant::KingOfTheHill => colors
+                .into_iter()
+                .find(|&color| {
+                    self.king(color)
+                        .is_some_and(|king| HILL.contains(&(king.file.0, king.rank.0)))
+                })
+                .map(|color| (color, VariantEnd::KingOfTheHill)),
+            Variant::Atomic => colors
+                .into_iter()
+                .find(|&color| self.king(color).is_none())
+                .map(|color| (color.opposite(), VariantEnd::Explosion)),
+        }
+    }
+
+    /// Whether the kings stand next to each other, which in Atomic means
+    /// neither can be in check: taking the other king would blow up your own.
+    pub(super) fn kings_touch(&self) -> bool {
+        self.variant == Variant::Atomic
+            && self
+                .king(Color::White)
+                .zip(self.king(Color::Black))
+                .is_some_and(|(a, b)| {
+                    a.file.0.abs_diff(b.file.0) <= 1 && a.rank.0.abs_diff(b.rank.0) <= 1
+                })
+    }
+
+    /// Variant rules on top of the ordinary check test, for the side that
+    /// just moved into `next`.
+    pub(super) fn is_safe(&self, mv: Move, next: &Self) -> bool {
+        let own = next.king(self.turn);
+        if self.variant != Variant::Atomic {
+            return own.is_none_or(|king| !next.is_attacked(king, next.turn));
+        }
+        // Kings never capture in Atomic, and blowing up the enemy king wins
+        // even if yours is left in check.
+        if self.piece_at(mv.from).map(Piece::kind) == Some(Kind::King) && self.is_capture(mv) {
+            return false;
+        }
+        match (own, next.king(next.turn)) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(king), Some(_)) => next.kings_touch() || !next.is_attacked(king, next.turn),
+        }
+    }
+
+    /// Crazyhouse drops: any pocket piece onto an empty square, but pawns not
+    /// on the first or last rank.
+    pub(super) fn drop_moves(&self, moves: &mut Vec<Move>) {
+        if self.variant != Variant::Crazyhouse {
+            return;
+        }
+        let pocket = self.pockets[side(self.turn)];
+        for kind in pocket.kinds() {
+            for index in 0..64 {
+                let to = Coord::from_index(index);
+                if self.piece_at(to).is_some() || (kind == Kind::Pawn && matches!(to.rank.0, 0 | 7))
+                {
+                    continue;
+                }
+                moves.push(Move::drop(kind, to));
+            }
+        }
+    }
+
+    /// Variant bookkeeping after the board has been updated for `mv`.
+    /// `captured` is what was taken and `from_promoted` whether the moving
+    /// piece was a promoted pawn, both as they were before the move.
+    pub(super) fn after_variant_move(
+        &mut self,
+        mv: Move,
+        captured: Option<Piece>,
+        from_promoted: bool,
+    ) {
+        let mover = self.turn;
+        match self.variant {
+            Variant::Atomic if captured.is_some() => {
+                self.explode(mv.to);
+            }
+            Variant::Crazyhouse => {
+                let to = 1u64 << mv.to.index();
+                if let Some(piece) = captured {
+                    let kind = if self.promoted & to != 0 {
+                        Kind::Pawn
+                    } else {
+                        piece.kind()
+                    };
+                    self.pockets[side(mover)].add(kind);
+                }
+                self.promoted &= !(to | 1 << mv.from.index());
+                if from_promoted || mv.promotion.is_some() {
+                    self.promoted |= to;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Removes the piece on `at` and every piece but a pawn around it.
+    fn explode(&mut self, at: Coord) {
+        self.board.0[at.index()] = None;
+        for &(df, dr) in &KING_STEPS {
+            if let Some(coord) = at.offset(df, dr) {
+                if self.piece_at(coord).is_some_and(|p| p.kind() != Kind::Pawn) {
+                    self.board.0[coord.index()] = None;
+                }
+            }
+        }
+        // Rights go with any rook or king caught in the blast.
+        for color in [Color::White, Color::Black] {
+            for castle in [CastleSide::King, CastleSide::Queen] {
+                let home = |file| Coord::new(file, color.home_rank());
+                let rook_gone = self.castling.rook(color, castle).is_some_and(|file| {
+                    self.piece_at(home(file)) != Some(Piece::new(color, Kind::Rook))
+                });
+                if rook_gone || self.king(color).is_none() {
+                    self.castling.set(color, castle, None);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup(fen: &str, variant: Variant) -> Position {
+        Position {
+            variant,
+            ..Position::parse_fen(fen).unwrap()
+        }
+        .checked()
+        .unwrap()
+    }
+
+    #[test]
+    fn three_check() {
+        let mut position = Position::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 1+3 0 1").unwrap();
+        assert_eq!(position.variant, Variant::ThreeCheck);
+        assert_eq!(position.checks, [2, 0]);
+        position.play("Ra8+").unwrap();
+        assert_eq!(
+            position.variant_end(),
+            Some((Color::White, VariantEnd::ThreeChecks))
+        );
+        assert!(position.legal_moves().is_empty());
+        assert_eq!(position.fen(), "R3k3/8/8/8/8/8/8/4K3 b - - 0+3 1 1");
+    }
+
+    #[test]
+    fn king_of_the_hill() {
+        let mut position = setup("4k3/8/8/8/8/4K3/8/8 w - - 0 1", Variant::KingOfTheHill);
+        assert_eq!(position.variant_end(), None);
+        position.play("Kd4").unwrap();
+        assert_eq!(
+            position.variant_end(),
+            Some((Color::White, VariantEnd::KingOfTheHill))
+        );
+        assert!(!position.insufficient_material());
+    }
+
+    #[test]
+    fn atomic() {
+        // Taking next to the king blows it up, pawns survive the blast.
+        let mut position = setup("4k3/4pp2/8/8/8/8/8/4KQ2 w - - 0 1", Variant::Atomic);
+        position.play("Qxf7").unwrap();
+        assert_eq!(position.fen(), "8/4p3/8/8/8/8/8/4K3 b - - 0 1");
+        assert_eq!(
+            position.variant_end(),
+            Some((Color::White, VariantEnd::Explosion))
+        );
+
+        // Kings never capture, and touching kings cannot give check.
+        let position = setup("8/8/8/8/8/8/3pk3/4K3 w - - 0 1", Variant::Atomic);
+        assert!(position.parse_move("Kxd2").is_err());
+        assert!(!position.in_check());
+        assert!(position.parse_move("Kf2").is_ok());
+
+        // Nor may a capture blow up your own king.
+        let position = setup("4k3/8/8/8/8/8/3r4/3QK3 w - - 0 1", Variant::Atomic);
+        assert!(position.parse_move("Qxd2").is_err());
+    }
+
+    #[test]
+    fn crazyhouse() {
+        let mut position = setup(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            Variant::Crazyhouse,
+        );
+        for mv in ["e4", "d5", "exd5", "Qxd5", "Nc3", "Qa5"] {
+            position.play(mv).unwrap();
+        }
+        assert_eq!(position.pockets[0].count(Kind::Pawn), 1);
+        let drop = position.parse_move("P@d7+").unwrap();
+        assert_eq!(position.san(drop), "P@d7+");
+        assert!(position.parse_move("P@d8").is_err());
+        assert!(position.parse_move("N@e4").is_err());
+        position.make_move(drop);
+        assert_eq!(
+            position.fen(),
+            "rnb1kbnr/pppPpppp/8/q7/8/2N5/PPPP1PPP/R1BQKBNR[p] b KQkq - 3 4"
+        );
+        assert_eq!(Position::from_fen(&position.fen()).unwrap(), position);
+
+        // A promoted pawn goes back to the pocket as a pawn.
+        let mut position = Position::from_fen("r3k3/1P6/8/8/8/8/8/4K3[] w - - 0 1").unwrap();
+        position.play("bxa8=Q+").unwrap();
+        assert_eq!(position.fen(), "Q~3k3/8/8/8/8/8/8/4K3[R] b - - 0 1");
+        position.board.0["c7".parse::<Coord>().unwrap().index()] =
+            Some(Piece::new(Color::Black, Kind::Knight));
+        position.play("Nxa8").unwrap();
+        assert_eq!(position.pockets[1].count(Kind::Pawn), 1);
+    }
+}
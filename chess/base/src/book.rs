@@ -0,0 +1,283 @@
+use crate::{
+    board::{
+        movement::{CastleSide, Move},
+        position::Position,
+        square::Coord,
+    },
+    pgn::PgnGame,
+    piece::{Color, Kind, Piece},
+    rng::Rng,
+};
+use std::collections::HashMap;
+
+/// One 16 byte record of a Polyglot `.bin` book, stored big-endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookEntry {
+    pub key: u64,
+    pub mv: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+/// A Polyglot opening book.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Book {
+    /// Sorted by key, as the format requires.
+    entries: Vec<BookEntry>,
+    /// Last full move number the book is consulted for.
+    pub depth: u16,
+}
+
+impl Default for Book {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+            depth: 20,
+        }
+    }
+}
+
+impl Book {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("Could not read {path}: {e}"))?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if !bytes.len().is_multiple_of(16) {
+            return Err("Polyglot book size is not a multiple of 16 bytes".to_string());
+        }
+        let mut entries = bytes
+            .chunks_exact(16)
+            .map(|chunk| BookEntry {
+                key: u64::from_be_bytes(chunk[0..8].try_into().unwrap()),
+                mv: u16::from_be_bytes(chunk[8..10].try_into().unwrap()),
+                weight: u16::from_be_bytes(chunk[10..12].try_into().unwrap()),
+                learn: u32::from_be_bytes(chunk[12..16].try_into().unwrap()),
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|entry| entry.key);
+        Ok(Self {
+            entries,
+            ..Default::default()
+        })
+    }
+
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.entries.len() * 16);
+        for entry in &self.entries {
+            bytes.extend(entry.key.to_be_bytes());
+            bytes.extend(entry.mv.to_be_bytes());
+            bytes.extend(entry.weight.to_be_bytes());
+            bytes.extend(entry.learn.to_be_bytes());
+        }
+        bytes
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_bytes()).map_err(|e| format!("Could not write {path}: {e}"))
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Legal book moves for `position` with their weights, heaviest first.
+    #[must_use]
+    pub fn moves(&self, position: &Position) -> Vec<(Move, u16)> {
+        let key = position.hash();
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let legal = position.legal_moves();
+        let mut moves = self.entries[start..]
+            .iter()
+            .take_while(|entry| entry.key == key)
+            .filter_map(|entry| {
+                decode_move(position, entry.mv)
+                    .filter(|mv| legal.contains(mv))
+                    .map(|mv| (mv, entry.weight))
+            })
+            .collect::<Vec<_>>();
+        moves.sort_by_key(|&(_, weight)| std::cmp::Reverse(weight));
+        moves
+    }
+
+    /// Picks a book move at random in proportion to its weight, as long as
+    /// the game is still within the book depth.
+    pub fn pick(&self, position: &Position, rng: &mut Rng) -> Option<Move> {
+        if position.fullmove > self.depth {
+            return None;
+        }
+        let moves = self.moves(position);
+        let total = moves.iter().map(|&(_, w)| u64::from(w)).sum::<u64>();
+        if total == 0 {
+            return None;
+        }
+        let mut roll = rng.below(total);
+        for (mv, weight) in moves {
+            if roll < u64::from(weight) {
+                return Some(mv);
+            }
+            roll -= u64::from(weight);
+        }
+        None
+    }
+
+    /// Builds a book from the first `plies` half moves of each game. Moves are
+    /// weighted the way Polyglot does: two points for a win, one for a draw.
+    pub fn from_pgn(games: &[PgnGame], plies: usize) -> Result<Self, String> {
+        let mut scores = HashMap::<(u64, u16), u32>::new();
+        for (i, game) in games.iter().enumerate() {
+            let (mut position, moves) =
+                game.replay().map_err(|e| format!("game {}: {e}", i + 1))?;
+            for mv in moves.into_iter().take(plies) {
+                let points = match (game.result.as_str(), position.turn) {
+                    ("1-0", Color::White) | ("0-1", Color::Black) => 2,
+                    ("1/2-1/2", _) => 1,
+                    _ => 0,
+                };
+                if points > 0 {
+                    *scores
+                        .entry((position.hash(), encode_move(&position, mv)))
+                        .or_default() += points;
+                }
+                position.make_move(mv);
+            }
+        }
+
+        let mut heaviest = HashMap::<u64, u32>::new();
+        for (&(key, _), &score) in &scores {
+            let max = heaviest.entry(key).or_default();
+            *max = (*max).max(score);
+        }
+        let mut entries = scores
+            .into_iter()
+            .map(|((key, mv), score)| {
+                // Scale each position's moves so the best one fits in 16 bits.
+                let max = u64::from(heaviest[&key].max(u32::from(u16::MAX)));
+                let weight = u64::from(score) * u64::from(u16::MAX) / max;
+                BookEntry {
+                    key,
+                    mv,
+                    weight: u16::try_from(weight).unwrap_or(u16::MAX).max(1),
+                    learn: 0,
+                }
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|entry| (entry.key, std::cmp::Reverse(entry.weight), entry.mv));
+        Ok(Self {
+            entries,
+            ..Default::default()
+        })
+    }
+}
+
+/// Polyglot move encoding. Castling is written as the king taking its rook.
+#[must_use]
+pub fn encode_move(position: &Position, mv: Move) -> u16 {
+    let color = position.turn;
+    let to_file = position
+        .castle_side(mv)
+        .and_then(|side| position.castling.rook(color, side))
+        .unwrap_or(mv.to.file.0);
+    let promotion = match mv.promotion {
+        Some(Kind::Knight) => 1,
+        Some(Kind::Bishop) => 2,
+        Some(Kind::Rook) => 3,
+        Some(Kind::Queen) => 4,
+        _ => 0,
+    };
+    u16::from(to_file)
+        | u16::from(mv.to.rank.0) << 3
+        | u16::from(mv.from.file.0) << 6
+        | u16::from(mv.from.rank.0) << 9
+        | promotion << 12
+}
+
+#[must_use]
+pub fn decode_move(position: &Position, code: u16) -> Option<Move> {
+    #[allow(clippy::cast_possible_truncation)]
+    let field = |shift: u16| ((code >> shift) & 0b111) as u8;
+    let from = Coord::new(field(6), field(9));
+    let to = Coord::new(field(0), field(3));
+    let promotion = match field(12) {
+        0 => None,
+        1 => Some(Kind::Knight),
+        2 => Some(Kind::Bishop),
+        3 => Some(Kind::Rook),
+        4 => Some(Kind::Queen),
+        _ => return None,
+    };
+
+    let color = position.turn;
+    let is_king = position.piece_at(from) == Some(Piece::new(color, Kind::King));
+    if is_king && position.piece_at(to) == Some(Piece::new(color, Kind::Rook)) {
+        return [CastleSide::King, CastleSide::Queen]
+            .into_iter()
+            .find(|&side| position.castling.rook(color, side) == Some(to.file.0))
+            .and_then(|side| position.castle_move(color, side));
+    }
+    Some(Move {
+        promotion,
+        ..Move::new(from, to)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAMES: &str = "
+[Result \"1-0\"]
+1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. O-O 1-0
+
+[Result \"1/2-1/2\"]
+1. e4 c5 1/2-1/2
+
+[Result \"0-1\"]
+1. d4 d5 0-1
+";
+
+    #[test]
+    fn castle_encoding() {
+        let position = Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let castle = position.parse_move("O-O").unwrap();
+        let code = encode_move(&position, castle);
+        assert_eq!(code, 0b000_000_100_000_111);
+        assert_eq!(decode_move(&position, code), Some(castle));
+
+        let promote = "a7a8q".parse::<Move>().unwrap();
+        assert_eq!(
+            decode_move(&position, encode_move(&position, promote)),
+            Some(promote)
+        );
+    }
+
+    #[test]
+    fn build_and_read_book() {
+        let games = PgnGame::parse_all(GAMES).unwrap();
+        let book = Book::from_pgn(&games, 8).unwrap();
+        let book = Book::from_bytes(&book.to_bytes()).unwrap();
+
+        let start = Position::default();
+        let moves = book.moves(&start);
+        assert_eq!(moves.len(), 1, "d4 only lost, so it is not in the book");
+        assert_eq!(moves[0].0, "e2e4".parse().unwrap());
+
+        let after_e4 = start.after(moves[0].0);
+        let replies = book.moves(&after_e4);
+        assert_eq!(replies.len(), 1, "only the drawn c5 scored for Black");
+
+        let mut rng = Rng::new(7);
+        assert_eq!(book.pick(&start, &mut rng), Some(moves[0].0));
+        let castle = Position::from_fen(
+            "r1bqk1nr/pppp1ppp/2n5/2b1p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+        )
+        .unwrap();
+        assert_eq!(book.pick(&castle, &mut rng), Some("e1g1".parse().unwrap()));
+
+        let shallow = Book { depth: 3, ..book };
+        assert_eq!(shallow.pick(&castle, &mut rng), None);
+    }
+}
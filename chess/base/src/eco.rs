@@ -0,0 +1,153 @@
+//! Names openings by their ECO code. The table pairs each code and name
+//! with the moves that reach it, and positions are matched rather than
+//! moves, so an opening is recognised however it was reached.
+
+use crate::board::{movement::Move, position::Position};
+use std::{collections::HashMap, sync::OnceLock};
+
+/// Code, name and moves of each opening, one per line and tab separated.
+const TABLE: &str = include_str!("eco.tsv");
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Opening {
+    pub code: &'static str,
+    pub name: &'static str,
+    /// The line that defines it, in SAN with move numbers.
+    pub moves: &'static str,
+    plies: usize,
+}
+
+/// The openings keyed by the position their line ends in.
+#[derive(Debug, Default)]
+pub struct Eco {
+    openings: HashMap<u64, Opening>,
+}
+
+impl Eco {
+    /// Reads a table in the bundled layout. Where two lines reach the same
+    /// position the first is kept.
+    pub fn parse(table: &'static str) -> Result<Self, String> {
+        let mut eco = Self::default();
+        for (number, line) in table.lines().enumerate() {
+            let mut fields = line.split('\t');
+            let (Some(code), Some(name), Some(moves), None) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                return Err(format!(
+                    "Line {}: expected code, name and moves",
+                    number + 1
+                ));
+            };
+            let mut position = Position::default();
+            let mut plies = 0;
+            for san in moves.split_whitespace().filter(|san| !san.ends_with('.')) {
+                position
+                    .play(san)
+                    .map_err(|e| format!("Line {}: {san}: {e}", number + 1))?;
+                plies += 1;
+            }
+            eco.openings.entry(position.hash()).or_insert(Opening {
+                code,
+                name,
+                moves,
+                plies,
+            });
+        }
+        Ok(eco)
+    }
+
+    /// The opening whose line ends in `position`.
+    #[must_use]
+    pub fn classify(&self, position: &Position) -> Option<&Opening> {
+        self.openings.get(&position.hash())
+    }
+
+    /// The deepest opening reached anywhere in `moves` played from the
+    /// standard `start`.
+    #[must_use]
+    pub fn classify_line(&self, start: &Position, moves: &[Move]) -> Option<&Opening> {
+        let mut position = start.clone();
+        let mut deepest = self.classify(&position);
+        for &mv in moves {
+            position.make_move(mv);
+            if let Some(opening) = self.classify(&position) {
+                if deepest.is_none_or(|deepest| opening.plies >= deepest.plies) {
+                    deepest = Some(opening);
+                }
+            }
+        }
+        deepest
+    }
+}
+
+/// The bundled table, read on first use.
+pub fn openings() -> &'static Eco {
+    static ECO: OnceLock<Eco> = OnceLock::new();
+    ECO.get_or_init(|| Eco::parse(TABLE).unwrap_or_else(|e| panic!("eco.tsv: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(moves: &str) -> Option<&'static str> {
+        let mut position = Position::default();
+        let line = moves
+            .split_whitespace()
+            .map(|san| position.play(san).unwrap())
+            .collect::<Vec<_>>();
+        openings()
+            .classify_line(&Position::default(), &line)
+            .map(|opening| opening.code)
+    }
+
+    #[test]
+    fn reads_the_table() {
+        assert_eq!(openings().openings.len(), TABLE.lines().count());
+        assert!(Eco::parse("C20\tKing's Pawn Game\t1. e4 e5 2. Ke3").is_err());
+        assert!(Eco::parse("C20\tKing's Pawn Game").is_err());
+    }
+
+    #[test]
+    fn covers_every_code() {
+        for letter in 'A'..='E' {
+            for number in 0..100 {
+                let code = format!("{letter}{number:02}");
+                assert!(
+                    openings()
+                        .openings
+                        .values()
+                        .any(|opening| opening.code == code),
+                    "{code} is missing"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn names_the_deepest_opening() {
+        assert_eq!(classify(""), None);
+        let mut najdorf = Position::default();
+        for san in [
+            "e4", "c5", "Nf3", "d6", "d4", "cxd4", "Nxd4", "Nf6", "Nc3", "a6",
+        ] {
+            najdorf.play(san).unwrap();
+        }
+        let opening = openings().classify(&najdorf).unwrap();
+        assert_eq!(opening.name, "Sicilian Defense: Najdorf Variation");
+        // Moves past the table keep the last opening reached.
+        assert_eq!(
+            classify("e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6 Be3"),
+            Some("B90")
+        );
+        assert_eq!(classify("e4 e5 Nf3 Nc6 Bc4 Nf6 a3 a6 h3 h6"), Some("C55"));
+    }
+
+    #[test]
+    fn recognises_transpositions() {
+        // The English reaches the Queen's Gambit Declined.
+        assert_eq!(classify("c4 e6 d4 d5"), Some("D30"));
+        // And a Zukertort the Queen's Indian.
+        assert_eq!(classify("Nf3 Nf6 c4 e6 d4 b6"), Some("E12"));
+    }
+}
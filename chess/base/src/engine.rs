@@ -0,0 +1,796 @@
+pub mod mate;
+#[cfg(feature = "nnue")]
+pub mod nnue;
+pub mod params;
+pub mod tt;
+
+use crate::{
+    bitbase::{Bitbases, Wdl},
+    board::{movement::Move, position::Position, square::Coord},
+    book::Book,
+    piece::Kind,
+    rng::Rng,
+};
+use params::Params;
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tt::{Bound, Entry, Table};
+
+/// Score for delivering mate; mates found sooner score higher.
+pub const MATE: i32 = 100_000;
+
+/// The moves to mate a score stands for: positive when the side it is for
+/// mates, negative when it is mated, and `None` short of mate. A mate
+/// partway through a move counts as the whole move.
+#[must_use]
+pub const fn mate_moves(score: i32) -> Option<i32> {
+    if score >= MATE - 1000 {
+        Some((MATE - score + 1) / 2)
+    } else if score <= 1000 - MATE {
+        Some(-((MATE + score + 1) / 2))
+    } else {
+        None
+    }
+}
+
+/// Deepest iteration a search will go to when only time limits it.
+pub const MAX_DEPTH: u32 = 64;
+
+/// Score for a bitbase win, well clear of material but below any mate.
+pub const KNOWN_WIN: i32 = 20_000;
+
+/// Score for a position the bitbases have settled, or `None` if they have not.
+/// Wins are nudged towards mate: drive the bare king to the edge (to the
+/// bishop's corner in KBNK), bring the kings together and push pawns.
+#[must_use]
+pub fn evaluate_known(bitbases: &Bitbases, position: &Position) -> Option<i32> {
+    let wdl = bitbases.probe(position)?;
+    if wdl == Wdl::Draw {
+        return Some(0);
+    }
+    let strong = match wdl {
+        Wdl::Win => position.turn,
+        _ => position.turn.opposite(),
+    };
+    let weak_king = position.king(strong.opposite())?;
+    let strong_king = position.king(strong)?;
+    let distance = |a: Coord, b: Coord| {
+        i32::from(a.file.0.abs_diff(b.file.0).max(a.rank.0.abs_diff(b.rank.0)))
+    };
+    let edge = |c: Coord| {
+        let centre = |x: u8| i32::from(x.min(7 - x));
+        3 - centre(c.file.0).min(centre(c.rank.0))
+    };
+
+    let mut progress = 20 * edge(weak_king) - 10 * distance(strong_king, weak_king);
+    for (coord, piece) in position.board.pieces() {
+        match piece.kind() {
+            Kind::Bishop => {
+                // Mate happens in a corner the bishop covers.
+                let light = (coord.file.0 + coord.rank.0) % 2 == 1;
+                let corners = if light {
+                    [(0, 7), (7, 0)]
+                } else {
+                    [(0, 0), (7, 7)]
+                };
+                let near = corners
+                    .iter()
+                    .map(|&(f, r)| distance(weak_king, Coord::new(f, r)))
+                    .min()
+                    .unwrap_or(0);
+                progress -= 30 * near;
+            }
+            Kind::Pawn => {
+                let advance = (i32::from(coord.rank.0) - i32::from(strong.home_rank())).abs();
+                progress += 50 * advance;
+            }
+            _ => {}
+        }
+    }
+    let score = KNOWN_WIN + progress;
+    Some(if wdl == Wdl::Win { score } else { -score })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    pub best: Option<Move>,
+    pub score: i32,
+    /// The last iteration that finished.
+    pub depth: u32,
+    /// Nodes searched by every thread together.
+    pub nodes: u64,
+    pub time: Duration,
+    /// The best lines found, best first: one unless `Engine::multipv` asks
+    /// for more.
+    pub lines: Vec<Line>,
+}
+
+/// A line the engine expects to be played, and its score.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub score: i32,
+    pub pv: Vec<Move>,
+}
+
+impl SearchResult {
+    /// Nodes per second across all threads.
+    #[must_use]
+    pub fn nps(&self) -> u64 {
+        let micros = self.time.as_micros().max(1);
+        u64::try_from(u128::from(self.nodes) * 1_000_000 / micros).unwrap_or(u64::MAX)
+    }
+}
+
+/// Size of the transposition table unless set otherwise, in megabytes.
+pub const DEFAULT_HASH: usize = 16;
+/// Largest transposition table allowed, in megabytes.
+pub const MAX_HASH: usize = 65_536;
+/// Most search threads allowed.
+pub const MAX_THREADS: usize = 256;
+/// Most lines a search will report.
+pub const MAX_LINES: usize = 64;
+
+/// Ends a search from another thread, straight away or at a set time.
+#[derive(Debug, Default)]
+pub struct Stop {
+    now: AtomicBool,
+    at: Mutex<Option<Instant>>,
+}
+
+impl Stop {
+    pub fn stop(&self) {
+        self.now.store(true, Ordering::Relaxed);
+    }
+
+    /// Lets the search go on for `time` more.
+    pub fn stop_after(&self, time: Duration) {
+        *self.at.lock().unwrap() = Some(Instant::now() + time);
+    }
+
+    /// Lets the next search run, once the stopped one is over.
+    pub fn reset(&self) {
+        self.now.store(false, Ordering::Relaxed);
+        *self.at.lock().unwrap() = None;
+    }
+
+    #[must_use]
+    pub fn is_stopped(&self) -> bool {
+        self.now.load(Ordering::Relaxed)
+            || self
+                .at
+                .lock()
+                .unwrap()
+                .is_some_and(|at| Instant::now() >= at)
+    }
+}
+
+/// An alpha-beta searcher, optionally playing from an opening book and
+/// scoring small endings from bitbases. With more than one thread it runs
+/// Lazy SMP: every thread searches the same position and they share what
+/// they find through the transposition table.
+#[derive(Debug, Clone)]
+pub struct Engine {
+    pub depth: u32,
+    /// Stop deepening once this much time has gone, dropping the unfinished
+    /// iteration.
+    pub time: Option<Duration>,
+    pub book: Option<Book>,
+    pub bitbases: Option<Arc<Bitbases>>,
+    pub params: Params,
+    /// Evaluates in place of `params` where it can.
+    #[cfg(feature = "nnue")]
+    pub network: Option<Arc<nnue::Network>>,
+    /// Threads searching at once. Only the first one's result is played; the
+    /// others fill the table with results it can use.
+    pub threads: usize,
+    /// Size of the transposition table in megabytes, applied at the next
+    /// search.
+    pub hash: usize,
+    /// How many of the best moves to find a line and score for.
+    pub multipv: usize,
+    table: Table,
+    stop: Arc<Stop>,
+    rng: Rng,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new(4)
+    }
+}
+
+impl Engine {
+    #[must_use]
+    pub fn new(depth: u32) -> Self {
+        Self {
+            depth,
+            time: None,
+            book: None,
+            bitbases: None,
+            params: Params::default(),
+            #[cfg(feature = "nnue")]
+            network: nnue::default_network(),
+            threads: 1,
+            hash: DEFAULT_HASH,
+            multipv: 1,
+            table: Table::default(),
+            stop: Arc::default(),
+            rng: Rng::default(),
+        }
+    }
+
+    /// A weighted random move from the book, if one is loaded and has the position.
+    pub fn book_move(&mut self, position: &Position) -> Option<Move> {
+        self.book.as_ref()?.pick(position, &mut self.rng)
+    }
+
+    /// Ends the current search from another thread, keeping the last
+    /// finished iteration. Searches stay stopped until it is reset.
+    #[must_use]
+    pub fn stop_handle(&self) -> Arc<Stop> {
+        Arc::clone(&self.stop)
+    }
+
+    /// Forgets what earlier searches learned.
+    pub fn new_game(&self) {
+        self.table.clear();
+    }
+
+    /// Searches `position` with iterative deepening up to `self.depth`, or
+    /// until `self.time` runs out.
+    pub fn search(&mut self, position: &Position) -> SearchResult {
+        self.search_with(position, |_| {})
+    }
+
+    /// Like [`Engine::search`], handing `report` the result of every
+    /// iteration as it finishes.
+    pub fn search_with(
+        &mut self,
+        position: &Position,
+        report: impl FnMut(&SearchResult),
+    ) -> SearchResult {
+        let hash = self.hash.max(1);
+        if self.table.megabytes() != hash {
+            self.table.resize(hash);
+        }
+        let started = Instant::now();
+        let shared = Shared {
+            started,
+            deadline: self.time.map(|time| started + time),
+            halt: AtomicBool::new(false),
+            nodes: AtomicU64::new(0),
+        };
+        let engine = &*self;
+        let mut result = std::thread::scope(|scope| {
+            for id in 1..engine.threads.max(1) {
+                let shared = &shared;
+                scope.spawn(move || Worker::new(engine, shared, id).iterate(position, |_| {}));
+            }
+            let result = Worker::new(engine, &shared, 0).iterate(position, report);
+            shared.halt.store(true, Ordering::Relaxed);
+            result
+        });
+        result.nodes = shared.nodes.load(Ordering::Relaxed);
+        result.time = started.elapsed();
+        result
+    }
+
+    /// The line the table expects after `first`, at most `length` moves in all.
+    fn pv(&self, position: &Position, first: Move, length: u32) -> Vec<Move> {
+        let mut line = vec![first];
+        let mut position = position.after(first);
+        while line.len() < length as usize {
+            let Some(mv) = self.table.probe(tt::key(&position)).and_then(|e| e.best) else {
+                break;
+            };
+            if !position.legal_moves().contains(&mv) {
+                break;
+            }
+            position.make_move(mv);
+            line.push(mv);
+        }
+        line
+    }
+}
+
+/// What the threads of one search share besides the engine.
+#[derive(Debug)]
+struct Shared {
+    started: Instant,
+    deadline: Option<Instant>,
+    /// Raised when the main thread is done, to stop the helpers.
+    halt: AtomicBool,
+    nodes: AtomicU64,
+}
+
+/// One thread's search.
+struct Worker<'a> {
+    engine: &'a Engine,
+    shared: &'a Shared,
+    /// 0 for the main thread, whose result is the one used.
+    id: usize,
+    /// Nodes not yet added to the shared count.
+    nodes: u64,
+    stopped: bool,
+}
+
+impl<'a> Worker<'a> {
+    const fn new(engine: &'a Engine, shared: &'a Shared, id: usize) -> Self {
+        Self {
+            engine,
+            shared,
+            id,
+            nodes: 0,
+            stopped: false,
+        }
+    }
+
+    fn iterate(
+        &mut self,
+        position: &Position,
+        mut report: impl FnMut(&SearchResult),
+    ) -> SearchResult {
+        let key = tt::key(position);
+        let mut moves = position.legal_moves();
+        order_moves(position, &mut moves);
+        if let Some(best) = self.engine.table.probe(key).and_then(|entry| entry.best) {
+            move_to_front(&mut moves, best);
+        }
+        // Helpers take the moves in other orders and start at other depths,
+        // so they are not all working on the same subtree.
+        if !moves.is_empty() {
+            let len = moves.len();
+            moves.rotate_left(self.id % len);
+        }
+        let (first, last, lines) = if self.id == 0 {
+            (1, self.engine.depth.max(1), self.engine.multipv.max(1))
+        } else {
+            (1 + u32::from(self.id % 2 == 1), MAX_DEPTH, 1)
+        };
+
+        let mut result = SearchResult {
+            best: moves.first().copied(),
+            score: if position.in_check() { -MATE } else { 0 },
+            depth: 0,
+            nodes: 0,
+            time: Duration::ZERO,
+            lines: Vec::new(),
+        };
+        let eval = self.engine.root_eval(position);
+        for depth in first..=last {
+            // Each further line is the best move left once the ones
+            // already found are set aside.
+            let mut found: Vec<(Move, i32)> = Vec::new();
+            while found.len() < lines.min(moves.len()) {
+                let mut alpha = -MATE - 1;
+                let mut best = None;
+                for &mv in moves
+                    .iter()
+                    .filter(|&&mv| found.iter().all(|&(m, _)| m != mv))
+                {
+                    let next = position.after(mv);
+                    let next_eval = self.engine.eval_after(&eval, position, &next);
+                    let score = -self.negamax(&next, &next_eval, depth - 1, 1, -MATE - 1, -alpha);
+                    if self.stopped {
+                        break;
+                    }
+                    if score > alpha {
+                        alpha = score;
+                        best = Some(mv);
+                    }
+                }
+                match best {
+                    Some(mv) if !self.stopped => found.push((mv, alpha)),
+                    _ => break,
+                }
+            }
+            if self.stopped {
+                break;
+            }
+            result.depth = depth;
+            if let Some(&(mv, score)) = found.first() {
+                result.best = Some(mv);
+                result.score = score;
+                self.store(key, Some(mv), score, depth, 0, Bound::Exact);
+            }
+            // Search this iteration's lines first next time, best first.
+            for &(mv, _) in found.iter().rev() {
+                move_to_front(&mut moves, mv);
+            }
+            result.lines = found
+                .iter()
+                .map(|&(mv, score)| Line {
+                    score,
+                    pv: self.engine.pv(position, mv, depth),
+                })
+                .collect();
+            result.nodes = self.shared.nodes.load(Ordering::Relaxed) + self.nodes;
+            result.time = self.shared.started.elapsed();
+            report(&result);
+            if found.iter().all(|&(_, score)| score.abs() >= MATE - 1000) {
+                break;
+            }
+        }
+        self.shared.nodes.fetch_add(self.nodes, Ordering::Relaxed);
+        self.nodes = 0;
+        result
+    }
+
+    fn negamax(
+        &mut self,
+        position: &Position,
+        eval: &Eval,
+        depth: u32,
+        ply: i32,
+        mut alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        self.nodes += 1;
+        if self.out_of_time() {
+            return 0;
+        }
+        if let Some(score) = variant_score(position, ply) {
+            return score;
+        }
+        if position.halfmove_clock >= 100 || position.insufficient_material() {
+            return 0;
+        }
+        let mut moves = position.legal_moves();
+        if moves.is_empty() {
+            return if position.in_check() { ply - MATE } else { 0 };
+        }
+        if depth == 0 {
+            return self.quiesce(position, eval, ply, alpha, beta);
+        }
+
+        let key = tt::key(position);
+        let entry = self.engine.table.probe(key);
+        if let Some(entry) = entry.filter(|entry| u32::from(entry.depth) >= depth) {
+            let score = from_table(entry.score, ply);
+            match entry.bound {
+                Bound::Exact => return score.clamp(alpha, beta),
+                Bound::Lower if score >= beta => return beta,
+                Bound::Upper if score <= alpha => return alpha,
+                _ => {}
+            }
+        }
+
+        order_moves(position, &mut moves);
+        if let Some(best) = entry.and_then(|entry| entry.best) {
+            move_to_front(&mut moves, best);
+        }
+        let original = alpha;
+        let mut best = None;
+        for mv in moves {
+            let next = position.after(mv);
+            let next_eval = self.engine.eval_after(eval, position, &next);
+            let score = -self.negamax(&next, &next_eval, depth - 1, ply + 1, -beta, -alpha);
+            if score >= beta {
+                self.store(key, Some(mv), beta, depth, ply, Bound::Lower);
+                return beta;
+            }
+            if score > alpha {
+                alpha = score;
+                best = Some(mv);
+            }
+        }
+        let bound = if alpha > original {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.store(key, best, alpha, depth, ply, bound);
+        alpha
+    }
+
+    /// Resolves captures so the static evaluation is not taken mid-exchange.
+    fn quiesce(
+        &mut self,
+        position: &Position,
+        eval: &Eval,
+        ply: i32,
+        mut alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        self.nodes += 1;
+        if self.out_of_time() {
+            return 0;
+        }
+        if let Some(score) = variant_score(position, ply) {
+            return score;
+        }
+        let known = self
+            .engine
+            .bitbases
+            .as_ref()
+            .and_then(|bitbases| evaluate_known(bitbases, position));
+        if let Some(score) = known {
+            return score;
+        }
+        let stand_pat = self.engine.evaluate(position, eval);
+        if stand_pat >= beta {
+            return beta;
+        }
+        alpha = alpha.max(stand_pat);
+
+        let mut captures = position.legal_moves();
+        captures.retain(|&mv| position.is_capture(mv) || mv.promotion.is_some());
+        order_moves(position, &mut captures);
+        for mv in captures {
+            let next = position.after(mv);
+            let next_eval = self.engine.eval_after(eval, position, &next);
+            let score = -self.quiesce(&next, &next_eval, ply + 1, -beta, -alpha);
+            if score >= beta {
+                return beta;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+
+    /// Saves a result, unless the search was cut short and it is worthless.
+    #[allow(clippy::cast_possible_truncation)]
+    fn store(&self, key: u64, best: Option<Move>, score: i32, depth: u32, ply: i32, bound: Bound) {
+        if self.stopped {
+            return;
+        }
+        let entry = Entry {
+            best,
+            score: to_table(score, ply),
+            depth: depth.min(u32::from(u8::MAX)) as u8,
+            bound,
+        };
+        self.engine.table.store(key, entry);
+    }
+
+    /// Whether to give up: the deadline has passed, the main thread is done
+    /// or someone called a stop. Only checked now and then, when this
+    /// thread's nodes are added to the total.
+    fn out_of_time(&mut self) -> bool {
+        if !self.stopped && self.nodes >= 1024 {
+            self.shared.nodes.fetch_add(self.nodes, Ordering::Relaxed);
+            self.nodes = 0;
+            self.stopped = self.shared.halt.load(Ordering::Relaxed)
+                || self.engine.stop.is_stopped()
+                || self
+                    .shared
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline);
+        }
+        self.stopped
+    }
+}
+
+/// Mate scores count from the root, but the table is shared between
+/// positions at any ply, so they are stored counting from the position.
+const fn to_table(score: i32, ply: i32) -> i32 {
+    if score >= MATE - 1000 {
+        score + ply
+    } else if score <= 1000 - MATE {
+        score - ply
+    } else {
+        score
+    }
+}
+
+const fn from_table(score: i32, ply: i32) -> i32 {
+    if score >= MATE - 1000 {
+        score - ply
+    } else if score <= 1000 - MATE {
+        score + ply
+    } else {
+        score
+    }
+}
+
+fn move_to_front(moves: &mut [Move], mv: Move) {
+    if let Some(i) = moves.iter().position(|&m| m == mv) {
+        moves[..=i].rotate_right(1);
+    }
+}
+
+/// What the static evaluation carries from a position to the ones after it:
+/// with the `nnue` feature, the network's accumulator.
+#[derive(Debug, Clone, Default)]
+struct Eval {
+    #[cfg(feature = "nnue")]
+    accumulator: Option<nnue::Accumulator>,
+}
+
+#[cfg(feature = "nnue")]
+impl Engine {
+    fn root_eval(&self, position: &Position) -> Eval {
+        // Pieces in hand are no inputs, so Crazyhouse keeps to `params`.
+        let accumulator = self
+            .network
+            .as_ref()
+            .filter(|_| position.variant != crate::board::variant::Variant::Crazyhouse)
+            .and_then(|network| network.accumulate(position));
+        Eval { accumulator }
+    }
+
+    fn eval_after(&self, eval: &Eval, before: &Position, after: &Position) -> Eval {
+        let accumulator = self
+            .network
+            .as_ref()
+            .and_then(|network| network.update(eval.accumulator.as_ref()?, before, after));
+        Eval { accumulator }
+    }
+
+    fn evaluate(&self, position: &Position, eval: &Eval) -> i32 {
+        match (&self.network, &eval.accumulator) {
+            (Some(network), Some(accumulator)) => network.evaluate(accumulator, position.turn),
+            _ => self.params.evaluate(position),
+        }
+    }
+}
+
+#[cfg(not(feature = "nnue"))]
+#[allow(clippy::unused_self)]
+impl Engine {
+    fn root_eval(&self, _position: &Position) -> Eval {
+        Eval::default()
+    }
+
+    fn eval_after(&self, _eval: &Eval, _before: &Position, _after: &Position) -> Eval {
+        Eval::default()
+    }
+
+    fn evaluate(&self, position: &Position, _eval: &Eval) -> i32 {
+        self.params.evaluate(position)
+    }
+}
+
+/// A mate score once a variant's own goal is reached, which only ever
+/// happens on the move of the side that reached it.
+fn variant_score(position: &Position, ply: i32) -> Option<i32> {
+    let (winner, _) = position.variant_end()?;
+    Some(if winner == position.turn {
+        MATE - ply
+    } else {
+        ply - MATE
+    })
+}
+
+/// Puts the most valuable captures by the least valuable pieces first.
+fn order_moves(position: &Position, moves: &mut [Move]) {
+    moves.sort_by_cached_key(|&mv| {
+        let victim = position.piece_at(mv.to).map_or(0, |p| p.kind().value());
+        let attacker = position.piece_at(mv.from).map_or(0, |p| p.kind().value());
+        let promotion = mv.promotion.map_or(0, Kind::value);
+        -(victim * 10 - attacker / 10 + promotion)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_mate_in_one() {
+        let position = Position::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let result = Engine::new(2).search(&position);
+        assert_eq!(result.best, Some("a1a8".parse().unwrap()));
+        assert_eq!(result.score, MATE - 1);
+    }
+
+    #[test]
+    fn counts_moves_to_mate() {
+        assert_eq!(mate_moves(MATE - 1), Some(1));
+        assert_eq!(mate_moves(MATE - 4), Some(2));
+        assert_eq!(mate_moves(2 - MATE), Some(-1));
+        // Mated halfway through a move still takes the whole move.
+        assert_eq!(mate_moves(3 - MATE), Some(-2));
+        assert_eq!(mate_moves(KNOWN_WIN), None);
+    }
+
+    #[test]
+    fn takes_hanging_queen() {
+        let position = Position::from_fen("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1").unwrap();
+        let result = Engine::new(2).search(&position);
+        assert_eq!(result.best, Some("d1d5".parse().unwrap()));
+    }
+
+    #[test]
+    fn threads_share_the_table() {
+        // Mate in two, with 1. Kb6 Kb8 2. Rh8# among the ways.
+        let position = Position::from_fen("k7/8/2K5/8/8/8/8/7R w - - 0 1").unwrap();
+        let mut engine = Engine::new(4);
+        engine.threads = 4;
+        engine.hash = 1;
+        let mut depths = Vec::new();
+        let result = engine.search_with(&position, |result| depths.push(result.depth));
+        assert_eq!(result.score, MATE - 3);
+        let mut line = position.clone();
+        let pv = &result.lines[0].pv;
+        for &mv in pv {
+            assert!(line.legal_moves().contains(&mv));
+            line.make_move(mv);
+        }
+        assert!(pv.len() == 3 && line.is_checkmate());
+        assert_eq!(depths, (1..=result.depth).collect::<Vec<_>>());
+        assert!(result.nodes > 0 && result.nps() > 0);
+
+        // The next search starts from what the table already knows.
+        let again = engine.search(&position);
+        assert_eq!(again.score, result.score);
+        assert!(again.nodes < result.nodes);
+    }
+
+    #[test]
+    fn multiple_lines() {
+        // Only taking the queen wins material; everything else loses it.
+        let position = Position::from_fen("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1").unwrap();
+        let mut engine = Engine::new(3);
+        engine.multipv = 3;
+        let result = engine.search(&position);
+        assert_eq!(result.lines.len(), 3);
+        assert_eq!(result.lines[0].pv[0], "d1d5".parse().unwrap());
+        assert_eq!(result.lines[0].score, result.score);
+        assert!(result.lines.windows(2).all(|w| w[0].score >= w[1].score));
+        assert!(result.lines[1].score < 0);
+        let firsts = result
+            .lines
+            .iter()
+            .map(|line| line.pv[0])
+            .collect::<Vec<_>>();
+        assert!(firsts[0] != firsts[1] && firsts[1] != firsts[2] && firsts[0] != firsts[2]);
+
+        // No more lines than there are moves.
+        let cornered = Position::from_fen("7k/8/8/8/8/8/R7/1R5K b - - 0 1").unwrap();
+        engine.multipv = 5;
+        assert_eq!(
+            engine.search(&cornered).lines.len(),
+            cornered.legal_moves().len()
+        );
+    }
+
+    #[test]
+    fn stop_handle() {
+        let mut engine = Engine::new(MAX_DEPTH);
+        engine.threads = 2;
+        engine.stop_handle().stop();
+        let started = Instant::now();
+        let result = engine.search(&Position::default());
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(result.best.is_some());
+    }
+
+    #[test]
+    fn time_limit() {
+        let mut engine = Engine::new(MAX_DEPTH);
+        engine.time = Some(Duration::from_millis(200));
+        let started = Instant::now();
+        let result = engine.search(&Position::default());
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!((1..MAX_DEPTH).contains(&result.depth));
+        assert!(result.best.is_some());
+    }
+
+    #[test]
+    fn bitbases_score_endings() {
+        let mut bitbases = Bitbases::default();
+        bitbases
+            .ensure(crate::bitbase::Material::Krk, None)
+            .unwrap();
+
+        let won = Position::from_fen("8/8/8/3k4/8/8/8/KR6 w - - 0 1").unwrap();
+        let edge = Position::from_fen("3k4/8/3K4/8/8/8/8/R7 w - - 0 1").unwrap();
+        let hanging = Position::from_fen("8/8/8/8/8/2k5/1R6/7K b - - 0 1").unwrap();
+        let score = |position| evaluate_known(&bitbases, position).unwrap();
+        assert!(score(&won) > KNOWN_WIN - 1000);
+        assert!(score(&edge) > score(&won));
+        assert_eq!(score(&hanging), 0);
+
+        // Without the tables the engine would happily keep the rook; with them
+        // it still sees a won ending and plays on.
+        let mut engine = Engine::new(2);
+        engine.bitbases = Some(Arc::new(bitbases));
+        assert!(engine.search(&won).score > KNOWN_WIN - 1000);
+    }
+}
@@ -0,0 +1,393 @@
+//! A solver for forced mates, as in composed problems. Unlike the engine's
+//! search it never guesses: a line it returns is a mate against every
+//! defence, and a refutation means there is none within the given moves.
+//!
+//! It is a depth-first proof-number search. Each position carries how many
+//! more positions must be settled to prove the mate from it and how many to
+//! disprove it, and the search always goes where that is least, so forcing
+//! lines are followed deep long before quiet ones are looked at. The
+//! numbers are kept in a table, which lets transposed lines share them and
+//! bounds the memory used.
+
+use super::{tt, Stop};
+use crate::{
+    board::{movement::Move, position::Position},
+    piece::Color,
+};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+/// The longest mate the solver will look for.
+pub const MAX_MOVES: u32 = 8;
+/// Positions the solver keeps numbers for before giving up, unless set
+/// otherwise, in about 60 MB.
+pub const DEFAULT_NODES: usize = 2_000_000;
+/// How long the solver looks unless set otherwise.
+pub const DEFAULT_TIME: Duration = Duration::from_secs(20);
+
+/// The proof or disproof number of a question that is settled.
+const INFINITY: u32 = u32::MAX;
+const PROVEN: (u32, u32) = (0, INFINITY);
+const DISPROVEN: (u32, u32) = (INFINITY, 0);
+
+/// What the solver found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Solution {
+    /// The shortest mate, with the defence that holds out longest.
+    Mate(Vec<Move>),
+    /// There is no mate within the moves asked for.
+    NoMate,
+    /// The budget ran out, or the search was stopped, before either.
+    Unknown,
+}
+
+#[derive(Debug)]
+pub struct Solver {
+    /// Most positions to keep numbers for before giving up.
+    pub max_nodes: usize,
+    /// Longest to look before giving up, if limited.
+    pub time: Option<Duration>,
+    /// Ends the search early from another thread.
+    pub stop: Arc<Stop>,
+    /// Positions searched so far.
+    pub nodes: u64,
+    /// Proof and disproof numbers by position and half moves left.
+    table: HashMap<(u64, u32), (u32, u32)>,
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Self {
+            max_nodes: DEFAULT_NODES,
+            time: Some(DEFAULT_TIME),
+            stop: Arc::default(),
+            nodes: 0,
+            table: HashMap::new(),
+        }
+    }
+}
+
+/// Whether `attacker` has won in `position`, by mate or the variant's goal.
+fn won(position: &Position, attacker: Color) -> bool {
+    match position.variant_end() {
+        Some((winner, _)) => winner == attacker,
+        None => position.turn != attacker && position.is_checkmate(),
+    }
+}
+
+/// A move that wins straight away for the side to move.
+fn winning_move(position: &Position) -> Option<Move> {
+    let attacker = position.turn;
+    position
+        .legal_moves()
+        .into_iter()
+        .find(|&mv| won(&position.after(mv), attacker))
+}
+
+/// Whether the attacker, not to move, wins straight after every reply.
+fn wins_next(position: &Position) -> bool {
+    let replies = position.legal_moves();
+    if replies.is_empty() {
+        return position.in_check();
+    }
+    replies
+        .into_iter()
+        .all(|mv| winning_move(&position.after(mv)).is_some())
+}
+
+/// The numbers of a position not yet searched, with `left` half moves for
+/// the mate. The last three are settled outright, as seeing whether there
+/// is mate in one is quicker than searching for it.
+fn evaluate(position: &Position, left: u32) -> (u32, u32) {
+    let attacker = if left % 2 == 1 {
+        position.turn
+    } else {
+        position.turn.opposite()
+    };
+    if let Some((winner, _)) = position.variant_end() {
+        return if winner == attacker {
+            PROVEN
+        } else {
+            DISPROVEN
+        };
+    }
+    let settled = match left {
+        0 => won(position, attacker),
+        1 => winning_move(position).is_some(),
+        2 => wins_next(position),
+        _ => return (1, 1),
+    };
+    if settled {
+        PROVEN
+    } else {
+        DISPROVEN
+    }
+}
+
+impl Solver {
+    /// The shortest forced mate in at most `moves` for the side to move.
+    pub fn solve(&mut self, position: &Position, moves: u32) -> Solution {
+        if let Some(time) = self.time {
+            self.stop.stop_after(time);
+        }
+        if position.variant_end().is_some() {
+            return Solution::NoMate;
+        }
+        for n in 1..=moves.min(MAX_MOVES) {
+            let left = 2 * n - 1;
+            loop {
+                match self.numbers(position, left) {
+                    (0, _) => return Solution::Mate(self.line(position, left)),
+                    (_, 0) => break,
+                    _ if !self.search(position, left, INFINITY, INFINITY) => {
+                        return Solution::Unknown;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Solution::NoMate
+    }
+
+    /// The numbers known for `position` with `left` half moves to go.
+    fn numbers(&mut self, position: &Position, left: u32) -> (u32, u32) {
+        let key = (tt::key(position), left);
+        if let Some(&numbers) = self.table.get(&key) {
+            return numbers;
+        }
+        let numbers = evaluate(position, left);
+        // Only what took a search to settle is worth the room.
+        if left <= 2 {
+            self.table.insert(key, numbers);
+        }
+        numbers
+    }
+
+    /// Searches `position` until its proof number reaches `proof` or its
+    /// disproof number `disproof`, returning false if the budget ran out
+    /// first. The attacker needs one move that mates, the defender one
+    /// that escapes.
+    fn search(&mut self, position: &Position, left: u32, proof: u32, disproof: u32) -> bool {
+        self.nodes += 1;
+        if self.table.len() >= self.max_nodes || self.stop.is_stopped() {
+            return false;
+        }
+        let attacking = left % 2 == 1;
+        let key = (tt::key(position), left);
+        let children = position
+            .legal_moves()
+            .into_iter()
+            .map(|mv| position.after(mv))
+            .collect::<Vec<_>>();
+        if children.is_empty() {
+            // Mate or stalemate, settled whichever side it is.
+            let mated = !attacking && position.in_check();
+            self.table
+                .insert(key, if mated { PROVEN } else { DISPROVEN });
+            return true;
+        }
+        loop {
+            let numbers = children
+                .iter()
+                .map(|child| self.numbers(child, left - 1))
+                .collect::<Vec<_>>();
+            // What the side to move picks, and what has to be settled for
+            // every move: the other number.
+            let pick = |&(proof, disproof): &(u32, u32)| if attacking { proof } else { disproof };
+            let all = |&(proof, disproof): &(u32, u32)| if attacking { disproof } else { proof };
+            let (best, best_pick) = numbers
+                .iter()
+                .map(pick)
+                .enumerate()
+                .min_by_key(|&(_, n)| n)
+                .unwrap_or((0, INFINITY));
+            let second = numbers
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| i != best)
+                .map(|(_, n)| pick(n))
+                .min()
+                .unwrap_or(INFINITY);
+            let total = if best_pick == 0 {
+                INFINITY
+            } else {
+                numbers
+                    .iter()
+                    .map(all)
+                    .fold(0, |sum: u32, n| sum.saturating_add(n))
+                    .min(INFINITY - 1)
+            };
+            let (my_limit, their_limit) = if attacking {
+                (proof, disproof)
+            } else {
+                (disproof, proof)
+            };
+            let here = if attacking {
+                (best_pick, total)
+            } else {
+                (total, best_pick)
+            };
+            self.table.insert(key, here);
+            if best_pick >= my_limit || total >= their_limit {
+                return true;
+            }
+            // Go on below the best move until it falls a little behind the
+            // second best, so as not to switch back and forth, or until
+            // this position reaches its limit.
+            let child_mine = my_limit.min(second.saturating_add(second / 4 + 1));
+            let child_theirs = their_limit - total + all(&numbers[best]);
+            let (child_proof, child_disproof) = if attacking {
+                (child_mine, child_theirs)
+            } else {
+                (child_theirs, child_mine)
+            };
+            if !self.search(&children[best], left - 1, child_proof, child_disproof) {
+                return false;
+            }
+        }
+    }
+
+    /// The mate from a proven position: the attacker's first mating move,
+    /// against the defence that lasts longest.
+    fn line(&mut self, position: &Position, left: u32) -> Vec<Move> {
+        if left == 0 {
+            return Vec::new();
+        }
+        let moves = position.legal_moves();
+        if left % 2 == 1 {
+            let Some(mv) = moves
+                .into_iter()
+                .find(|&mv| self.numbers(&position.after(mv), left - 1).0 == 0)
+            else {
+                return Vec::new();
+            };
+            [vec![mv], self.line(&position.after(mv), left - 1)].concat()
+        } else {
+            moves
+                .into_iter()
+                .map(|mv| [vec![mv], self.line(&position.after(mv), left - 1)].concat())
+                .max_by_key(Vec::len)
+                .unwrap_or_default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pgn::san_line;
+
+    /// Problems with the number of moves to mate and the solution's first
+    /// move, which is the only one that mates in time.
+    const PROBLEMS: [(&str, u32, &str); 9] = [
+        // Back rank.
+        ("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 1, "Ra8#"),
+        ("r5k1/8/8/8/8/8/5PPP/6K1 b - - 0 1", 1, "Ra1#"),
+        // Scholar's mate.
+        (
+            "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+            1,
+            "Qxf7#",
+        ),
+        // Smothered in the corner.
+        ("6rk/6pp/8/6N1/8/8/8/6K1 w - - 0 1", 1, "Nf7#"),
+        // Légal's mate, after Black took the queen.
+        (
+            "r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - 1 1",
+            2,
+            "Nf6+",
+        ),
+        // Morphy at the opera, 1858.
+        (
+            "4kb1r/p2n1ppp/4q3/4p1B1/4P3/1Q6/PPP2PPP/2KR4 w k - 1 16",
+            2,
+            "Qb8+",
+        ),
+        // A queen sacrifice opens the g-file.
+        (
+            "r1b2k1r/ppppq3/5N1p/4P2Q/4PP2/1B6/PP5P/n2K2R1 w - - 1 1",
+            2,
+            "Qxh6+",
+        ),
+        // The queen herds the king back into its corner.
+        (
+            "2r3k1/p4p2/3Rp2p/1p2P1pK/8/1P4P1/P3Q2P/1q6 b - - 0 1",
+            3,
+            "Qg6+",
+        ),
+        // Philidor's legacy: check, double check, sacrifice, smother.
+        ("r6k/6pp/8/4N3/8/BQ6/8/6K1 w - - 0 1", 4, "Nf7+"),
+    ];
+
+    #[test]
+    fn solves_known_problems() {
+        for (fen, moves, first) in PROBLEMS {
+            let position = Position::from_fen(fen).unwrap();
+            let mut solver = Solver::default();
+            let Solution::Mate(line) = solver.solve(&position, moves) else {
+                panic!("{fen}");
+            };
+            assert_eq!(line.len(), moves as usize * 2 - 1, "{fen}");
+            assert_eq!(position.san(line[0]), first, "{fen}");
+            let mut end = position.clone();
+            for &mv in &line {
+                assert!(end.legal_moves().contains(&mv), "{fen}");
+                end.make_move(mv);
+            }
+            assert!(end.is_checkmate(), "{fen}");
+            // And there is nothing quicker.
+            assert_eq!(
+                Solver::default().solve(&position, moves - 1),
+                Solution::NoMate,
+                "{fen}"
+            );
+        }
+    }
+
+    #[test]
+    fn refutes_what_is_not_mate() {
+        let mut solver = Solver::default();
+        // The start is no mate, nor is a lone king with nothing to mate.
+        assert_eq!(solver.solve(&Position::default(), 2), Solution::NoMate);
+        let bare = Position::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(solver.solve(&bare, 3), Solution::NoMate);
+    }
+
+    #[test]
+    fn gives_up_within_its_budget() {
+        // Quiet, with no mate in three to find; settling that takes
+        // hundreds of positions.
+        let italian = Position::from_fen(
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+        )
+        .unwrap();
+        assert_eq!(Solver::default().solve(&italian, 3), Solution::NoMate);
+        let mut solver = Solver {
+            max_nodes: 100,
+            ..Solver::default()
+        };
+        assert_eq!(solver.solve(&italian, 3), Solution::Unknown);
+
+        let mut solver = Solver::default();
+        solver.stop.stop();
+        assert_eq!(solver.solve(&italian, 3), Solution::Unknown);
+        // Mate in one needs no search, so it is still seen.
+        let back_rank = Position::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        assert!(matches!(
+            solver.solve(&back_rank, 1),
+            Solution::Mate(line) if line.len() == 1
+        ));
+    }
+
+    #[test]
+    fn writes_the_line_in_san() {
+        let position =
+            Position::from_fen("2r3k1/p4p2/3Rp2p/1p2P1pK/8/1P4P1/P3Q2P/1q6 b - - 0 1").unwrap();
+        let Solution::Mate(line) = Solver::default().solve(&position, 3) else {
+            panic!("a mate in 3");
+        };
+        assert_eq!(
+            san_line(&position, &line),
+            "1... Qg6+ 2. Kg4 Qf5+ 3. Kh5 Qh3#"
+        );
+    }
+}
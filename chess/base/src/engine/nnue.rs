@@ -0,0 +1,287 @@
+//! An efficiently updatable neural network evaluation.
+//!
+//! The network sees the board once from each side. Its inputs are `HalfKP`
+//! style: one for every pairing of that side's king square with a
+//! non-king piece on some square, 64 × 10 × 64 in all, with the board
+//! flipped for Black so both sides read it the same way. They feed a
+//! hidden layer whose sums, the accumulator, only change for the few
+//! pieces a move touches. Both halves are clamped to `0..=QA` and feed a
+//! single output, with the side to move's half first.
+//!
+//! # File format
+//!
+//! Everything is little-endian:
+//!
+//! | bytes | contents |
+//! |-------|----------|
+//! | 4 | magic `BNUE` |
+//! | 4 | `u32` format version, 1 |
+//! | 4 | `u32` hidden size `H` |
+//! | 2·H | `i16` hidden biases |
+//! | 2·H·40960 | `i16` hidden weights, `H` for each input in turn |
+//! | 2·2H | `i16` output weights, side to move first |
+//! | 4 | `i32` output bias |
+//!
+//! Input `(king * 10 + piece) * 64 + square` has `king` and `square` as
+//! 0 for a8 through 63 for h1 from that side's view (mirrored top to
+//! bottom for Black) and `piece` as `2 * kind + them`, where kind counts
+//! pawn 0 to queen 4 and `them` is 1 for the other side's pieces.
+//! Hidden values are clipped to `0..=QA` and the output is scaled by
+//! `SCALE / (QA * QB)` to centipawns.
+
+use crate::{
+    board::{position::Position, square::Coord, variant::side},
+    piece::{Color, Kind, Piece},
+};
+use std::sync::{Arc, OnceLock};
+
+/// Where the engine looks for a network unless told otherwise.
+pub const FILE: &str = "network.nnue";
+
+const MAGIC: &[u8; 4] = b"BNUE";
+const VERSION: u32 = 1;
+/// Inputs per side: king square × piece × square.
+pub const INPUTS: usize = 64 * 10 * 64;
+/// The ceiling hidden values are clamped to, which is 1.0 in the float network.
+const QA: i32 = 255;
+/// What 1.0 is in the quantized output weights.
+const QB: i32 = 64;
+/// Centipawns for an output of 1.0.
+const SCALE: i32 = 400;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Network {
+    biases: Vec<i16>,
+    weights: Vec<i16>,
+    output: Vec<i16>,
+    bias: i32,
+}
+
+/// The hidden layer's sums for a position, from White's and Black's side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Accumulator([Vec<i16>; 2]);
+
+/// Squares as seen from `perspective`.
+const fn orient(perspective: Color, coord: Coord) -> usize {
+    match perspective {
+        Color::White => coord.index(),
+        Color::Black => coord.index() ^ 0b11_1000,
+    }
+}
+
+/// The input for `piece` on `coord` with `perspective`'s king on `king`,
+/// or `None` for the kings themselves.
+fn input(perspective: Color, king: Coord, coord: Coord, piece: Piece) -> Option<usize> {
+    if piece.kind() == Kind::King {
+        return None;
+    }
+    let piece = piece.kind() as usize * 2 + usize::from(piece.color() != perspective);
+    Some((orient(perspective, king) * 10 + piece) * 64 + orient(perspective, coord))
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+fn read_i16s(bytes: &[u8]) -> Vec<i16> {
+    bytes
+        .chunks_exact(2)
+        .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+        .collect()
+}
+
+impl Network {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("Could not read {path}: {e}"))?;
+        Self::from_bytes(&bytes).map_err(|e| format!("{path}: {e}"))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < 12 || &bytes[..4] != MAGIC {
+            return Err("Not a network file".to_string());
+        }
+        let version = read_u32(bytes, 4);
+        if version != VERSION {
+            return Err(format!("Unsupported network version {version}"));
+        }
+        let hidden = read_u32(bytes, 8) as usize;
+        if !(1..=4096).contains(&hidden) {
+            return Err(format!("Unreasonable hidden size {hidden}"));
+        }
+        let sizes = [hidden * 2, hidden * INPUTS * 2, hidden * 4, 4];
+        if bytes.len() != 12 + sizes.iter().sum::<usize>() {
+            return Err(format!("Wrong size for a hidden layer of {hidden}"));
+        }
+        let (biases, rest) = bytes[12..].split_at(sizes[0]);
+        let (weights, rest) = rest.split_at(sizes[1]);
+        let (output, bias) = rest.split_at(sizes[2]);
+        Ok(Self {
+            biases: read_i16s(biases),
+            weights: read_i16s(weights),
+            output: read_i16s(output),
+            bias: i32::from_le_bytes(bias.try_into().unwrap()),
+        })
+    }
+
+    const fn hidden(&self) -> usize {
+        self.biases.len()
+    }
+
+    fn add(&self, values: &mut [i16], input: usize) {
+        let weights = &self.weights[input * self.hidden()..][..self.hidden()];
+        for (value, weight) in values.iter_mut().zip(weights) {
+            *value = value.wrapping_add(*weight);
+        }
+    }
+
+    fn sub(&self, values: &mut [i16], input: usize) {
+        let weights = &self.weights[input * self.hidden()..][..self.hidden()];
+        for (value, weight) in values.iter_mut().zip(weights) {
+            *value = value.wrapping_sub(*weight);
+        }
+    }
+
+    /// One side's half of the accumulator, built from nothing.
+    fn refresh(&self, position: &Position, perspective: Color) -> Option<Vec<i16>> {
+        let king = position.king(perspective)?;
+        let mut values = self.biases.clone();
+        for (coord, piece) in position.board.pieces() {
+            if let Some(input) = input(perspective, king, coord, piece) {
+                self.add(&mut values, input);
+            }
+        }
+        Some(values)
+    }
+
+    /// The accumulator for `position`, or `None` if a king is missing.
+    #[must_use]
+    pub fn accumulate(&self, position: &Position) -> Option<Accumulator> {
+        Some(Accumulator([
+            self.refresh(position, Color::White)?,
+            self.refresh(position, Color::Black)?,
+        ]))
+    }
+
+    /// The accumulator for `after` worked out from the one for `before`,
+    /// adjusting only the squares that changed. A side whose king moved
+    /// sees every input change, so its half is rebuilt.
+    #[must_use]
+    pub fn update(
+        &self,
+        accumulator: &Accumulator,
+        before: &Position,
+        after: &Position,
+    ) -> Option<Accumulator> {
+        let mut next = accumulator.clone();
+        for perspective in [Color::White, Color::Black] {
+            let king = after.king(perspective)?;
+            let values = &mut next.0[side(perspective)];
+            if before.king(perspective) != Some(king) {
+                *values = self.refresh(after, perspective)?;
+                continue;
+            }
+            let squares = before.board.0.iter().zip(&after.board.0).enumerate();
+            for (index, (old, new)) in squares.filter(|(_, (old, new))| old != new) {
+                let coord = Coord::from_index(index);
+                if let Some(input) = old.and_then(|piece| input(perspective, king, coord, piece)) {
+                    self.sub(values, input);
+                }
+                if let Some(input) = new.and_then(|piece| input(perspective, king, coord, piece)) {
+                    self.add(values, input);
+                }
+            }
+        }
+        Some(next)
+    }
+
+    /// The score in centipawns for the side to move, `turn`.
+    #[must_use]
+    pub fn evaluate(&self, accumulator: &Accumulator, turn: Color) -> i32 {
+        let us = &accumulator.0[side(turn)];
+        let them = &accumulator.0[side(turn.opposite())];
+        let sum = us
+            .iter()
+            .chain(them)
+            .zip(&self.output)
+            .map(|(&value, &weight)| i32::from(value).clamp(0, QA) * i32::from(weight))
+            .sum::<i32>();
+        (sum + self.bias) * SCALE / (QA * QB)
+    }
+}
+
+/// The network in [`FILE`], read the first time it is asked for. Without
+/// one the engine keeps to the handcrafted evaluation.
+pub fn default_network() -> Option<Arc<Network>> {
+    static NETWORK: OnceLock<Option<Arc<Network>>> = OnceLock::new();
+    NETWORK
+        .get_or_init(|| Network::open(FILE).ok().map(Arc::new))
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+
+    const HIDDEN: usize = 8;
+
+    fn random_bytes() -> Vec<u8> {
+        let mut rng = Rng::default();
+        let mut small = || i16::try_from(rng.below(61)).unwrap() - 30;
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(VERSION.to_le_bytes());
+        bytes.extend(u32::try_from(HIDDEN).unwrap().to_le_bytes());
+        for _ in 0..HIDDEN * (1 + INPUTS + 2) {
+            bytes.extend(small().to_le_bytes());
+        }
+        bytes.extend(17i32.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn reads_the_file_format() {
+        let bytes = random_bytes();
+        let network = Network::from_bytes(&bytes).unwrap();
+        assert_eq!(network.hidden(), HIDDEN);
+        assert_eq!(network.weights.len(), HIDDEN * INPUTS);
+        assert_eq!(network.bias, 17);
+
+        assert!(Network::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut wrong = bytes;
+        wrong[4] = 2;
+        assert_eq!(
+            Network::from_bytes(&wrong),
+            Err("Unsupported network version 2".to_string())
+        );
+        assert!(Network::from_bytes(b"NNUE").is_err());
+    }
+
+    #[test]
+    fn updates_match_a_refresh() {
+        let network = Network::from_bytes(&random_bytes()).unwrap();
+        let mut position =
+            Position::from_fen("r3k2r/1P3ppp/8/3pP3/8/8/5PPP/R3K2R w KQkq d6 0 1").unwrap();
+        let mut accumulator = network.accumulate(&position).unwrap();
+        // En passant, castling both ways, a king move, a promotion with capture
+        // and a plain capture.
+        for mv in ["exd6", "O-O", "O-O-O", "Kh8", "bxa8=Q", "Rxa8"] {
+            let before = position.clone();
+            position.play(mv).unwrap();
+            accumulator = network.update(&accumulator, &before, &position).unwrap();
+            assert_eq!(Some(&accumulator), network.accumulate(&position).as_ref());
+        }
+    }
+
+    #[test]
+    fn both_sides_see_the_same() {
+        let network = Network::from_bytes(&random_bytes()).unwrap();
+        let white = Position::from_fen("4k3/2p5/8/8/8/5N2/PP6/4K3 w - - 0 1").unwrap();
+        let black = Position::from_fen("4k3/pp6/5n2/8/8/8/2P5/4K3 b - - 0 1").unwrap();
+        let score = |position: &Position| {
+            network.evaluate(&network.accumulate(position).unwrap(), position.turn)
+        };
+        assert_eq!(score(&white), score(&black));
+        let kingless = Position::parse_fen("8/8/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(kingless.map(|p| network.accumulate(&p)), Ok(None));
+    }
+}
@@ -0,0 +1,253 @@
+use crate::{
+    board::position::Position,
+    piece::{Color, Kind},
+};
+use std::{fmt::Display, str::FromStr};
+
+/// Where tuned parameters are saved unless another file is named.
+pub const FILE: &str = "params.txt";
+
+/// Every kind, in the order the tables are indexed by.
+pub const KINDS: [Kind; 6] = [
+    Kind::Pawn,
+    Kind::Knight,
+    Kind::Bishop,
+    Kind::Rook,
+    Kind::Queen,
+    Kind::King,
+];
+
+/// The weights the evaluation adds up, in centipawns: a material value and
+/// a piece-square table for each kind, both indexed as in [`KINDS`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Params {
+    pub material: [i32; 6],
+    /// Bonus for a piece on each square, seen from White with rank 8 first.
+    pub squares: [[i32; 64]; 6],
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Self {
+            material: [100, 320, 330, 500, 900, 0],
+            squares: [
+                PAWN_TABLE,
+                KNIGHT_TABLE,
+                BISHOP_TABLE,
+                ROOK_TABLE,
+                QUEEN_TABLE,
+                KING_TABLE,
+            ],
+        }
+    }
+}
+
+impl Params {
+    /// Static evaluation from the side to move's point of view.
+    #[must_use]
+    pub fn evaluate(&self, position: &Position) -> i32 {
+        let score = position
+            .board
+            .pieces()
+            .map(|(coord, piece)| {
+                // Tables are written from White's side, so mirror them for Black.
+                let (index, sign) = match piece.color() {
+                    Color::White => (coord.index(), 1),
+                    Color::Black => (coord.index() ^ 0b11_1000, -1),
+                };
+                let kind = piece.kind() as usize;
+                sign * (self.material[kind] + self.squares[kind][index])
+            })
+            .sum::<i32>()
+            + self.pockets(position);
+        match position.turn {
+            Color::White => score,
+            Color::Black => -score,
+        }
+    }
+
+    /// Material in hand in Crazyhouse, White's less Black's.
+    fn pockets(&self, position: &Position) -> i32 {
+        let [white, black] = position.pockets;
+        KINDS
+            .into_iter()
+            .map(|kind| {
+                let count = i32::from(white.count(kind)) - i32::from(black.count(kind));
+                count * self.material[kind as usize]
+            })
+            .sum()
+    }
+
+    pub fn open(path: &str) -> Result<Self, String> {
+        std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {path}: {e}"))?
+            .parse()
+            .map_err(|e| format!("{path}: {e}"))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_string()).map_err(|e| format!("Could not save {path}: {e}"))
+    }
+}
+
+fn table_name(kind: Kind) -> String {
+    kind.to_string().to_lowercase()
+}
+
+impl Display for Params {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "# Evaluation parameters in centipawns.")?;
+        writeln!(f, "# material: pawn knight bishop rook queen king")?;
+        let material = self.material.map(|value| value.to_string());
+        writeln!(f, "material {}", material.join(" "))?;
+        writeln!(f, "# Square tables from a8 to h1, as seen from White.")?;
+        for (kind, table) in KINDS.into_iter().zip(&self.squares) {
+            writeln!(f, "{}", table_name(kind))?;
+            for row in table.chunks(8) {
+                for value in row {
+                    write!(f, "{value:4}")?;
+                }
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Params {
+    type Err = String;
+
+    /// Reads the form written by `Display`. Sections may come in any order
+    /// and any left out keep their default values.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut params = Self::default();
+        let mut words = s
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default())
+            .flat_map(str::split_whitespace);
+        while let Some(section) = words.next() {
+            let values = if section == "material" {
+                &mut params.material[..]
+            } else {
+                let kind = KINDS
+                    .into_iter()
+                    .position(|kind| table_name(kind) == section)
+                    .ok_or_else(|| format!("Unknown section '{section}'"))?;
+                &mut params.squares[kind][..]
+            };
+            for value in values.iter_mut() {
+                let word = words
+                    .next()
+                    .ok_or_else(|| format!("Section '{section}' is cut short"))?;
+                *value = word
+                    .parse()
+                    .map_err(|_| format!("Invalid number '{word}' in '{section}'"))?;
+            }
+        }
+        Ok(params)
+    }
+}
+
+#[rustfmt::skip]
+const PAWN_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const KNIGHT_TABLE: [i32; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+
+#[rustfmt::skip]
+const BISHOP_TABLE: [i32; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const ROOK_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const QUEEN_TABLE: [i32; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const KING_TABLE: [i32; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evaluation_is_symmetric() {
+        let params = Params::default();
+        assert_eq!(params.evaluate(&Position::default()), 0);
+        let white = Position::from_fen("4k3/8/8/8/8/8/3N4/4K3 w - - 0 1").unwrap();
+        let black = Position::from_fen("4k3/3n4/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(params.evaluate(&white), params.evaluate(&black));
+        assert!(params.evaluate(&white) > 300);
+    }
+
+    #[test]
+    fn file_format() {
+        let mut params = Params::default();
+        params.material[1] = 305;
+        params.squares[0][8] = -7;
+        assert_eq!(params.to_string().parse(), Ok(params));
+
+        let partial = "material 90 300 300 500 1000 0 # cheaper pawns\n".parse::<Params>();
+        assert_eq!(partial.unwrap().squares, Params::default().squares);
+        assert_eq!(
+            "knight 1 2 3".parse::<Params>(),
+            Err("Section 'knight' is cut short".to_string())
+        );
+        assert!("bishops 1".parse::<Params>().is_err());
+        assert!("material 1 2 x".parse::<Params>().is_err());
+    }
+}
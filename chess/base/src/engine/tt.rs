@@ -0,0 +1,258 @@
+use super::params::KINDS;
+use crate::board::{movement::Move, position::Position, square::Coord};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// How a stored score relates to the true one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    /// The search failed high, so the score is at least this.
+    Lower,
+    /// Nothing beat alpha, so the score is at most this.
+    Upper,
+}
+
+/// What a search learned about a position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    pub best: Option<Move>,
+    pub score: i32,
+    pub depth: u8,
+    pub bound: Bound,
+}
+
+fn kind_code(kind: Option<crate::piece::Kind>) -> u64 {
+    kind.map_or(0, |kind| kind as u64 + 1)
+}
+
+fn code_kind(code: u64) -> Option<crate::piece::Kind> {
+    code.checked_sub(1)
+        .and_then(|i| KINDS.get(usize::try_from(i).ok()?).copied())
+}
+
+impl Entry {
+    /// Packs the entry into 64 bits: the score, depth and bound in the low
+    /// 42 and the move, when there is one, above them.
+    fn encode(self) -> u64 {
+        let bound = match self.bound {
+            Bound::Exact => 0,
+            Bound::Lower => 1,
+            Bound::Upper => 2,
+        };
+        let mv = self.best.map_or(0, |mv| {
+            1 | (mv.from.index() as u64) << 1
+                | (mv.to.index() as u64) << 7
+                | kind_code(mv.promotion) << 13
+                | kind_code(mv.drop) << 16
+        });
+        u64::from(self.score.cast_unsigned()) | u64::from(self.depth) << 32 | bound << 40 | mv << 42
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn decode(data: u64) -> Self {
+        let mv = data >> 42;
+        let square = |shift: u32| Coord::from_index(((mv >> shift) & 63) as usize);
+        Self {
+            best: (mv & 1 == 1).then(|| Move {
+                from: square(1),
+                to: square(7),
+                promotion: code_kind((mv >> 13) & 7),
+                drop: code_kind((mv >> 16) & 7),
+            }),
+            score: (data as u32).cast_signed(),
+            depth: (data >> 32) as u8,
+            bound: match (data >> 40) & 3 {
+                0 => Bound::Exact,
+                1 => Bound::Lower,
+                _ => Bound::Upper,
+            },
+        }
+    }
+}
+
+/// The key a position is stored under: its Zobrist hash, with the variant
+/// state the hash leaves out stirred in.
+#[must_use]
+pub fn key(position: &Position) -> u64 {
+    let mut extra = position.variant as u64;
+    for count in position.checks {
+        extra = extra << 8 | u64::from(count);
+    }
+    for pocket in position.pockets {
+        for kind in pocket.kinds() {
+            extra = extra.rotate_left(11) ^ ((kind as u64 + 1) * u64::from(pocket.count(kind)));
+        }
+        extra = extra.rotate_left(3);
+    }
+    if extra == 0 {
+        return position.hash();
+    }
+    // SplitMix64's finaliser, to spread the bits.
+    let mut z = extra.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    position.hash() ^ z ^ (z >> 31)
+}
+
+/// A transposition table that any number of search threads can share
+/// without locks. Each slot holds the data beside the key exclusive-ored
+/// with it, so a slot torn by two threads writing at once fails its key
+/// check instead of returning a mix of both.
+#[derive(Debug, Default)]
+pub struct Table {
+    slots: Vec<[AtomicU64; 2]>,
+}
+
+impl Clone for Table {
+    fn clone(&self) -> Self {
+        Self {
+            slots: self
+                .slots
+                .iter()
+                .map(|[check, data]| {
+                    [
+                        AtomicU64::new(check.load(Ordering::Relaxed)),
+                        AtomicU64::new(data.load(Ordering::Relaxed)),
+                    ]
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Table {
+    /// Size in megabytes, rounded down.
+    #[must_use]
+    pub const fn megabytes(&self) -> usize {
+        (self.slots.len() * size_of::<[AtomicU64; 2]>()) >> 20
+    }
+
+    /// Makes room for `megabytes` of entries, emptying the table.
+    pub fn resize(&mut self, megabytes: usize) {
+        let slots = (megabytes << 20) / size_of::<[AtomicU64; 2]>();
+        self.slots = (0..slots.max(1))
+            .map(|_| [AtomicU64::new(0), AtomicU64::new(0)])
+            .collect();
+    }
+
+    pub fn clear(&self) {
+        for [check, data] in &self.slots {
+            check.store(0, Ordering::Relaxed);
+            data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn slot(&self, key: u64) -> Option<&[AtomicU64; 2]> {
+        let index = ((u128::from(key) * self.slots.len() as u128) >> 64) as usize;
+        self.slots.get(index)
+    }
+
+    #[must_use]
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        let [check, data] = self.slot(key)?;
+        let data = data.load(Ordering::Relaxed);
+        (data != 0 && check.load(Ordering::Relaxed) ^ data == key).then(|| Entry::decode(data))
+    }
+
+    /// Stores `entry`, keeping a deeper result for the same position unless
+    /// this one is exact.
+    pub fn store(&self, key: u64, entry: Entry) {
+        let Some([check, data]) = self.slot(key) else {
+            return;
+        };
+        let old = self.probe(key);
+        if old.is_some_and(|old| old.depth > entry.depth && entry.bound != Bound::Exact) {
+            return;
+        }
+        let entry = Entry {
+            best: entry.best.or_else(|| old.and_then(|old| old.best)),
+            ..entry
+        };
+        let encoded = entry.encode();
+        check.store(key ^ encoded, Ordering::Relaxed);
+        data.store(encoded, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piece::Kind;
+
+    #[test]
+    fn entries_round_trip() {
+        for entry in [
+            Entry {
+                best: Some("e7e8n".parse().unwrap()),
+                score: -99_950,
+                depth: 64,
+                bound: Bound::Upper,
+            },
+            Entry {
+                best: Some(Move::drop(Kind::Knight, "f3".parse().unwrap())),
+                score: 12,
+                depth: 0,
+                bound: Bound::Lower,
+            },
+            Entry {
+                best: None,
+                score: 0,
+                depth: 3,
+                bound: Bound::Exact,
+            },
+        ] {
+            assert_eq!(Entry::decode(entry.encode()), entry);
+        }
+    }
+
+    #[test]
+    fn stores_and_probes() {
+        let mut table = Table::default();
+        table.resize(1);
+        assert_eq!(table.megabytes(), 1);
+        let position = Position::default();
+        let key = key(&position);
+        assert_eq!(table.probe(key), None);
+        let deep = Entry {
+            best: Some("e2e4".parse().unwrap()),
+            score: 30,
+            depth: 6,
+            bound: Bound::Lower,
+        };
+        table.store(key, deep);
+        assert_eq!(table.probe(key), Some(deep));
+        assert_eq!(table.probe(key ^ 1), None);
+
+        // A shallower bound does not replace a deeper result.
+        let shallow = Entry {
+            best: None,
+            score: -5,
+            depth: 2,
+            bound: Bound::Upper,
+        };
+        table.store(key, shallow);
+        assert_eq!(table.probe(key), Some(deep));
+        let exact = Entry {
+            bound: Bound::Exact,
+            ..shallow
+        };
+        table.store(key, exact);
+        assert_eq!(table.probe(key).unwrap().best, deep.best);
+
+        assert_eq!(table.clone().probe(key), table.probe(key));
+        table.clear();
+        assert_eq!(table.probe(key), None);
+    }
+
+    #[test]
+    fn variant_state_changes_the_key() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -";
+        let standard = Position::from_fen(fen).unwrap();
+        assert_eq!(key(&standard), standard.hash());
+        let fresh = Position::from_fen(&format!("{fen} 3+3 0 1")).unwrap();
+        let checked = Position::from_fen(&format!("{fen} 2+3 0 1")).unwrap();
+        assert_eq!(fresh.hash(), checked.hash());
+        assert_ne!(key(&checked), key(&fresh));
+    }
+}
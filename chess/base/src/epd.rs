@@ -0,0 +1,206 @@
+use crate::board::{movement::Move, position::Position};
+use std::{fmt::Display, str::FromStr};
+
+/// One line of an EPD file: a position without move counters, followed by
+/// operations such as `bm Qg6;` or `id "WAC.001";`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Epd {
+    pub position: Position,
+    /// Opcodes with their operands, in the order given.
+    pub operations: Vec<(String, Vec<String>)>,
+}
+
+impl Epd {
+    /// The operands of `opcode`, if the line has it.
+    #[must_use]
+    pub fn operation(&self, opcode: &str) -> Option<&[String]> {
+        self.operations
+            .iter()
+            .find(|(op, _)| op == opcode)
+            .map(|(_, operands)| operands.as_slice())
+    }
+
+    /// The position's name from its `id` operation.
+    #[must_use]
+    pub fn id(&self) -> Option<&str> {
+        self.operation("id")?.first().map(String::as_str)
+    }
+
+    /// The moves listed by `opcode`, usually `bm` (best moves) or `am` (moves
+    /// to avoid), read as SAN in this position.
+    pub fn moves(&self, opcode: &str) -> Result<Vec<Move>, String> {
+        self.operation(opcode)
+            .unwrap_or_default()
+            .iter()
+            .map(|san| self.position.parse_move(san))
+            .collect()
+    }
+}
+
+impl FromStr for Epd {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = Vec::new();
+        let mut rest = s.trim();
+        for _ in 0..4 {
+            let (field, after) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            if field.is_empty() {
+                return Err(format!("EPD needs 4 position fields: '{s}'"));
+            }
+            fields.push(field);
+            rest = after.trim_start();
+        }
+        // Some suites keep FEN's move counters before the operations.
+        let mut counters = Vec::new();
+        while counters.len() < 2 {
+            let (number, after) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            if number.parse::<u16>().is_err() {
+                break;
+            }
+            counters.push(number);
+            rest = after.trim_start();
+        }
+
+        let operations = parse_operations(rest)?;
+        let counter = |opcode: &str, index: usize, default: &'static str| {
+            operations
+                .iter()
+                .find(|(op, _)| op == opcode)
+                .and_then(|(_, operands)| operands.first().map(String::as_str))
+                .or_else(|| counters.get(index).copied())
+                .unwrap_or(default)
+        };
+        let fen = format!(
+            "{} {} {}",
+            fields.join(" "),
+            counter("hmvc", 0, "0"),
+            counter("fmvn", 1, "1")
+        );
+        Ok(Self {
+            position: Position::from_fen(&fen)?,
+            operations,
+        })
+    }
+}
+
+/// Splits `bm Qg6 Qh5; id "WAC.001";` into opcodes and operands. Quoted
+/// operands may hold spaces and semicolons; the last `;` may be left off.
+fn parse_operations(text: &str) -> Result<Vec<(String, Vec<String>)>, String> {
+    let mut operations = Vec::new();
+    let mut words = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ';' => {
+                let mut words = std::mem::take(&mut words).into_iter();
+                if let Some(opcode) = words.next() {
+                    operations.push((opcode, words.collect()));
+                }
+            }
+            '"' => {
+                let mut word = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(format!("Unclosed quote in '{text}'")),
+                    }
+                }
+                words.push(word);
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == ';' || c == '"' {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                words.push(word);
+            }
+        }
+    }
+    let mut words = words.into_iter();
+    if let Some(opcode) = words.next() {
+        operations.push((opcode, words.collect()));
+    }
+    Ok(operations)
+}
+
+impl Display for Epd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fen = self.position.fen();
+        let fields = fen.split_whitespace().take(4).collect::<Vec<_>>();
+        write!(f, "{}", fields.join(" "))?;
+        for (opcode, operands) in &self.operations {
+            write!(f, " {opcode}")?;
+            // Names and comments are strings; anything else only needs
+            // quotes when it would not read back as one word.
+            let text = opcode == "id" || (opcode.len() == 2 && opcode.starts_with('c'));
+            for operand in operands {
+                if text || operand.is_empty() || operand.contains([' ', ';']) {
+                    write!(f, " \"{operand}\"")?;
+                } else {
+                    write!(f, " {operand}")?;
+                }
+            }
+            write!(f, ";")?;
+        }
+        Ok(())
+    }
+}
+
+/// Every position in an EPD file, skipping blank lines and `#` comments.
+pub fn parse_all(text: &str) -> Result<Vec<Epd>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(i, line)| line.parse().map_err(|e| format!("line {}: {e}", i + 1)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WAC_1: &str =
+        "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";";
+
+    #[test]
+    fn operations() {
+        let epd = WAC_1.parse::<Epd>().unwrap();
+        assert_eq!(epd.id(), Some("WAC.001"));
+        assert_eq!(epd.operation("bm"), Some(&["Qg6".to_string()][..]));
+        assert_eq!(epd.moves("bm"), Ok(vec!["g3g6".parse().unwrap()]));
+        assert_eq!(epd.moves("am"), Ok(Vec::new()));
+        assert_eq!(epd.to_string(), WAC_1);
+
+        let epd = "4k3/8/8/8/8/8/8/4K2R w K - am O-O Kf1; c0 \"castles; bad\"; hmvc 7; fmvn 40;"
+            .parse::<Epd>()
+            .unwrap();
+        assert_eq!(epd.moves("am").unwrap().len(), 2);
+        assert_eq!(epd.operation("c0"), Some(&["castles; bad".to_string()][..]));
+        assert_eq!(epd.position.halfmove_clock, 7);
+        assert_eq!(epd.position.fullmove, 40);
+    }
+
+    #[test]
+    fn lenient_lines() {
+        // Move counters left in, and the final `;` missing.
+        let epd = "4k3/8/8/8/8/8/8/4K2R w K - 3 12 bm Rh8+"
+            .parse::<Epd>()
+            .unwrap();
+        assert_eq!(epd.position.fullmove, 12);
+        assert_eq!(epd.moves("bm").unwrap().len(), 1);
+
+        assert!("4k3/8/8/8 w".parse::<Epd>().is_err());
+        assert!("4k3/8/8/8/8/8/8/4K3 w - - id \"open"
+            .parse::<Epd>()
+            .is_err());
+        let suite = format!("# WAC\n\n{WAC_1}\n8/8/8/8/8/8/8/8 x - -\n");
+        assert!(parse_all(&suite).unwrap_err().starts_with("line 4:"));
+    }
+}
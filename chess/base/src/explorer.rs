@@ -0,0 +1,505 @@
+use crate::{
+    board::{movement::Move, position::Position},
+    book::{decode_move, encode_move},
+    pgn::{san_line, PgnGame},
+};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    io::{stdin, stdout, Write},
+};
+
+/// Where `--index` writes the database unless told otherwise.
+pub const DB: &str = "positions.db";
+
+const MAGIC: &[u8; 4] = b"BPDB";
+const VERSION: u32 = 1;
+/// Games kept as examples for each move.
+const EXAMPLES: usize = 3;
+/// Marks an unused example slot on disk.
+const NO_GAME: u32 = u32::MAX;
+
+/// The tags a game is listed by.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GameInfo {
+    pub white: String,
+    pub black: String,
+    pub result: String,
+    pub event: String,
+    pub date: String,
+}
+
+impl GameInfo {
+    fn of(pgn: &PgnGame) -> Self {
+        let tag = |name| {
+            pgn.tag(name)
+                .filter(|value| *value != "?")
+                .unwrap_or_default()
+                .to_string()
+        };
+        Self {
+            white: tag("White"),
+            black: tag("Black"),
+            result: pgn.result.clone(),
+            event: tag("Event"),
+            date: tag("Date"),
+        }
+    }
+}
+
+impl Display for GameInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = |name: &str| if name.is_empty() { "?" } else { name }.to_string();
+        write!(
+            f,
+            "{} - {} {}",
+            name(&self.white),
+            name(&self.black),
+            self.result
+        )?;
+        let place = [self.event.as_str(), self.date.as_str()]
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+        if !place.is_empty() {
+            write!(f, " ({})", place.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// How often a move was played from one position and how those games
+/// ended, stored under the position's Zobrist hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Record {
+    key: u64,
+    /// In Polyglot's encoding.
+    mv: u16,
+    /// White wins, draws and Black wins.
+    results: [u32; 3],
+    examples: [u32; EXAMPLES],
+}
+
+const RECORD_SIZE: usize = 8 + 2 + 4 * 3 + 4 * EXAMPLES;
+
+/// A move from a position with the games that went on with it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveStats {
+    pub mv: Move,
+    /// White wins, draws and Black wins; unfinished games are not counted.
+    pub results: [u32; 3],
+    /// Indices into [`Database::games`].
+    pub examples: Vec<u32>,
+}
+
+impl MoveStats {
+    #[must_use]
+    pub fn games(&self) -> u32 {
+        self.results.iter().sum()
+    }
+
+    /// White wins, draws and Black wins in percent.
+    #[must_use]
+    pub fn percentages(&self) -> [f64; 3] {
+        let games = f64::from(self.games().max(1));
+        self.results.map(|n| f64::from(n) * 100.0 / games)
+    }
+}
+
+/// Every position reached in a collection of games, with the moves played
+/// from it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Database {
+    games: Vec<GameInfo>,
+    /// Sorted by key and move.
+    records: Vec<Record>,
+}
+
+/// Reads the little-endian number at the front of `bytes`, moving past it.
+fn take<const N: usize>(bytes: &mut &[u8]) -> Result<[u8; N], String> {
+    if bytes.len() < N {
+        return Err("Database file is cut short".to_string());
+    }
+    let (front, rest) = bytes.split_at(N);
+    *bytes = rest;
+    Ok(front.try_into().unwrap())
+}
+
+impl Database {
+    /// Indexes the first `plies` half moves of each game, or all of them.
+    /// Games that do not replay are left out, and their numbers returned.
+    pub fn index(pgns: &[PgnGame], plies: Option<usize>) -> (Self, Vec<usize>) {
+        let mut database = Self::default();
+        let mut skipped = Vec::new();
+        let mut records = HashMap::<(u64, u16), Record>::new();
+        for (i, pgn) in pgns.iter().enumerate() {
+            let Ok((mut position, moves)) = pgn.replay() else {
+                skipped.push(i + 1);
+                continue;
+            };
+            let game = u32::try_from(database.games.len()).unwrap_or(NO_GAME);
+            database.games.push(GameInfo::of(pgn));
+            let result = match pgn.result.as_str() {
+                "1-0" => Some(0),
+                "1/2-1/2" => Some(1),
+                "0-1" => Some(2),
+                _ => None,
+            };
+            for mv in moves.into_iter().take(plies.unwrap_or(usize::MAX)) {
+                let (key, code) = (position.hash(), encode_move(&position, mv));
+                let record = records.entry((key, code)).or_insert(Record {
+                    key,
+                    mv: code,
+                    results: [0; 3],
+                    examples: [NO_GAME; EXAMPLES],
+                });
+                if let Some(result) = result {
+                    record.results[result] += 1;
+                }
+                if let Some(slot) = record.examples.iter_mut().find(|slot| **slot == NO_GAME) {
+                    *slot = game;
+                }
+                position.make_move(mv);
+            }
+        }
+        database.records = records.into_values().collect();
+        database
+            .records
+            .sort_by_key(|record| (record.key, record.mv));
+        (database, skipped)
+    }
+
+    #[must_use]
+    pub fn games(&self) -> &[GameInfo] {
+        &self.games
+    }
+
+    /// Number of position and move pairs.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// The moves played from `position`, most played first.
+    #[must_use]
+    pub fn query(&self, position: &Position) -> Vec<MoveStats> {
+        let key = position.hash();
+        let start = self.records.partition_point(|record| record.key < key);
+        let legal = position.legal_moves();
+        let mut moves = self.records[start..]
+            .iter()
+            .take_while(|record| record.key == key)
+            .filter_map(|record| {
+                let mv = decode_move(position, record.mv).filter(|mv| legal.contains(mv))?;
+                Some(MoveStats {
+                    mv,
+                    results: record.results,
+                    examples: record
+                        .examples
+                        .into_iter()
+                        .filter(|&game| game != NO_GAME)
+                        .collect(),
+                })
+            })
+            .collect::<Vec<_>>();
+        moves.sort_by_key(|stats| (std::cmp::Reverse(stats.games()), stats.mv.to_string()));
+        moves
+    }
+
+    /// The file layout: magic, version, the game count and each game's tags
+    /// as tab separated text after its byte length, then the record count
+    /// and records. Numbers are little-endian.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(VERSION.to_le_bytes());
+        bytes.extend((self.games.len() as u64).to_le_bytes());
+        for game in &self.games {
+            let line = [
+                &game.white,
+                &game.black,
+                &game.result,
+                &game.event,
+                &game.date,
+            ]
+            .map(|s| s.replace('\t', " "))
+            .join("\t");
+            bytes.extend((line.len() as u64).to_le_bytes());
+            bytes.extend(line.as_bytes());
+        }
+        bytes.extend((self.records.len() as u64).to_le_bytes());
+        for record in &self.records {
+            bytes.extend(record.key.to_le_bytes());
+            bytes.extend(record.mv.to_le_bytes());
+            for n in record.results.iter().chain(&record.examples) {
+                bytes.extend(n.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self, String> {
+        if take::<4>(&mut bytes)? != *MAGIC {
+            return Err("Not a position database".to_string());
+        }
+        let version = u32::from_le_bytes(take(&mut bytes)?);
+        if version != VERSION {
+            return Err(format!("Unsupported database version {version}"));
+        }
+        let count = |bytes: &mut &[u8]| -> Result<usize, String> {
+            usize::try_from(u64::from_le_bytes(take(bytes)?))
+                .map_err(|_| "Database file is corrupt".to_string())
+        };
+        let mut database = Self::default();
+        for _ in 0..count(&mut bytes)? {
+            let length = count(&mut bytes)?;
+            if bytes.len() < length {
+                return Err("Database file is cut short".to_string());
+            }
+            let (line, rest) = bytes.split_at(length);
+            bytes = rest;
+            let line = std::str::from_utf8(line).map_err(|e| e.to_string())?;
+            let mut fields = line.split('\t').map(ToString::to_string);
+            let mut field = || fields.next().unwrap_or_default();
+            database.games.push(GameInfo {
+                white: field(),
+                black: field(),
+                result: field(),
+                event: field(),
+                date: field(),
+            });
+        }
+        let records = count(&mut bytes)?;
+        if bytes.len() != records * RECORD_SIZE {
+            return Err("Database file has the wrong size".to_string());
+        }
+        for chunk in bytes.chunks_exact(RECORD_SIZE) {
+            let mut chunk = chunk;
+            let key = u64::from_le_bytes(take(&mut chunk)?);
+            let mv = u16::from_le_bytes(take(&mut chunk)?);
+            let mut number = || take(&mut chunk).map(u32::from_le_bytes);
+            let results = [number()?, number()?, number()?];
+            let mut examples = [NO_GAME; EXAMPLES];
+            for example in &mut examples {
+                *example = number()?;
+            }
+            database.records.push(Record {
+                key,
+                mv,
+                results,
+                examples,
+            });
+        }
+        database
+            .records
+            .sort_by_key(|record| (record.key, record.mv));
+        Ok(database)
+    }
+
+    pub fn open(path: &str) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("Could not read {path}: {e}"))?;
+        Self::from_bytes(&bytes).map_err(|e| format!("{path}: {e}"))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_bytes()).map_err(|e| format!("Could not write {path}: {e}"))
+    }
+
+    /// The moves from `position` as a table, numbered for picking, with a
+    /// few of the games that reached it.
+    #[must_use]
+    pub fn table(&self, position: &Position) -> String {
+        let moves = self.query(position);
+        if moves.is_empty() {
+            return "No games reached this position".to_string();
+        }
+        let mut lines = vec![format!(
+            "    {:<8}{:>7}  {:>6} {:>6} {:>6}",
+            "Move", "Games", "White", "Draw", "Black"
+        )];
+        for (i, stats) in moves.iter().enumerate() {
+            let [white, draw, black] = stats.percentages();
+            lines.push(format!(
+                "{:>2}. {:<8}{:>7}  {white:>5.1}% {draw:>5.1}% {black:>5.1}%",
+                i + 1,
+                position.san(stats.mv),
+                stats.games()
+            ));
+        }
+        let mut examples = moves
+            .iter()
+            .flat_map(|stats| stats.examples.iter().copied())
+            .collect::<Vec<_>>();
+        examples.sort_unstable();
+        examples.dedup();
+        lines.push("Games:".to_string());
+        for &game in examples.iter().take(EXAMPLES) {
+            if let Some(info) = usize::try_from(game).ok().and_then(|i| self.games.get(i)) {
+                lines.push(format!("  {info}"));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+/// Lets the player walk through the database from `start`, a move at a
+/// time, until they quit. Moves can be typed or picked by their number.
+pub fn explore(database: &Database, start: &Position, flipped: bool) {
+    let mut line: Vec<Move> = Vec::new();
+    let mut message = None;
+    loop {
+        let mut position = start.clone();
+        for &mv in &line {
+            position.make_move(mv);
+        }
+        position.board.print(flipped);
+        if !line.is_empty() {
+            println!("{}", san_line(start, &line));
+        }
+        println!("{}", database.table(&position));
+        if let Some(message) = message.take() {
+            println!("{message}");
+        }
+        print!("explore (move, number, back, q) >>> ");
+        stdout().flush().unwrap();
+        let mut input = String::new();
+        match stdin().read_line(&mut input) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
+        }
+        match input.trim() {
+            "q" | "quit" => return,
+            "back" | "b" => {
+                if line.pop().is_none() {
+                    message = Some("Already at the start".to_string());
+                }
+            }
+            text => {
+                let picked = text.parse::<usize>().map_or_else(
+                    |_| position.parse_move(text),
+                    |n| {
+                        database
+                            .query(&position)
+                            .get(n.wrapping_sub(1))
+                            .map(|stats| stats.mv)
+                            .ok_or_else(|| format!("No move numbered {n}"))
+                    },
+                );
+                match picked {
+                    Ok(mv) => line.push(mv),
+                    Err(e) => message = Some(e),
+                }
+            }
+        }
+    }
+}
+
+/// Entry point for `--index <games.pgn> [db] [plies]`; returns the process
+/// exit code.
+pub fn main(args: &[String]) -> i32 {
+    let result = (|| {
+        let [pgn, rest @ ..] = args else {
+            return Err("usage: base --index <games.pgn> [db] [plies]".to_string());
+        };
+        let out = rest.first().map_or(DB, String::as_str);
+        let plies = rest
+            .get(1)
+            .map(|n| n.parse().map_err(|_| format!("Invalid ply count '{n}'")))
+            .transpose()?;
+        let text =
+            std::fs::read_to_string(pgn).map_err(|e| format!("Could not read {pgn}: {e}"))?;
+        let (database, skipped) = Database::index(&PgnGame::parse_all(&text)?, plies);
+        if !skipped.is_empty() {
+            let numbers = skipped.iter().map(ToString::to_string).collect::<Vec<_>>();
+            eprintln!("Skipped games that did not replay: {}", numbers.join(", "));
+        }
+        database.save(out)?;
+        println!(
+            "Indexed {} games, {} positions and moves, into {out}",
+            database.games().len(),
+            database.len()
+        );
+        Ok(())
+    })();
+    result.map_or_else(
+        |e| {
+            eprintln!("{e}");
+            1
+        },
+        |()| 0,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAMES: &str = "
+[White \"Ann\"]
+[Black \"Bob\"]
+[Event \"Club\"]
+[Result \"1-0\"]
+1. e4 e5 2. Nf3 Nc6 1-0
+
+[White \"Bob\"]
+[Black \"Ann\"]
+[Result \"1/2-1/2\"]
+1. e4 c5 2. Nf3 1/2-1/2
+
+[White \"Cy\"]
+[Black \"Ann\"]
+[Result \"0-1\"]
+1. d4 d5 0-1
+
+[White \"Cy\"]
+[Black \"Bob\"]
+[Result \"*\"]
+1. e4 e5 *
+
+[Result \"1-0\"]
+1. e5 1-0
+";
+
+    #[test]
+    fn indexes_and_queries() {
+        let (database, skipped) = Database::index(&PgnGame::parse_all(GAMES).unwrap(), None);
+        assert_eq!(skipped, [5]);
+        assert_eq!(database.games().len(), 4);
+        let database = Database::from_bytes(&database.to_bytes()).unwrap();
+
+        let start = Position::default();
+        let moves = database.query(&start);
+        assert_eq!(moves.len(), 2);
+        assert_eq!(moves[0].mv, "e2e4".parse().unwrap());
+        // The unfinished game counts as an example but not in the results.
+        assert_eq!(moves[0].results, [1, 1, 0]);
+        assert_eq!(moves[0].examples, [0, 1, 3]);
+        assert_eq!(moves[1].results, [0, 0, 1]);
+
+        // Only the first game went on after 1. e4 e5.
+        let mut e4_e5 = start;
+        e4_e5.play("e4").unwrap();
+        e4_e5.play("e5").unwrap();
+        assert_eq!(database.query(&e4_e5)[0].examples, [0]);
+        assert_eq!(database.games()[0].to_string(), "Ann - Bob 1-0 (Club)");
+
+        let plies = Database::index(&PgnGame::parse_all(GAMES).unwrap(), Some(1)).0;
+        assert!(plies.query(&e4_e5).is_empty());
+        assert!(Database::from_bytes(b"BPDB").is_err());
+        assert!(Database::from_bytes(&database.to_bytes()[..30]).is_err());
+    }
+
+    #[test]
+    fn draws_a_table() {
+        let (database, _) = Database::index(&PgnGame::parse_all(GAMES).unwrap(), None);
+        let table = database.table(&Position::default());
+        let lines = table.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "    Move      Games   White   Draw  Black");
+        assert_eq!(lines[1], " 1. e4            2   50.0%  50.0%   0.0%");
+        assert_eq!(lines[2], " 2. d4            1    0.0%   0.0% 100.0%");
+        assert_eq!(lines[3], "Games:");
+        assert_eq!(lines.len(), 4 + EXAMPLES);
+        let empty = Position::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(database.table(&empty), "No games reached this position");
+    }
+}
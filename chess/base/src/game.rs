@@ -0,0 +1,1033 @@
+pub mod command;
+pub mod editor;
+pub mod mouse;
+pub mod net;
+pub mod panel;
+pub mod save;
+pub mod server;
+pub mod viewer;
+
+use self::{
+    command::{Command, Start, HELP},
+    editor::Exit,
+    mouse::Input,
+    viewer::Viewer,
+};
+use crate::{
+    bitbase::{self, Bitbases, Material, Wdl},
+    board::{
+        chess960,
+        movement::Move,
+        position::Position,
+        variant::{side, Variant, VariantEnd},
+    },
+    book::Book,
+    eco::{self, Opening},
+    engine::{
+        mate::{Solution, Solver},
+        mate_moves, Engine, Line, MAX_DEPTH, MAX_LINES,
+    },
+    explorer::{self, Database},
+    pgn::{san_line, PgnGame, PgnMove},
+    piece::Color,
+    review,
+    rng::Rng,
+};
+use std::{
+    fmt::Display,
+    io::{stdin, stdout, Read, Write},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use termion::{clear, cursor, raw::IntoRawMode};
+
+/// How a finished game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Checkmate(Color),
+    Resignation(Color),
+    Stalemate,
+    DrawAgreed,
+    FiftyMoves,
+    Repetition,
+    InsufficientMaterial,
+    /// A variant's own goal, such as a third check.
+    Variant(Color, VariantEnd),
+    /// The loser left a server game unfinished.
+    Abandoned(Color),
+    /// A result read from a game record that does not say how it came
+    /// about: the winner, or `None` for a draw.
+    Recorded(Option<Color>),
+}
+
+impl Outcome {
+    #[must_use]
+    pub const fn winner(self) -> Option<Color> {
+        match self {
+            Self::Checkmate(color)
+            | Self::Resignation(color)
+            | Self::Variant(color, _)
+            | Self::Abandoned(color)
+            | Self::Recorded(Some(color)) => Some(color),
+            _ => None,
+        }
+    }
+
+    /// The PGN result string, e.g. `1-0`.
+    #[must_use]
+    pub const fn result(self) -> &'static str {
+        match self.winner() {
+            Some(Color::White) => "1-0",
+            Some(Color::Black) => "0-1",
+            None => "1/2-1/2",
+        }
+    }
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Checkmate(winner) => write!(f, "Checkmate, {winner} wins"),
+            Self::Resignation(winner) => write!(f, "{} resigns, {winner} wins", winner.opposite()),
+            Self::Stalemate => write!(f, "Draw by stalemate"),
+            Self::DrawAgreed => write!(f, "Draw agreed"),
+            Self::FiftyMoves => write!(f, "Draw by the fifty move rule"),
+            Self::Repetition => write!(f, "Draw by threefold repetition"),
+            Self::InsufficientMaterial => write!(f, "Draw by insufficient material"),
+            Self::Variant(winner, end) => write!(f, "{end}, {winner} wins"),
+            Self::Abandoned(winner) => write!(f, "{} left, {winner} wins", winner.opposite()),
+            Self::Recorded(Some(winner)) => write!(f, "{winner} wins"),
+            Self::Recorded(None) => write!(f, "Drawn"),
+        }
+    }
+}
+
+/// Thinking time each side has used so far.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Clocks {
+    pub white: Duration,
+    pub black: Duration,
+}
+
+impl Clocks {
+    pub fn add(&mut self, color: Color, elapsed: Duration) {
+        match color {
+            Color::White => self.white += elapsed,
+            Color::Black => self.black += elapsed,
+        }
+    }
+}
+
+impl Display for Clocks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let clock = |d: Duration| {
+            let s = d.as_secs();
+            format!("{}:{:02}:{:02}", s / 3600, s / 60 % 60, s % 60)
+        };
+        write!(
+            f,
+            "White {} | Black {}",
+            clock(self.white),
+            clock(self.black)
+        )
+    }
+}
+
+/// A move that was played, with what is needed to take it back.
+#[derive(Debug, Clone)]
+struct Ply {
+    mv: Move,
+    san: String,
+    before: Position,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Game {
+    start: Position,
+    position: Position,
+    history: Vec<Ply>,
+    undone: Vec<Move>,
+    flipped: bool,
+    /// Whether moves can only be typed, with the mouse ignored.
+    mouse_off: bool,
+    white: String,
+    black: String,
+    clocks: Clocks,
+    engine: Engine,
+    /// File the game was last saved to or loaded from.
+    path: Option<String>,
+    draw_offer: Option<Color>,
+    outcome: Option<Outcome>,
+    message: Option<String>,
+}
+
+impl Game {
+    #[must_use]
+    pub fn from_position(position: Position) -> Self {
+        Self {
+            start: position.clone(),
+            position,
+            ..Default::default()
+        }
+    }
+
+    /// Starts from the PGN's setup and replays its main line.
+    pub fn from_pgn(pgn: &PgnGame) -> Result<Self, String> {
+        let (start, moves) = pgn.replay()?;
+        let mut game = Self::from_position(start);
+        game.white = pgn.tag("White").unwrap_or_default().to_string();
+        game.black = pgn.tag("Black").unwrap_or_default().to_string();
+        for mv in moves {
+            game.push(mv);
+        }
+        if game.outcome.is_none() {
+            game.outcome = match pgn.result.as_str() {
+                "1-0" => Some(Outcome::Recorded(Some(Color::White))),
+                "0-1" => Some(Outcome::Recorded(Some(Color::Black))),
+                "1/2-1/2" => Some(Outcome::Recorded(None)),
+                _ => None,
+            };
+        }
+        Ok(game)
+    }
+
+    #[must_use]
+    pub const fn position(&self) -> &Position {
+        &self.position
+    }
+
+    #[must_use]
+    pub const fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    pub fn play(&mut self) {
+        loop {
+            self.show();
+
+            let mut line = String::with_capacity(10);
+            let mover = self.position.turn;
+            let started = Instant::now();
+            let input =
+                if self.mouse_off || !termion::is_tty(&stdin()) || !termion::is_tty(&stdout()) {
+                    print!("{mover} move >>> ");
+                    stdout().flush().unwrap();
+                    None
+                } else {
+                    Some(mouse::read(&self.position, self.flipped))
+                };
+            let command = match input {
+                Some(Input::Move(mv)) => Ok(Command::Move(self.position.san(mv))),
+                Some(Input::Quit) => Ok(Command::Quit),
+                Some(Input::Key('\n')) => continue,
+                Some(Input::Key(c)) => {
+                    line.push(c);
+                    Self::read_command(&mut line)
+                }
+                None => Self::read_command(&mut line),
+            };
+
+            match command {
+                Ok(Command::Quit) => {
+                    match self.autosave() {
+                        Some(Ok(path)) => println!("Saved to {path}"),
+                        Some(Err(e)) => println!("{e}"),
+                        None => {}
+                    }
+                    println!("END");
+                    break;
+                }
+                Ok(command) => {
+                    let played = self.history.len();
+                    self.execute(command);
+                    if self.history.len() > played {
+                        self.clocks.add(mover, started.elapsed());
+                    }
+                }
+                Err(e) => self.message = Some(e),
+            }
+        }
+    }
+
+    /// Reads the rest of a typed line onto `line` and parses it.
+    fn read_command(line: &mut String) -> Result<Command, String> {
+        match stdin().read_line(line) {
+            Ok(0) | Err(_) if line.is_empty() => Ok(Command::Quit),
+            _ => line.parse(),
+        }
+    }
+
+    /// Draws the board with the last message, the clocks and how the game stands.
+    fn show(&mut self) {
+        self.position.board.print(self.flipped);
+        self.print_panel();
+        if let Some(message) = self.message.take() {
+            println!("{message}");
+        }
+        println!("{}", self.clocks);
+        if let Some(opening) = self.opening() {
+            println!("{} {}", opening.code, opening.name);
+        }
+        if let Some(status) = self.variant_status() {
+            println!("{status}");
+        }
+        if let Some(outcome) = self.outcome {
+            println!("{outcome} ({})", outcome.result());
+        } else if self.position.in_check() {
+            println!("Check!");
+        }
+    }
+
+    /// The deepest named opening the game has passed through, if it began
+    /// from the standard setup.
+    #[must_use]
+    pub fn opening(&self) -> Option<&'static Opening> {
+        if self.start != Position::default() {
+            return None;
+        }
+        let moves = self.history.iter().map(|ply| ply.mv).collect::<Vec<_>>();
+        eco::openings().classify_line(&self.start, &moves)
+    }
+
+    /// Draws the move list, taken pieces and material beside the board, or
+    /// a summary under it on a narrow terminal.
+    fn print_panel(&self) {
+        let sans = self
+            .history
+            .iter()
+            .map(|ply| ply.san.clone())
+            .collect::<Vec<_>>();
+        let layout = panel::layout(
+            &self.start,
+            &self.position,
+            &sans,
+            [&self.white, &self.black],
+            self.flipped,
+            panel::terminal_width(),
+        );
+        match layout {
+            panel::Layout::Beside(lines) => {
+                print!("{}", cursor::Save);
+                for (row, line) in (1..).zip(lines) {
+                    print!("{}{line}", cursor::Goto(panel::COLUMN, row));
+                }
+                print!("{}", cursor::Restore);
+            }
+            panel::Layout::Below(lines) => {
+                for line in lines {
+                    println!("{line}");
+                }
+            }
+        }
+    }
+
+    /// A line on the variant's extra state: checks given or pieces in hand.
+    fn variant_status(&self) -> Option<String> {
+        let position = &self.position;
+        match position.variant {
+            Variant::ThreeCheck => Some(format!(
+                "Checks: White {} | Black {}",
+                position.checks[0], position.checks[1]
+            )),
+            Variant::Crazyhouse => {
+                let pocket = |color| {
+                    let letters = position.pockets[side(color)].letters(color);
+                    if letters.is_empty() {
+                        "-".to_string()
+                    } else {
+                        letters
+                    }
+                };
+                Some(format!(
+                    "In hand: White {} | Black {}",
+                    pocket(Color::White),
+                    pocket(Color::Black)
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    /// Runs one command, leaving anything to report in `self.message`.
+    pub fn execute(&mut self, command: Command) {
+        let message = match command {
+            Command::Help => Ok(HELP.to_string()),
+            Command::New(start) => self.restart(start),
+            Command::Quit => Ok(String::new()),
+            Command::Move(text) => self.try_move(&text),
+            Command::Undo => self.undo(),
+            Command::Redo => self.redo(),
+            Command::Resign => self.resign(self.position.turn),
+            Command::Draw => self.draw(self.position.turn),
+            Command::Flip => {
+                self.flipped = !self.flipped;
+                Ok(String::new())
+            }
+            Command::Mouse => {
+                self.mouse_off = !self.mouse_off;
+                Ok(if self.mouse_off {
+                    "Mouse off, type moves at the prompt".to_string()
+                } else {
+                    "Mouse on, click or drag pieces to move them".to_string()
+                })
+            }
+            Command::Setup => Ok(self.setup()),
+            Command::View(path) => self.view(path.as_deref()),
+            Command::Fen => Ok(self.position.fen()),
+            Command::Pgn => Ok(self.to_pgn().write()),
+            Command::Moves => Ok(self.legal_moves_san().join(" ")),
+            Command::Hint => Ok(self.hint()),
+            Command::Analyze(count) => Ok(self.analyze(count)),
+            Command::Review(path) => self.review(path.as_deref()),
+            Command::Explore(path) => self.explore(path.as_deref()),
+            Command::Mate(moves, fen) => self.mate(moves, fen.as_deref()),
+            Command::Probe(fen) => self.probe(fen.as_deref()),
+            Command::Book(None) => self.book_moves(),
+            Command::Book(Some(path)) => Book::open(&path).map(|mut book| {
+                let message = format!("Loaded {} book entries from {path}", book.len());
+                if let Some(old) = &self.engine.book {
+                    book.depth = old.depth;
+                }
+                self.engine.book = Some(book);
+                message
+            }),
+            Command::BookDepth(depth) => match &mut self.engine.book {
+                Some(book) => {
+                    book.depth = depth;
+                    Ok(format!("The book is used up to move {depth}"))
+                }
+                None => Err("No book loaded, use 'book <file>'".to_string()),
+            },
+            Command::Player(color, name) => {
+                match color {
+                    Color::White => self.white = name,
+                    Color::Black => self.black = name,
+                }
+                Ok(String::new())
+            }
+            Command::Save(path) => self.save(&path).map(|()| format!("Saved to {path}")),
+            Command::Load(path) => Self::open(&path).map(|game| {
+                *self = Self {
+                    engine: std::mem::take(&mut self.engine),
+                    mouse_off: self.mouse_off,
+                    ..game
+                };
+                format!("Loaded {path}")
+            }),
+        };
+        self.message = match message {
+            Ok(m) if m.is_empty() => None,
+            Ok(m) | Err(m) => Some(m),
+        };
+    }
+
+    /// Starts a fresh game, keeping the players, engine and board orientation.
+    fn restart(&mut self, start: Start) -> Result<String, String> {
+        let (position, message) = match start {
+            Start::Standard => (Position::default(), "New game".to_string()),
+            Start::Variant(variant) => (
+                Position {
+                    variant,
+                    ..Position::default()
+                },
+                format!("New {variant} game"),
+            ),
+            Start::Chess960(number) => {
+                let number = number.unwrap_or_else(|| {
+                    u16::try_from(Rng::default().below(u64::from(chess960::COUNT))).unwrap_or(0)
+                });
+                (
+                    Position::chess960(number)?,
+                    format!("New Chess960 game, position {number}"),
+                )
+            }
+        };
+        let game = Self {
+            white: std::mem::take(&mut self.white),
+            black: std::mem::take(&mut self.black),
+            engine: std::mem::take(&mut self.engine),
+            flipped: self.flipped,
+            mouse_off: self.mouse_off,
+            ..Self::from_position(position)
+        };
+        *self = game;
+        Ok(message)
+    }
+
+    /// Lets the player edit the position, starting a new game from it
+    /// unless they leave the editor.
+    fn setup(&mut self) -> String {
+        match editor::edit(self.position.clone(), self.flipped) {
+            Exit::Play(position) => {
+                let fen = position.fen();
+                *self = Self {
+                    white: std::mem::take(&mut self.white),
+                    black: std::mem::take(&mut self.black),
+                    engine: std::mem::take(&mut self.engine),
+                    flipped: self.flipped,
+                    mouse_off: self.mouse_off,
+                    ..Self::from_position(position)
+                };
+                format!("New game from {fen}")
+            }
+            Exit::Cancel => String::new(),
+        }
+    }
+
+    /// Steps through the first game in a PGN file, or this one.
+    fn view(&self, path: Option<&str>) -> Result<String, String> {
+        let pgn = match path {
+            Some(path) => {
+                let text = std::fs::read_to_string(path)
+                    .map_err(|e| format!("Could not read {path}: {e}"))?;
+                PgnGame::parse_all(&text)?
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| format!("No games in {path}"))?
+            }
+            None => self.to_pgn(),
+        };
+        viewer::view(Viewer::new(pgn, self.flipped)?);
+        Ok(String::new())
+    }
+
+    /// Ends the game by a decision from outside the rules, such as an
+    /// adjudicated result.
+    pub const fn end(&mut self, outcome: Outcome) {
+        self.outcome = Some(outcome);
+    }
+
+    /// Plays a move typed in algebraic or coordinate form.
+    pub fn try_move(&mut self, text: &str) -> Result<String, String> {
+        if let Some(outcome) = self.outcome {
+            return Err(format!("The game is over: {outcome}"));
+        }
+        let mv = self.position.parse_move(text)?;
+        if self.draw_offer != Some(self.position.turn) {
+            self.draw_offer = None;
+        }
+        self.undone.clear();
+        self.push(mv);
+        Ok(String::new())
+    }
+
+    fn push(&mut self, mv: Move) {
+        let before = self.position.clone();
+        let san = before.san(mv);
+        self.position.make_move(mv);
+        self.history.push(Ply { mv, san, before });
+        self.outcome = self.detect_outcome();
+    }
+
+    fn detect_outcome(&self) -> Option<Outcome> {
+        let position = &self.position;
+        if let Some((winner, end)) = position.variant_end() {
+            return Some(Outcome::Variant(winner, end));
+        }
+        if position.is_checkmate() {
+            return Some(Outcome::Checkmate(position.turn.opposite()));
+        }
+        if position.is_stalemate() {
+            return Some(Outcome::Stalemate);
+        }
+        if position.insufficient_material() {
+            return Some(Outcome::InsufficientMaterial);
+        }
+        if position.halfmove_clock >= 100 {
+            return Some(Outcome::FiftyMoves);
+        }
+        let repeats = self
+            .history
+            .iter()
+            .filter(|ply| ply.before.same_as(position))
+            .count();
+        (repeats >= 2).then_some(Outcome::Repetition)
+    }
+
+    fn undo(&mut self) -> Result<String, String> {
+        let ply = self.history.pop().ok_or("Nothing to undo")?;
+        self.position = ply.before;
+        self.undone.push(ply.mv);
+        self.outcome = None;
+        self.draw_offer = None;
+        Ok(format!("Took back {}", ply.san))
+    }
+
+    fn redo(&mut self) -> Result<String, String> {
+        let mv = self.undone.pop().ok_or("Nothing to redo")?;
+        self.push(mv);
+        Ok(String::new())
+    }
+
+    fn resign(&mut self, color: Color) -> Result<String, String> {
+        if self.outcome.is_some() {
+            return Err("The game is already over".to_string());
+        }
+        self.outcome = Some(Outcome::Resignation(color.opposite()));
+        Ok(String::new())
+    }
+
+    /// Offers a draw on behalf of `color`, or accepts the other side's offer.
+    fn draw(&mut self, color: Color) -> Result<String, String> {
+        if self.outcome.is_some() {
+            return Err("The game is already over".to_string());
+        }
+        match self.draw_offer {
+            Some(offer) if offer != color => {
+                self.outcome = Some(Outcome::DrawAgreed);
+                Ok(String::new())
+            }
+            Some(_) => Err("You have already offered a draw".to_string()),
+            None => {
+                self.draw_offer = Some(color);
+                Ok(format!(
+                    "{color} offers a draw. {} can type 'draw' to accept",
+                    color.opposite()
+                ))
+            }
+        }
+    }
+
+    fn hint(&mut self) -> String {
+        if let Some(mv) = self.engine.book_move(&self.position) {
+            return format!("Hint: {} (book)", self.position.san(mv));
+        }
+        self.bitbases();
+        let result = self.engine.search(&self.position);
+        result.best.map_or_else(
+            || "No legal moves".to_string(),
+            |mv| {
+                format!(
+                    "Hint: {} ({:+.2})",
+                    self.position.san(mv),
+                    f64::from(result.score) / 100.0
+                )
+            },
+        )
+    }
+
+    /// Shows the engine's `count` best lines under the board, deepening
+    /// until a key is pressed, and leaves the last ones as the message.
+    fn analyze(&mut self, count: usize) -> String {
+        self.bitbases();
+        let limits = (self.engine.depth, self.engine.time, self.engine.multipv);
+        (self.engine.depth, self.engine.time, self.engine.multipv) =
+            (MAX_DEPTH, None, count.clamp(1, MAX_LINES));
+        let stop = self.engine.stop_handle();
+        let (engine, position) = (&mut self.engine, &self.position);
+        position.board.print(self.flipped);
+        // Raw mode lets a single key end the analysis; without a terminal
+        // it ends at the next line of input instead.
+        let raw = stdout().into_raw_mode().ok();
+        print!("{}", cursor::Save);
+        let result = std::thread::scope(|scope| {
+            let search = scope.spawn(|| {
+                engine.search_with(position, |result| {
+                    // Redraw only the lines: asking the terminal where the
+                    // cursor is would race the key press for stdin.
+                    print!("{}{}", cursor::Restore, clear::AfterCursor);
+                    for line in &result.lines {
+                        print!("{}\r\n", line_text(position, line));
+                    }
+                    print!("Depth {}, press a key to stop\r\n", result.depth);
+                    stdout().flush().unwrap();
+                })
+            });
+            let _ = stdin().lock().read(&mut [0]);
+            stop.stop();
+            search.join().expect("the search thread panicked")
+        });
+        drop(raw);
+        stop.reset();
+        (self.engine.depth, self.engine.time, self.engine.multipv) = limits;
+        if result.lines.is_empty() {
+            return "No legal moves".to_string();
+        }
+        let lines = result.lines.iter().map(|line| line_text(position, line));
+        format!(
+            "Depth {}\n{}",
+            result.depth,
+            lines.collect::<Vec<_>>().join("\n")
+        )
+    }
+
+    /// Judges every move played so far, leaving the summary as the message.
+    fn review(&self, path: Option<&str>) -> Result<String, String> {
+        if self.history.is_empty() {
+            return Err("No moves to review".to_string());
+        }
+        let review = review::review(&self.to_pgn(), review::QUICK, |done, total| {
+            print!("\rReviewing position {done}/{total}");
+            stdout().flush().unwrap();
+        })?;
+        println!();
+        let summary = review.to_string().trim_end().to_string();
+        let Some(path) = path else {
+            return Ok(summary);
+        };
+        std::fs::write(path, review.pgn.write())
+            .map_err(|e| format!("Could not save {path}: {e}"))?;
+        Ok(format!("{summary}\nSaved the annotated game to {path}"))
+    }
+
+    /// Browses a position database from here until the player quits it.
+    fn explore(&self, path: Option<&str>) -> Result<String, String> {
+        let database = Database::open(path.unwrap_or(explorer::DB))?;
+        explorer::explore(&database, &self.position, self.flipped);
+        Ok(String::new())
+    }
+
+    /// Proves a forced mate here or in another position, or that there is
+    /// none in time, unless the solver's budget runs out or a key is
+    /// pressed first.
+    fn mate(&self, moves: u32, fen: Option<&str>) -> Result<String, String> {
+        let position = fen.map_or_else(|| Ok(self.position.clone()), Position::from_fen)?;
+        let mut solver = Solver::default();
+        let stop = Arc::clone(&solver.stop);
+        let solution = match stdout().into_raw_mode() {
+            Ok(raw) if termion::is_tty(&stdin()) => {
+                print!("Looking for a mate in {moves}, press a key to stop\r\n");
+                stdout().flush().unwrap();
+                let solution = std::thread::scope(|scope| {
+                    let search = scope.spawn(|| {
+                        let solution = solver.solve(&position, moves);
+                        // Asking where the cursor is makes the terminal
+                        // answer on stdin, which ends the wait for a key.
+                        print!("\x1b[6n");
+                        stdout().flush().unwrap();
+                        solution
+                    });
+                    let mut stdin = stdin().lock();
+                    let mut byte = [0];
+                    let _ = stdin.read(&mut byte);
+                    stop.stop();
+                    let solution = search.join().expect("the mate search panicked");
+                    // Whether the key or the answer came first, the rest of
+                    // the answer is still to be read, and it ends in 'R'.
+                    while stdin.read(&mut byte).is_ok_and(|read| read == 1) && byte[0] != b'R' {}
+                    solution
+                });
+                drop(raw);
+                solution
+            }
+            _ => solver.solve(&position, moves),
+        };
+        Ok(match solution {
+            Solution::Mate(line) => format!(
+                "Mate in {}: {}",
+                line.len().div_ceil(2),
+                san_line(&position, &line)
+            ),
+            Solution::NoMate => format!("No forced mate in {moves}"),
+            Solution::Unknown => format!(
+                "No mate in {moves} found in {} positions, nor ruled out",
+                solver.nodes
+            ),
+        })
+    }
+
+    /// The engine's bitbases, reading whatever is cached on disk the first time.
+    fn bitbases(&mut self) -> &mut Bitbases {
+        let bitbases = self
+            .engine
+            .bitbases
+            .get_or_insert_with(|| Arc::new(Bitbases::open(bitbase::DIR)));
+        Arc::make_mut(bitbases)
+    }
+
+    /// Looks a position up in the bitbases, building the table if it is missing.
+    fn probe(&mut self, fen: Option<&str>) -> Result<String, String> {
+        let position = fen.map_or_else(|| Ok(self.position.clone()), Position::from_fen)?;
+        let (material, _) =
+            Material::of(&position).ok_or("Bitbases cover KPK, KRK, KQK and KBNK only")?;
+        self.bitbases().ensure(material, Some(bitbase::DIR))?;
+        let wdl = self
+            .bitbases()
+            .probe(&position)
+            .ok_or("Not a legal position")?;
+        let verdict = match wdl {
+            Wdl::Win => format!("{} wins", position.turn),
+            Wdl::Loss => format!("{} wins", position.turn.opposite()),
+            Wdl::Draw => "draw".to_string(),
+        };
+        Ok(format!("{material}: {verdict}"))
+    }
+
+    fn book_moves(&self) -> Result<String, String> {
+        let book = self
+            .engine
+            .book
+            .as_ref()
+            .ok_or("No book loaded, use 'book <file>'")?;
+        let moves = book.moves(&self.position);
+        if moves.is_empty() {
+            return Ok("Out of book".to_string());
+        }
+        let total = moves.iter().map(|&(_, w)| f64::from(w)).sum::<f64>();
+        Ok(moves
+            .into_iter()
+            .map(|(mv, weight)| {
+                format!(
+                    "{:<8}{:5.1}%",
+                    self.position.san(mv),
+                    f64::from(weight) * 100.0 / total
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    fn legal_moves_san(&self) -> Vec<String> {
+        let mut moves = self
+            .position
+            .legal_moves()
+            .into_iter()
+            .map(|mv| self.position.san(mv))
+            .collect::<Vec<_>>();
+        moves.sort();
+        moves
+    }
+
+    #[must_use]
+    pub fn to_pgn(&self) -> PgnGame {
+        let mut pgn = PgnGame {
+            moves: self
+                .history
+                .iter()
+                .map(|ply| PgnMove::new(&ply.san))
+                .collect(),
+            result: self.outcome.map_or("*", Outcome::result).to_string(),
+            ..Default::default()
+        };
+        for (tag, name) in [("White", &self.white), ("Black", &self.black)] {
+            if !name.is_empty() {
+                pgn.set_tag(tag, name.as_str());
+            }
+        }
+        if self.start.variant != Variant::Standard {
+            pgn.set_tag("Variant", self.start.variant.name());
+        } else if self.start.is_chess960() {
+            pgn.set_tag("Variant", "Chess960");
+        }
+        let setup = Position {
+            variant: self.start.variant,
+            ..Position::default()
+        };
+        if self.start != setup {
+            pgn.set_tag("SetUp", "1");
+            pgn.set_tag("FEN", self.start.fen());
+        }
+        if let Some(opening) = self.opening() {
+            pgn.set_tag("ECO", opening.code);
+            pgn.set_tag("Opening", opening.name);
+        }
+        pgn
+    }
+}
+
+/// A line of analysis in SAN, numbered from `position`, after its score
+/// for the side to move: `+0.35  1. e4 e5 2. Nf3` or `#2  12... Qd7 ...`.
+fn line_text(position: &Position, line: &Line) -> String {
+    let score = match mate_moves(line.score) {
+        Some(moves) if line.score > 0 => format!("#{moves:<5}"),
+        Some(moves) => format!("#-{:<4}", -moves),
+        None => format!("{:<+6.2}", f64::from(line.score) / 100.0),
+    };
+    if line.pv.is_empty() {
+        return score;
+    }
+    format!("{score} {}", san_line(position, &line.pv))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::MATE;
+
+    fn run(game: &mut Game, lines: &[&str]) {
+        for line in lines {
+            game.execute(line.parse().unwrap());
+        }
+    }
+
+    #[test]
+    fn analysis_lines() {
+        let position = Position::default();
+        let line = |score, moves: &[&str]| Line {
+            score,
+            pv: moves.iter().map(|mv| mv.parse().unwrap()).collect(),
+        };
+        assert_eq!(
+            line_text(&position, &line(35, &["e2e4", "e7e5", "g1f3"])),
+            "+0.35  1. e4 e5 2. Nf3"
+        );
+        let mut black = position.clone();
+        black.play("e4").unwrap();
+        assert_eq!(
+            line_text(&black, &line(-120, &["e7e5", "g1f3"])),
+            "-1.20  1... e5 2. Nf3"
+        );
+        assert_eq!(line_text(&position, &line(MATE - 3, &[])), "#2    ");
+        assert_eq!(
+            line_text(&black, &line(2 - MATE, &["f7f6"])),
+            "#-1    1... f6"
+        );
+    }
+
+    #[test]
+    fn mate_command() {
+        let mut game = Game::default();
+        run(&mut game, &["mate 1"]);
+        assert_eq!(game.message.as_deref(), Some("No forced mate in 1"));
+        run(
+            &mut game,
+            &["mate 3 4kb1r/p2n1ppp/4q3/4p1B1/4P3/1Q6/PPP2PPP/2KR4 w k - 1 16"],
+        );
+        assert_eq!(
+            game.message.as_deref(),
+            Some("Mate in 2: 16. Qb8+ Nxb8 17. Rd8#")
+        );
+    }
+
+    #[test]
+    fn bad_move_keeps_playing() {
+        let mut game = Game::default();
+        run(&mut game, &["e5"]);
+        assert!(game.message.as_deref().unwrap().contains("Illegal move"));
+        assert_eq!(game.position, Position::default());
+        run(&mut game, &["e4"]);
+        assert_eq!(game.position.turn, Color::Black);
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut game = Game::default();
+        run(&mut game, &["e4", "e5", "undo"]);
+        assert_eq!(game.history.len(), 1);
+        run(&mut game, &["redo"]);
+        assert_eq!(game.history.len(), 2);
+        run(&mut game, &["undo", "d5", "redo"]);
+        assert_eq!(game.message.as_deref(), Some("Nothing to redo"));
+        assert_eq!(game.history[1].san, "d5");
+    }
+
+    #[test]
+    fn draw_offer_and_resign() {
+        let mut game = Game::default();
+        run(&mut game, &["draw", "e4", "e5"]);
+        assert_eq!(game.draw_offer, None);
+        run(&mut game, &["draw", "Nf3", "draw"]);
+        assert_eq!(game.outcome, Some(Outcome::DrawAgreed));
+
+        let mut game = Game::default();
+        run(&mut game, &["e4", "resign"]);
+        assert_eq!(game.outcome, Some(Outcome::Resignation(Color::White)));
+        assert_eq!(game.to_pgn().result, "1-0");
+    }
+
+    #[test]
+    fn keeps_a_recorded_result() {
+        let read = |text: &str| Game::from_pgn(&PgnGame::parse_all(text).unwrap()[0]).unwrap();
+        // Lost on time, say; the record only has the result.
+        let game = read("1. e4 e5 2. Nf3 0-1");
+        assert_eq!(game.outcome, Some(Outcome::Recorded(Some(Color::Black))));
+        assert_eq!(game.outcome.unwrap().to_string(), "Black wins");
+        assert_eq!(game.to_pgn().result, "0-1");
+        assert_eq!(
+            read("1. e4 e5 1/2-1/2").outcome,
+            Some(Outcome::Recorded(None))
+        );
+        assert_eq!(read("1. e4 e5 *").outcome, None);
+        // A finished position still says how the game ended.
+        let game = read("1. f3 e5 2. g4 Qh4# 0-1");
+        assert_eq!(game.outcome, Some(Outcome::Checkmate(Color::Black)));
+    }
+
+    #[test]
+    fn detects_mate_and_repetition() {
+        let mut game = Game::default();
+        run(&mut game, &["f3", "e5", "g4", "Qh4"]);
+        assert_eq!(game.outcome, Some(Outcome::Checkmate(Color::Black)));
+        assert_eq!(game.to_pgn().moves[3].san, "Qh4#");
+
+        let mut game = Game::default();
+        run(
+            &mut game,
+            &["Nf3", "Nf6", "Ng1", "Ng8", "Nf3", "Nf6", "Ng1"],
+        );
+        assert_eq!(game.outcome, None);
+        run(&mut game, &["Ng8"]);
+        assert_eq!(game.outcome, Some(Outcome::Repetition));
+    }
+
+    #[test]
+    fn save_and_load() {
+        let path = std::env::temp_dir().join(format!("chess-save-{}.pgn", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let mut game = Game::default();
+        run(&mut game, &["e4", "c5", "Nf3", &format!("save {path}")]);
+        let mut loaded = Game::default();
+        run(&mut loaded, &[&format!("load {path}")]);
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(loaded.position, game.position);
+        assert_eq!(loaded.history.len(), 3);
+        run(&mut loaded, &["fen"]);
+        assert_eq!(loaded.message, Some(game.position.fen()));
+    }
+
+    #[test]
+    fn opening_tags() {
+        let mut game = Game::default();
+        assert_eq!(game.opening(), None);
+        run(&mut game, &["e4", "c5", "Nf3", "d6"]);
+        assert_eq!(game.opening().unwrap().code, "B50");
+        let pgn = game.to_pgn();
+        assert_eq!(pgn.tag("ECO"), Some("B50"));
+        assert_eq!(
+            pgn.tag("Opening"),
+            Some("Sicilian Defense: Modern Variations")
+        );
+
+        run(&mut game, &["new 960 97", "Ng3"]);
+        assert_eq!(game.to_pgn().tag("ECO"), None);
+    }
+
+    #[test]
+    fn chess960_game() {
+        let mut game = Game::default();
+        run(&mut game, &["player white Ann", "new 960 97"]);
+        assert_eq!(game.white, "Ann");
+        assert_eq!(game.position, Position::chess960(97).unwrap());
+        run(&mut game, &["Ng3", "Ng6", "O-O"]);
+        assert_eq!(game.message, None);
+
+        let pgn = game.to_pgn();
+        assert_eq!(pgn.tag("Variant"), Some("Chess960"));
+        let replayed = Game::from_pgn(&PgnGame::parse_all(&pgn.write()).unwrap()[0]).unwrap();
+        assert_eq!(replayed.position, game.position);
+    }
+
+    #[test]
+    fn variant_game() {
+        let mut game = Game::default();
+        run(
+            &mut game,
+            &["new koth", "d4", "e5", "Kd2", "Ke7", "Kd3", "Ke6"],
+        );
+        assert_eq!(game.outcome, None);
+        run(&mut game, &["Ke4"]);
+        assert_eq!(
+            game.outcome,
+            Some(Outcome::Variant(Color::White, VariantEnd::KingOfTheHill))
+        );
+        assert_eq!(game.outcome.unwrap().result(), "1-0");
+
+        let pgn = game.to_pgn();
+        assert_eq!(pgn.tag("Variant"), Some("King of the Hill"));
+        assert_eq!(pgn.tag("FEN"), None);
+        let replayed = Game::from_pgn(&pgn).unwrap();
+        assert_eq!(replayed.outcome, game.outcome);
+    }
+}
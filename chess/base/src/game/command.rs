@@ -0,0 +1,280 @@
+use crate::{
+    board::{chess960, variant::Variant},
+    engine::{mate, MAX_LINES},
+    piece::Color,
+};
+use std::str::FromStr;
+
+pub const HELP: &str = "\
+Commands:
+  <move>       play a move, e.g. e4, Nf3, exd5, O-O, e8=Q or e2e4
+  help         show this list
+  new [960 [n] | variant]
+               start again, from the standard setup, a Chess960 one, or
+               as threecheck, koth, atomic or crazyhouse
+  undo / redo  take back or replay a move
+  resign       give up the game
+  draw         offer a draw, or accept the one on the table
+  flip         turn the board around
+  mouse        stop or start moving pieces by clicking or dragging them
+  setup        put a position together on the board, then play from it
+  view [file]  step through this game, or the first in a PGN file, with
+               its variations and comments
+  fen          show the position as FEN
+  pgn          show the game so far as PGN
+  moves        list the legal moves
+  hint         ask the engine for a move; it does not ponder on your
+               time here, only when run with --uci
+  analyze [n]  show the engine's n best lines (3), until a key is pressed
+  review [file]
+               have the engine judge every move so far, and save the
+               annotated game to a PGN file
+  explore [db]
+               browse the moves played here in an indexed collection
+               of games (positions.db), one move at a time
+  mate <n> [fen]
+               find a forced mate in n moves (8 at most) here, or in
+               another position; a key press gives up
+  probe [fen]  look this position, or another, up in the endgame bitbases
+  book [file]  list book moves here, or load a Polyglot book
+  book depth <n>
+               only use the book up to move n
+  player <white|black> <name>
+               set a player's name
+  save <file>  write the game to a PGN file
+  load <file>  read a game from a PGN or FEN file
+  q            quit, saving an unfinished game first";
+
+/// Which setup a new game starts from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Start {
+    Standard,
+    /// A numbered Chess960 position, or a random one.
+    Chess960(Option<u16>),
+    Variant(Variant),
+}
+
+/// A line typed at the game prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Help,
+    New(Start),
+    Quit,
+    Undo,
+    Redo,
+    Resign,
+    Draw,
+    Flip,
+    /// Switch moving pieces with the mouse off or back on.
+    Mouse,
+    /// Edit the position by hand in the board editor.
+    Setup,
+    /// Step through a game, this one unless given a PGN file.
+    View(Option<String>),
+    Fen,
+    Pgn,
+    Moves,
+    Hint,
+    /// Show this many of the engine's best lines.
+    Analyze(usize),
+    /// Judge the game's moves, saving the annotated game if given a file.
+    Review(Option<String>),
+    /// Browse a position database, the default one unless given a file.
+    Explore(Option<String>),
+    /// Look for a mate in this many moves, here or in the given FEN.
+    Mate(u32, Option<String>),
+    Probe(Option<String>),
+    Book(Option<String>),
+    BookDepth(u16),
+    Player(Color, String),
+    Save(String),
+    Load(String),
+    Move(String),
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (word, argument) = s
+            .split_once(char::is_whitespace)
+            .map_or((s, ""), |(word, argument)| (word, argument.trim()));
+        let file = |name: &str| {
+            if argument.is_empty() {
+                Err(format!("usage: {name} <file>"))
+            } else {
+                Ok(argument.to_string())
+            }
+        };
+
+        match word {
+            "" => Err("Type a move or 'help'".to_string()),
+            "help" | "?" => Ok(Self::Help),
+            "new" => match argument.split_whitespace().collect::<Vec<_>>().as_slice() {
+                [] => Ok(Self::New(Start::Standard)),
+                ["960"] => Ok(Self::New(Start::Chess960(None))),
+                ["960", n] => n
+                    .parse()
+                    .ok()
+                    .filter(|&n| n < chess960::COUNT)
+                    .map(|n| Self::New(Start::Chess960(Some(n))))
+                    .ok_or_else(|| format!("Chess960 positions are numbered 0 to 959, not '{n}'")),
+                [variant] => variant.parse().map(|v| Self::New(Start::Variant(v))),
+                _ => Err("usage: new [960 [n] | variant]".to_string()),
+            },
+            "q" | "quit" => Ok(Self::Quit),
+            "undo" => Ok(Self::Undo),
+            "redo" => Ok(Self::Redo),
+            "resign" => Ok(Self::Resign),
+            "draw" => Ok(Self::Draw),
+            "flip" => Ok(Self::Flip),
+            "mouse" => Ok(Self::Mouse),
+            "setup" => Ok(Self::Setup),
+            "view" if argument.is_empty() => Ok(Self::View(None)),
+            "view" => Ok(Self::View(Some(argument.to_string()))),
+            "fen" => Ok(Self::Fen),
+            "pgn" => Ok(Self::Pgn),
+            "moves" => Ok(Self::Moves),
+            "hint" => Ok(Self::Hint),
+            "analyze" if argument.is_empty() => Ok(Self::Analyze(3)),
+            "analyze" => argument
+                .parse()
+                .ok()
+                .filter(|n| (1..=MAX_LINES).contains(n))
+                .map(Self::Analyze)
+                .ok_or_else(|| format!("analyze shows 1 to {MAX_LINES} lines, not '{argument}'")),
+            "review" if argument.is_empty() => Ok(Self::Review(None)),
+            "review" => Ok(Self::Review(Some(argument.to_string()))),
+            "explore" if argument.is_empty() => Ok(Self::Explore(None)),
+            "explore" => Ok(Self::Explore(Some(argument.to_string()))),
+            "mate" => {
+                let (moves, fen) = argument
+                    .split_once(char::is_whitespace)
+                    .map_or((argument, None), |(moves, fen)| (moves, Some(fen.trim())));
+                moves
+                    .parse()
+                    .ok()
+                    .filter(|n| (1..=mate::MAX_MOVES).contains(n))
+                    .map(|n| Self::Mate(n, fen.map(ToString::to_string)))
+                    .ok_or_else(|| {
+                        format!(
+                            "usage: mate <n> [fen], with n from 1 to {}",
+                            mate::MAX_MOVES
+                        )
+                    })
+            }
+            "probe" if argument.is_empty() => Ok(Self::Probe(None)),
+            "probe" => Ok(Self::Probe(Some(argument.to_string()))),
+            "player" => {
+                let usage = || "usage: player <white|black> <name>".to_string();
+                let (color, name) = argument.split_once(char::is_whitespace).ok_or_else(usage)?;
+                let color = match color {
+                    "white" => Color::White,
+                    "black" => Color::Black,
+                    _ => return Err(usage()),
+                };
+                Ok(Self::Player(color, name.trim().to_string()))
+            }
+            "book" => match argument.split_once(char::is_whitespace) {
+                Some(("depth", depth)) => depth
+                    .trim()
+                    .parse()
+                    .map(Self::BookDepth)
+                    .map_err(|_| format!("Invalid book depth '{depth}'")),
+                _ if argument.is_empty() => Ok(Self::Book(None)),
+                _ => Ok(Self::Book(Some(argument.to_string()))),
+            },
+            "save" => file("save").map(Self::Save),
+            "load" => file("load").map(Self::Load),
+            _ if argument.is_empty() => Ok(Self::Move(s.to_string())),
+            _ => Err(format!("Unknown command '{word}'")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn help_fits_the_terminal() {
+        assert!(HELP.lines().all(|line| line.chars().count() <= 80));
+        assert!(HELP.lines().any(|line| line.starts_with("  probe [fen]")));
+    }
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!("help".parse(), Ok(Command::Help));
+        assert_eq!(" undo ".parse(), Ok(Command::Undo));
+        assert_eq!("q".parse(), Ok(Command::Quit));
+        assert_eq!("setup".parse(), Ok(Command::Setup));
+        assert_eq!("mouse".parse(), Ok(Command::Mouse));
+        assert_eq!(
+            "view games.pgn".parse(),
+            Ok(Command::View(Some("games.pgn".to_string())))
+        );
+        assert_eq!("new".parse(), Ok(Command::New(Start::Standard)));
+        assert_eq!("new 960".parse(), Ok(Command::New(Start::Chess960(None))));
+        assert_eq!(
+            "new 960 12".parse(),
+            Ok(Command::New(Start::Chess960(Some(12))))
+        );
+        assert!("new 960 960".parse::<Command>().is_err());
+        assert_eq!(
+            "new King-of-the-Hill".parse(),
+            Ok(Command::New(Start::Variant(Variant::KingOfTheHill)))
+        );
+        assert!("new bughouse".parse::<Command>().is_err());
+        assert_eq!(
+            "save games/my game.pgn".parse(),
+            Ok(Command::Save("games/my game.pgn".to_string()))
+        );
+        assert!("save".parse::<Command>().is_err());
+        assert_eq!(
+            "player black Bob Smith".parse(),
+            Ok(Command::Player(Color::Black, "Bob Smith".to_string()))
+        );
+        assert!("player blue Bob".parse::<Command>().is_err());
+        assert_eq!("book".parse(), Ok(Command::Book(None)));
+        assert_eq!(
+            "book openings.bin".parse(),
+            Ok(Command::Book(Some("openings.bin".to_string())))
+        );
+        assert_eq!("review".parse(), Ok(Command::Review(None)));
+        assert_eq!(
+            "review notes.pgn".parse(),
+            Ok(Command::Review(Some("notes.pgn".to_string())))
+        );
+        assert_eq!("explore".parse(), Ok(Command::Explore(None)));
+        assert_eq!(
+            "explore club.db".parse(),
+            Ok(Command::Explore(Some("club.db".to_string())))
+        );
+        assert_eq!("mate 3".parse(), Ok(Command::Mate(3, None)));
+        assert_eq!(
+            "mate 2 k7/8/2K5/8/8/8/8/7R w - - 0 1".parse(),
+            Ok(Command::Mate(
+                2,
+                Some("k7/8/2K5/8/8/8/8/7R w - - 0 1".to_string())
+            ))
+        );
+        assert!("mate".parse::<Command>().is_err());
+        assert!("mate 0".parse::<Command>().is_err());
+        assert_eq!("probe".parse(), Ok(Command::Probe(None)));
+        assert_eq!(
+            "probe 8/8/8/3k4/8/8/8/KQ6 w - - 0 1".parse(),
+            Ok(Command::Probe(Some(
+                "8/8/8/3k4/8/8/8/KQ6 w - - 0 1".to_string()
+            )))
+        );
+        assert_eq!("analyze".parse(), Ok(Command::Analyze(3)));
+        assert_eq!("analyze 5".parse(), Ok(Command::Analyze(5)));
+        assert!("analyze 0".parse::<Command>().is_err());
+        assert_eq!("book depth 8".parse(), Ok(Command::BookDepth(8)));
+        assert!("book depth x".parse::<Command>().is_err());
+        assert!("".parse::<Command>().is_err());
+        assert!("play e4".parse::<Command>().is_err());
+        assert_eq!("Nf3".parse(), Ok(Command::Move("Nf3".to_string())));
+    }
+}
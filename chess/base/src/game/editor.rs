@@ -0,0 +1,241 @@
+//! Setting up a position by hand: a cursor moves over the board, keys put
+//! down and take away pieces, and the side to move, castling rights and en
+//! passant square are switched in place. The result is only played from
+//! once it passes validation.
+
+use crate::{
+    board::{
+        movement::CastleSide,
+        position::{Castling, Position},
+        square::Coord,
+        Board,
+    },
+    game::panel::STATUS_ROW,
+    piece::{Color, Piece},
+};
+use std::io::{stdin, stdout, Write};
+use termion::{clear, cursor, event::Key, input::TermRead, raw::IntoRawMode};
+
+const HELP: &str = "\
+arrows move  PNBRQK/pnbrqk place  space/x remove  tab side to move
+1-4 castling KQkq  e en passant here  0 empty  i initial setup
+f show FEN  enter play from here  esc leave";
+
+/// How an editing session ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Exit {
+    /// Start a game from this position, which has been validated.
+    Play(Position),
+    Cancel,
+}
+
+#[derive(Debug, Clone)]
+pub struct Editor {
+    pub position: Position,
+    pub cursor: Coord,
+    flipped: bool,
+    message: Option<String>,
+}
+
+impl Editor {
+    #[must_use]
+    pub const fn new(position: Position, flipped: bool) -> Self {
+        Self {
+            position,
+            cursor: Coord::new(4, 3),
+            flipped,
+            message: None,
+        }
+    }
+
+    /// Handles one key press, returning how the session ends if it does.
+    pub fn press(&mut self, key: Key) -> Option<Exit> {
+        let position = &mut self.position;
+        // Up the screen is up the board unless it is seen from Black's side.
+        let (df, dr) = match key {
+            Key::Up => (0, 1),
+            Key::Down => (0, -1),
+            Key::Left => (-1, 0),
+            Key::Right => (1, 0),
+            _ => (0, 0),
+        };
+        if (df, dr) != (0, 0) {
+            let sign = if self.flipped { -1 } else { 1 };
+            if let Some(coord) = self.cursor.offset(df * sign, dr * sign) {
+                self.cursor = coord;
+            }
+            return None;
+        }
+        let square = self.cursor.index();
+        match key {
+            Key::Esc | Key::Ctrl('c') => return Some(Exit::Cancel),
+            Key::Char('\n') => match position.clone().checked() {
+                Ok(position) => return Some(Exit::Play(position)),
+                Err(problems) => self.message = Some(problems),
+            },
+            Key::Char('\t') => position.turn = position.turn.opposite(),
+            Key::Char(' ' | 'x') | Key::Backspace | Key::Delete => {
+                position.board.0[square] = None;
+                position.promoted &= !(1 << square);
+            }
+            Key::Char(c @ '1'..='4') => {
+                let (color, side, file) = match c {
+                    '1' => (Color::White, CastleSide::King, 7),
+                    '2' => (Color::White, CastleSide::Queen, 0),
+                    '3' => (Color::Black, CastleSide::King, 7),
+                    _ => (Color::Black, CastleSide::Queen, 0),
+                };
+                let right = if position.castling.has(color, side) {
+                    None
+                } else {
+                    Some(file)
+                };
+                position.castling.set(color, side, right);
+            }
+            Key::Char('e') => {
+                position.en_passant = if position.en_passant == Some(self.cursor) {
+                    None
+                } else {
+                    Some(self.cursor)
+                };
+            }
+            Key::Char('0') => {
+                *position = Position {
+                    board: Board([None; 64]),
+                    castling: Castling::NONE,
+                    variant: position.variant,
+                    ..Position::default()
+                };
+            }
+            Key::Char('i') => {
+                *position = Position {
+                    variant: position.variant,
+                    ..Position::default()
+                };
+            }
+            Key::Char('f') => self.message = Some(position.fen()),
+            Key::Char(c) => match Piece::from_fen_char(c) {
+                Some(piece) => {
+                    position.board.0[square] = Some(piece);
+                    position.promoted &= !(1 << square);
+                }
+                None => self.message = Some(format!("No key '{c}', see the list above")),
+            },
+            _ => {}
+        }
+        None
+    }
+
+    /// The lines under the board: the state FEN keeps beside the pieces,
+    /// the keys, and the last message.
+    #[must_use]
+    pub fn status(&self) -> Vec<String> {
+        let position = &self.position;
+        let castling = [
+            (Color::White, CastleSide::King, 'K'),
+            (Color::White, CastleSide::Queen, 'Q'),
+            (Color::Black, CastleSide::King, 'k'),
+            (Color::Black, CastleSide::Queen, 'q'),
+        ]
+        .into_iter()
+        .filter(|&(color, side, _)| position.castling.has(color, side))
+        .map(|(_, _, letter)| letter)
+        .collect::<String>();
+        let en_passant = position
+            .en_passant
+            .map_or_else(|| "-".to_string(), |square| square.to_string());
+        let mut lines = vec![format!(
+            "Setup | {} to move | castling {} | en passant {en_passant} | cursor {}",
+            position.turn,
+            if castling.is_empty() { "-" } else { &castling },
+            self.cursor
+        )];
+        lines.extend(HELP.lines().map(ToString::to_string));
+        lines.extend(self.message.iter().cloned());
+        lines
+    }
+
+    fn draw(&self) {
+        self.position.board.print(self.flipped);
+        let mut out = stdout();
+        write!(out, "{}", cursor::Goto(1, STATUS_ROW)).unwrap();
+        for line in self.status() {
+            write!(out, "{}{line}\r\n", clear::CurrentLine).unwrap();
+        }
+        write!(out, "{}", Board::cell(self.cursor, self.flipped)).unwrap();
+        out.flush().unwrap();
+    }
+}
+
+/// Runs the editor on the terminal from `position` until the player
+/// starts a game from the result or leaves.
+pub fn edit(position: Position, flipped: bool) -> Exit {
+    let mut editor = Editor::new(position, flipped);
+    let raw = stdout().into_raw_mode().unwrap();
+    let exit = loop {
+        editor.draw();
+        editor.message = None;
+        let Some(Ok(key)) = stdin().keys().next() else {
+            break Exit::Cancel;
+        };
+        if let Some(exit) = editor.press(key) {
+            break exit;
+        }
+    };
+    drop(raw);
+    println!("{}{}", cursor::Goto(1, STATUS_ROW), clear::AfterCursor);
+    exit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(editor: &mut Editor, keys: &str) -> Option<Exit> {
+        keys.chars().find_map(|c| {
+            editor.press(match c {
+                '^' => Key::Up,
+                'v' => Key::Down,
+                '<' => Key::Left,
+                '>' => Key::Right,
+                c => Key::Char(c),
+            })
+        })
+    }
+
+    #[test]
+    fn builds_a_position() {
+        let mut editor = Editor::new(Position::default(), false);
+        assert_eq!(editor.cursor, "e4".parse().unwrap());
+        // Empty the board, put the kings on e1 and e8 and a rook on a1.
+        press(&mut editor, "0vvvK^^^^^^^k<<<<vvvvvvvR\t2");
+        assert_eq!(editor.cursor, "a1".parse().unwrap());
+        assert_eq!(editor.position.fen(), "4k3/8/8/8/8/8/8/R3K3 b Q - 0 1");
+        assert_eq!(press(&mut editor, "f"), None);
+        assert_eq!(editor.status().last().unwrap(), &editor.position.fen());
+
+        let Some(Exit::Play(position)) = press(&mut editor, "\n") else {
+            panic!("the position is legal");
+        };
+        assert_eq!(position, editor.position);
+    }
+
+    #[test]
+    fn refuses_illegal_positions() {
+        let mut editor = Editor::new(Position::default(), true);
+        // Seen from Black's side, up the screen is down the board.
+        press(&mut editor, "^");
+        assert_eq!(editor.cursor, "e3".parse().unwrap());
+        press(&mut editor, "^^ ");
+        assert_eq!(editor.position.piece_at("e1".parse().unwrap()), None);
+        assert_eq!(press(&mut editor, "\n"), None);
+        assert_eq!(
+            editor.status().last().unwrap(),
+            "White has no king; White may castle kingside without the king and rook for it; \
+             White may castle queenside without the king and rook for it"
+        );
+        press(&mut editor, "12K");
+        assert!(matches!(press(&mut editor, "\n"), Some(Exit::Play(_))));
+        assert_eq!(editor.press(Key::Esc), Some(Exit::Cancel));
+    }
+}
@@ -0,0 +1,301 @@
+//! Playing with the mouse: click a piece and then where it goes, or drag
+//! it there. Clicks are mapped back to squares with the layout the board
+//! is drawn with, and only legal moves are let through. A key press hands
+//! the prompt back for typing.
+
+use crate::{
+    board::{movement::Move, position::Position, square::Coord, Board},
+    piece::Piece,
+};
+use std::io::{stdin, stdout, Write};
+use termion::{
+    clear, cursor,
+    event::{Event, Key, MouseButton, MouseEvent},
+    input::{MouseTerminal, TermRead},
+    raw::IntoRawMode,
+    style,
+};
+
+/// What the player did instead of typing a whole line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    /// A legal move made on the board.
+    Move(Move),
+    /// The first character of a typed line.
+    Key(char),
+    Quit,
+}
+
+#[derive(Debug, Clone)]
+pub struct Picker {
+    position: Position,
+    flipped: bool,
+    /// The square whose piece is picked up.
+    pub selected: Option<Coord>,
+    /// Whether the mouse button is still down since the piece was picked.
+    dragging: bool,
+    /// The choices left when a pawn reaches the last rank.
+    promotions: Vec<Move>,
+}
+
+impl Picker {
+    #[must_use]
+    pub const fn new(position: Position, flipped: bool) -> Self {
+        Self {
+            position,
+            flipped,
+            selected: None,
+            dragging: false,
+            promotions: Vec::new(),
+        }
+    }
+
+    /// The legal moves taking the piece on `from` to `to`. A king may also
+    /// castle by being put on its own rook.
+    fn moves(&self, from: Coord, to: Coord) -> Vec<Move> {
+        let position = &self.position;
+        let home = position.turn.home_rank();
+        position
+            .legal_moves()
+            .into_iter()
+            .filter(|mv| mv.drop.is_none() && mv.from == from)
+            .filter(|&mv| {
+                mv.to == to
+                    || position.castle_side(mv).is_some_and(|side| {
+                        to.rank.0 == home
+                            && position.castling.rook(position.turn, side) == Some(to.file.0)
+                    })
+            })
+            .collect()
+    }
+
+    /// Plays the move to `to` from the selected square if there is one,
+    /// asking for the piece first when it is a promotion.
+    fn move_to(&mut self, to: Coord) -> Option<Input> {
+        let from = self.selected?;
+        let moves = self.moves(from, to);
+        match moves.as_slice() {
+            [] => None,
+            [mv] => Some(Input::Move(*mv)),
+            _ => {
+                self.promotions = moves;
+                None
+            }
+        }
+    }
+
+    /// Whether the piece on `coord` belongs to the side to move.
+    fn movable(&self, coord: Coord) -> bool {
+        self.position
+            .piece_at(coord)
+            .is_some_and(|piece| piece.color() == self.position.turn)
+    }
+
+    /// Handles one key press or mouse event, returning the player's input
+    /// once there is some.
+    pub fn handle(&mut self, event: &Event) -> Option<Input> {
+        if !self.promotions.is_empty() {
+            // The button coming up after the click that reached the last rank.
+            if let Event::Mouse(MouseEvent::Hold(..) | MouseEvent::Release(..)) = *event {
+                return None;
+            }
+            let promotions = std::mem::take(&mut self.promotions);
+            if let Event::Key(Key::Char(c)) = *event {
+                let kind = Piece::from_fen_char(c).map(Piece::kind);
+                if let Some(mv) = promotions.iter().find(|mv| mv.promotion == kind) {
+                    return Some(Input::Move(*mv));
+                }
+            }
+            // Anything else puts the pawn back.
+            self.selected = None;
+            return None;
+        }
+        match *event {
+            Event::Mouse(MouseEvent::Press(MouseButton::Left, x, y)) => {
+                let square = Board::square_at(x, y, self.flipped);
+                if let Some(to) = square.filter(|&to| self.selected.is_some_and(|from| from != to))
+                {
+                    if let Some(input) = self.move_to(to) {
+                        return Some(input);
+                    }
+                    if !self.promotions.is_empty() {
+                        return None;
+                    }
+                }
+                self.selected = square.filter(|&coord| self.movable(coord));
+                self.dragging = self.selected.is_some();
+            }
+            Event::Mouse(MouseEvent::Release(x, y)) if self.dragging => {
+                self.dragging = false;
+                match Board::square_at(x, y, self.flipped) {
+                    // Let go where it was picked up, so it waits for a click.
+                    square if square == self.selected => {}
+                    Some(to) => {
+                        let input = self.move_to(to);
+                        if input.is_none() && self.promotions.is_empty() {
+                            self.selected = None;
+                        }
+                        return input;
+                    }
+                    None => self.selected = None,
+                }
+            }
+            Event::Key(Key::Ctrl('c' | 'd')) => return Some(Input::Quit),
+            Event::Key(Key::Esc) => self.selected = None,
+            Event::Key(Key::Char(c)) => return Some(Input::Key(c)),
+            _ => {}
+        }
+        None
+    }
+
+    /// What the player can do next, shown in the prompt.
+    #[must_use]
+    pub fn status(&self) -> String {
+        if !self.promotions.is_empty() {
+            return "promote to q, r, b or n".to_string();
+        }
+        self.selected.map_or_else(
+            || "click or drag a piece, or type".to_string(),
+            |from| format!("{from} to where?"),
+        )
+    }
+
+    /// Redraws the pieces, the selected one and where it may go shown in
+    /// reverse, and the prompt.
+    fn draw(&self) {
+        let targets = self.selected.map_or_else(Vec::new, |from| {
+            self.position
+                .legal_moves()
+                .into_iter()
+                .filter(|mv| mv.drop.is_none() && mv.from == from)
+                .map(|mv| mv.to)
+                .collect()
+        });
+        let mut out = stdout();
+        write!(out, "{}", cursor::Save).unwrap();
+        for (index, square) in self.position.board.0.iter().enumerate() {
+            let coord = Coord::from_index(index);
+            let text = square.map_or_else(|| ".".to_string(), |piece| piece.to_string());
+            let cell = Board::cell(coord, self.flipped);
+            if Some(coord) == self.selected || targets.contains(&coord) {
+                write!(out, "{cell}{}{text}{}", style::Invert, style::Reset).unwrap();
+            } else {
+                write!(out, "{cell}{text}").unwrap();
+            }
+        }
+        write!(
+            out,
+            "{}\r{}{} move ({}) >>> ",
+            cursor::Restore,
+            clear::CurrentLine,
+            self.position.turn,
+            self.status()
+        )
+        .unwrap();
+        out.flush().unwrap();
+    }
+}
+
+/// Waits at the prompt for a move made with the mouse, or the first key of
+/// a typed line, which is echoed after a plain prompt.
+pub fn read(position: &Position, flipped: bool) -> Input {
+    let mut picker = Picker::new(position.clone(), flipped);
+    let terminal = MouseTerminal::from(stdout().into_raw_mode().unwrap());
+    let input = loop {
+        picker.draw();
+        let Some(Ok(event)) = stdin().events().next() else {
+            break Input::Quit;
+        };
+        if let Some(input) = picker.handle(&event) {
+            break input;
+        }
+    };
+    drop(terminal);
+    picker.selected = None;
+    picker.draw();
+    print!("\r{}{} move >>> ", clear::CurrentLine, position.turn);
+    match input {
+        Input::Key(c) if c != '\n' => print!("{c}"),
+        Input::Key(_) | Input::Quit => println!(),
+        Input::Move(mv) => println!("{}", position.san(mv)),
+    }
+    stdout().flush().unwrap();
+    input
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cursor::Goto;
+
+    fn click(picker: &mut Picker, square: &str) -> Option<Input> {
+        let Goto(x, y) = Board::cell(square.parse().unwrap(), picker.flipped);
+        let input = picker.handle(&Event::Mouse(MouseEvent::Press(MouseButton::Left, x, y)));
+        input.or_else(|| picker.handle(&Event::Mouse(MouseEvent::Release(x, y))))
+    }
+
+    fn drag(picker: &mut Picker, from: &str, to: &str) -> Option<Input> {
+        let Goto(x, y) = Board::cell(from.parse().unwrap(), picker.flipped);
+        picker.handle(&Event::Mouse(MouseEvent::Press(MouseButton::Left, x, y)));
+        let Goto(x, y) = Board::cell(to.parse().unwrap(), picker.flipped);
+        picker.handle(&Event::Mouse(MouseEvent::Hold(x, y)));
+        picker.handle(&Event::Mouse(MouseEvent::Release(x, y)))
+    }
+
+    fn played(input: Option<Input>) -> String {
+        match input {
+            Some(Input::Move(mv)) => mv.to_string(),
+            other => panic!("expected a move, not {other:?}"),
+        }
+    }
+
+    #[test]
+    fn click_and_drag() {
+        let mut picker = Picker::new(Position::default(), false);
+        // Only the side to move's pieces can be picked up.
+        assert_eq!(click(&mut picker, "e7"), None);
+        assert_eq!(picker.selected, None);
+        assert_eq!(click(&mut picker, "e2"), None);
+        assert_eq!(picker.status(), "e2 to where?");
+        // A square it cannot reach puts the pawn down.
+        assert_eq!(click(&mut picker, "e5"), None);
+        assert_eq!(picker.selected, None);
+        click(&mut picker, "e2");
+        // Clicking another piece picks that one up instead.
+        assert_eq!(click(&mut picker, "g1"), None);
+        assert_eq!(picker.selected, Some("g1".parse().unwrap()));
+        assert_eq!(played(click(&mut picker, "f3")), "g1f3");
+
+        let mut picker = Picker::new(Position::default(), true);
+        assert_eq!(played(drag(&mut picker, "d2", "d4")), "d2d4");
+        // Dropped somewhere it cannot go, the piece goes back.
+        assert_eq!(drag(&mut picker, "b1", "b3"), None);
+        assert_eq!(picker.selected, None);
+        assert_eq!(
+            picker.handle(&Event::Key(Key::Char('f'))),
+            Some(Input::Key('f'))
+        );
+    }
+
+    #[test]
+    fn castling_and_promotion() {
+        let position = Position::from_fen("4k3/1P6/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        let mut picker = Picker::new(position.clone(), false);
+        click(&mut picker, "e1");
+        assert_eq!(played(click(&mut picker, "h1")), "e1g1");
+        let mut picker = Picker::new(position, false);
+        assert_eq!(played(drag(&mut picker, "e1", "g1")), "e1g1");
+
+        assert_eq!(drag(&mut picker, "b7", "b8"), None);
+        assert_eq!(picker.status(), "promote to q, r, b or n");
+        assert_eq!(played(picker.handle(&Event::Key(Key::Char('n')))), "b7b8n");
+        click(&mut picker, "b7");
+        assert_eq!(click(&mut picker, "b8"), None);
+        assert_eq!(played(picker.handle(&Event::Key(Key::Char('Q')))), "b7b8q");
+        // Anything but a piece letter puts the pawn back.
+        drag(&mut picker, "b7", "b8");
+        assert_eq!(picker.handle(&Event::Key(Key::Esc)), None);
+        assert_eq!(picker.selected, None);
+        assert_eq!(picker.status(), "click or drag a piece, or type");
+    }
+}
@@ -0,0 +1,516 @@
+use super::{command::Command, Game};
+use crate::{board::position::Position, piece::Color};
+use std::{
+    fmt::Display,
+    io::{stdin, stdout, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    ops::ControlFlow,
+    str::FromStr,
+    sync::mpsc,
+    thread,
+    time::Instant,
+};
+
+/// Port used when `--host` or `--join` does not name one.
+pub const DEFAULT_PORT: u16 = 7878;
+
+/// Sent in the handshake; both ends must speak the same version.
+const VERSION: u32 = 1;
+
+/// Where an unfinished network game is saved on quit, kept apart from the
+/// [`AUTOSAVE`](super::save::AUTOSAVE) of games played alone.
+pub const AUTOSAVE: &str = "network.pgn";
+
+/// One line of the protocol. Each side opens with `HELLO`, the host then
+/// sends `START`, and after that moves and the rest go back and forth.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    /// Protocol version and the sender's name.
+    Hello {
+        version: u32,
+        name: String,
+    },
+    /// From the host: the colour the guest plays and the position to start from.
+    Start {
+        color: Color,
+        fen: String,
+    },
+    /// A move in coordinate form, e.g. `e2e4`.
+    Move(String),
+    /// Offers a draw, or accepts the one on the table.
+    Draw,
+    Resign,
+    /// The sender is leaving.
+    Bye,
+    /// The sender cannot go on, e.g. after an illegal move.
+    Error(String),
+}
+
+impl Display for Message {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Hello { version, name } => write!(f, "HELLO {version} {name}"),
+            Self::Start { color, fen } => write!(f, "START {} {fen}", color_name(*color)),
+            Self::Move(mv) => write!(f, "MOVE {mv}"),
+            Self::Draw => write!(f, "DRAW"),
+            Self::Resign => write!(f, "RESIGN"),
+            Self::Bye => write!(f, "BYE"),
+            Self::Error(reason) => write!(f, "ERROR {reason}"),
+        }
+    }
+}
+
+impl FromStr for Message {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (word, rest) = s
+            .split_once(' ')
+            .map_or((s, ""), |(word, rest)| (word, rest.trim()));
+        let invalid = || format!("Invalid message '{s}'");
+        match word {
+            "HELLO" => {
+                let (version, name) = rest.split_once(' ').unwrap_or((rest, ""));
+                Ok(Self::Hello {
+                    version: version.parse().map_err(|_| invalid())?,
+                    name: name.trim().to_string(),
+                })
+            }
+            "START" => {
+                let (color, fen) = rest.split_once(' ').ok_or_else(invalid)?;
+                Ok(Self::Start {
+                    color: parse_color(color).ok_or_else(invalid)?,
+                    fen: fen.trim().to_string(),
+                })
+            }
+            "MOVE" if !rest.is_empty() => Ok(Self::Move(rest.to_string())),
+            "DRAW" => Ok(Self::Draw),
+            "RESIGN" => Ok(Self::Resign),
+            "BYE" => Ok(Self::Bye),
+            "ERROR" => Ok(Self::Error(rest.to_string())),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+const fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::White => "white",
+        Color::Black => "black",
+    }
+}
+
+/// Reads `white` or `black`, as written in `START` and on the command line.
+#[must_use]
+pub fn parse_color(name: &str) -> Option<Color> {
+    match name {
+        "white" => Some(Color::White),
+        "black" => Some(Color::Black),
+        _ => None,
+    }
+}
+
+/// The name sent to the opponent: the login name, if there is one.
+#[must_use]
+pub fn local_name() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default()
+}
+
+/// A TCP connection speaking the line protocol.
+#[derive(Debug)]
+pub struct Connection {
+    /// Handed to a reading thread once play starts.
+    reader: Option<BufReader<TcpStream>>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> Result<Self, String> {
+        let reader = stream
+            .try_clone()
+            .map_err(|e| format!("Could not use the connection: {e}"))?;
+        Ok(Self {
+            reader: Some(BufReader::new(reader)),
+            writer: stream,
+        })
+    }
+
+    pub fn send(&mut self, message: &Message) -> Result<(), String> {
+        writeln!(self.writer, "{message}").map_err(|e| format!("Connection lost: {e}"))
+    }
+
+    /// The next message, or `None` once the other side has hung up.
+    pub fn receive(&mut self) -> Result<Option<Message>, String> {
+        let reader = self.reader.as_mut().ok_or("The connection is being read")?;
+        receive(reader)
+    }
+
+    /// Swaps `HELLO`s and returns the opponent's name.
+    fn hello(&mut self, name: &str) -> Result<String, String> {
+        self.send(&Message::Hello {
+            version: VERSION,
+            name: name.to_string(),
+        })?;
+        match self.receive()? {
+            Some(Message::Hello { version, name }) if version == VERSION => Ok(name),
+            Some(Message::Hello { version, .. }) => {
+                let reason = format!("Protocol version {VERSION} needed, not {version}");
+                self.send(&Message::Error(reason.clone())).ok();
+                Err(reason)
+            }
+            message => Err(unexpected(message)),
+        }
+    }
+}
+
+fn receive(reader: &mut impl BufRead) -> Result<Option<Message>, String> {
+    let mut line = String::new();
+    match reader.read_line(&mut line) {
+        Ok(0) => Ok(None),
+        Ok(_) => line.parse().map(Some),
+        Err(e) => Err(format!("Connection lost: {e}")),
+    }
+}
+
+fn unexpected(message: Option<Message>) -> String {
+    match message {
+        None => "The other side hung up".to_string(),
+        Some(Message::Error(reason)) => format!("The other side gave up: {reason}"),
+        Some(message) => format!("Unexpected message '{message}'"),
+    }
+}
+
+/// What either end of the connection sends the play loop.
+enum Event {
+    /// A line typed here, or `None` at the end of input.
+    Local(Option<String>),
+    Remote(Result<Option<Message>, String>),
+}
+
+/// One game played over a connection, from this side's point of view.
+#[derive(Debug)]
+pub struct Session {
+    game: Game,
+    /// The colour played at this end.
+    color: Color,
+    connection: Connection,
+}
+
+/// Waits on `port` for one opponent and starts a game with them, this end
+/// playing `color`.
+pub fn host(port: u16, color: Color, name: &str) -> Result<Session, String> {
+    let listener = TcpListener::bind(("0.0.0.0", port))
+        .map_err(|e| format!("Could not listen on port {port}: {e}"))?;
+    println!("Waiting for an opponent on port {port}...");
+    accept(&listener, color, name)
+}
+
+fn accept(listener: &TcpListener, color: Color, name: &str) -> Result<Session, String> {
+    let (stream, _) = listener
+        .accept()
+        .map_err(|e| format!("Could not accept a connection: {e}"))?;
+    let mut connection = Connection::new(stream)?;
+    let opponent = connection.hello(name)?;
+    let mut game = Game::default();
+    connection.send(&Message::Start {
+        color: color.opposite(),
+        fen: game.position.fen(),
+    })?;
+    game.set_names(color, name, opponent);
+    Ok(Session {
+        game,
+        color,
+        connection,
+    })
+}
+
+/// Connects to a host at `address`, which may leave off the port.
+pub fn join(address: &str, name: &str) -> Result<Session, String> {
+    let address = if address.contains(':') {
+        address.to_string()
+    } else {
+        format!("{address}:{DEFAULT_PORT}")
+    };
+    let stream =
+        TcpStream::connect(&address).map_err(|e| format!("Could not connect to {address}: {e}"))?;
+    let mut connection = Connection::new(stream)?;
+    let opponent = connection.hello(name)?;
+    let (color, fen) = match connection.receive()? {
+        Some(Message::Start { color, fen }) => (color, fen),
+        message => return Err(unexpected(message)),
+    };
+    let mut game = Game::from_position(Position::from_fen(&fen)?);
+    game.set_names(color, name, opponent);
+    Ok(Session {
+        game,
+        color,
+        connection,
+    })
+}
+
+impl Game {
+    fn set_names(&mut self, color: Color, name: &str, opponent: String) {
+        let (white, black) = match color {
+            Color::White => (name.to_string(), opponent),
+            Color::Black => (opponent, name.to_string()),
+        };
+        self.white = white;
+        self.black = black;
+    }
+}
+
+impl Session {
+    /// Plays until either side leaves, taking commands from the terminal and
+    /// the opponent's moves from the connection as they come in.
+    pub fn play(mut self) {
+        let (events, incoming) = mpsc::channel();
+        if let Some(mut reader) = self.connection.reader.take() {
+            let events = events.clone();
+            thread::spawn(move || loop {
+                let message = receive(&mut reader);
+                let done = !matches!(message, Ok(Some(_)));
+                if events.send(Event::Remote(message)).is_err() || done {
+                    break;
+                }
+            });
+        }
+        thread::spawn(move || loop {
+            let mut line = String::new();
+            let done = matches!(stdin().read_line(&mut line), Ok(0) | Err(_));
+            if events.send(Event::Local((!done).then_some(line))).is_err() || done {
+                break;
+            }
+        });
+
+        let mut started = Instant::now();
+        loop {
+            self.game.show();
+            if self.game.position.turn == self.color || self.game.outcome.is_some() {
+                print!("{} move >>> ", self.color);
+            } else {
+                print!("Waiting for {} >>> ", self.color.opposite());
+            }
+            stdout().flush().unwrap();
+
+            let mover = self.game.position.turn;
+            let played = self.game.history.len();
+            let step = match incoming.recv() {
+                Ok(Event::Local(Some(line))) => match line.parse::<Command>() {
+                    Ok(Command::Quit) => {
+                        self.connection.send(&Message::Bye).ok();
+                        ControlFlow::Break(String::new())
+                    }
+                    Ok(command) => ControlFlow::Continue(self.local(command)),
+                    Err(e) => ControlFlow::Continue(Err(e)),
+                },
+                Ok(Event::Local(None)) | Err(_) => {
+                    self.connection.send(&Message::Bye).ok();
+                    ControlFlow::Break(String::new())
+                }
+                Ok(Event::Remote(Ok(Some(message)))) => self.remote(message),
+                Ok(Event::Remote(Ok(None))) => {
+                    ControlFlow::Break(format!("{} disconnected", self.color.opposite()))
+                }
+                Ok(Event::Remote(Err(e))) => ControlFlow::Break(e),
+            };
+            if self.game.history.len() > played {
+                self.game.clocks.add(mover, started.elapsed());
+                started = Instant::now();
+            }
+            match step {
+                ControlFlow::Continue(message) => {
+                    self.game.message = match message {
+                        Ok(m) if m.is_empty() => None,
+                        Ok(m) | Err(m) => Some(m),
+                    };
+                }
+                ControlFlow::Break(reason) => {
+                    if !reason.is_empty() {
+                        println!("{reason}");
+                    }
+                    break;
+                }
+            }
+        }
+        match self.game.autosave_to(AUTOSAVE) {
+            Some(Ok(path)) => println!("Saved to {path}"),
+            Some(Err(e)) => println!("{e}"),
+            None => {}
+        }
+        println!("END");
+    }
+
+    /// Runs a command typed at this end, passing moves, draw offers and
+    /// resignations on to the opponent.
+    fn local(&mut self, command: Command) -> Result<String, String> {
+        let game = &mut self.game;
+        let (message, reply) = match command {
+            Command::Move(text) => {
+                if game.outcome.is_none() && game.position.turn != self.color {
+                    return Err(format!("Waiting for {} to move", self.color.opposite()));
+                }
+                let message = game.try_move(&text)?;
+                let mv = game.history.last().map(|ply| ply.mv.to_string());
+                (message, Message::Move(mv.unwrap_or_default()))
+            }
+            Command::Resign => (game.resign(self.color)?, Message::Resign),
+            Command::Draw => (game.draw(self.color)?, Message::Draw),
+            Command::Help
+            | Command::Flip
+            | Command::Fen
+            | Command::Pgn
+            | Command::Moves
+            | Command::Save(_) => {
+                game.execute(command);
+                return Ok(game.message.take().unwrap_or_default());
+            }
+            _ => return Err("Not available in a network game".to_string()),
+        };
+        self.connection.send(&reply)?;
+        Ok(message)
+    }
+
+    /// Applies a message from the opponent. Anything that does not fit the
+    /// game ends the session, since the two ends no longer agree.
+    fn remote(&mut self, message: Message) -> ControlFlow<String, Result<String, String>> {
+        let opponent = self.color.opposite();
+        let result = match message {
+            Message::Move(text) if self.game.position.turn == opponent => self.game.try_move(&text),
+            Message::Draw => self.game.draw(opponent),
+            Message::Resign => self.game.resign(opponent),
+            Message::Bye => return ControlFlow::Break(format!("{opponent} left the game")),
+            Message::Error(reason) => {
+                return ControlFlow::Break(format!("{opponent} gave up: {reason}"))
+            }
+            message => Err(format!("Unexpected message '{message}'")),
+        };
+        match result {
+            Ok(message) => ControlFlow::Continue(Ok(message)),
+            Err(e) => {
+                self.connection.send(&Message::Error(e.clone())).ok();
+                ControlFlow::Break(format!("{opponent} sent something wrong: {e}"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Outcome;
+
+    #[test]
+    fn message_lines() {
+        let messages = [
+            Message::Hello {
+                version: 1,
+                name: "Ada Lovelace".to_string(),
+            },
+            Message::Start {
+                color: Color::Black,
+                fen: Position::default().fen(),
+            },
+            Message::Move("e7e8q".to_string()),
+            Message::Draw,
+            Message::Resign,
+            Message::Bye,
+            Message::Error("Illegal move 'e2e5'".to_string()),
+        ];
+        for message in messages {
+            assert_eq!(message.to_string().parse(), Ok(message));
+        }
+        assert_eq!(
+            "HELLO 1".parse(),
+            Ok(Message::Hello {
+                version: 1,
+                name: String::new()
+            })
+        );
+        assert!("MOVE".parse::<Message>().is_err());
+        assert!("START red 8/8/8/8/8/8/8/8 w - -"
+            .parse::<Message>()
+            .is_err());
+        assert!("hello 1".parse::<Message>().is_err());
+    }
+
+    /// A host playing White and a guest joined over localhost.
+    fn sessions() -> (Session, Session) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let host = thread::spawn(move || accept(&listener, Color::White, "Host").unwrap());
+        let guest = join(&address, "Guest").unwrap();
+        (host.join().unwrap(), guest)
+    }
+
+    /// Plays `line` at `from`'s end and hands whatever it sent to `to`.
+    fn relay(from: &mut Session, to: &mut Session, line: &str) -> Result<String, String> {
+        from.local(line.parse().unwrap())?;
+        let message = to.connection.receive().unwrap().unwrap();
+        match to.remote(message) {
+            ControlFlow::Continue(result) => result,
+            ControlFlow::Break(reason) => Err(reason),
+        }
+    }
+
+    #[test]
+    fn game_over_localhost() {
+        let (mut host, mut guest) = sessions();
+        assert_eq!(guest.color, Color::Black);
+        assert_eq!(guest.game.white, "Host");
+        assert_eq!(host.game.black, "Guest");
+
+        assert_eq!(
+            guest.local("e5".parse().unwrap()),
+            Err("Waiting for White to move".to_string())
+        );
+        assert_eq!(
+            host.local("undo".parse().unwrap()),
+            Err("Not available in a network game".to_string())
+        );
+        for (line, by_host) in [("e4", true), ("e5", false), ("Nf3", true)] {
+            if by_host {
+                relay(&mut host, &mut guest, line).unwrap();
+            } else {
+                relay(&mut guest, &mut host, line).unwrap();
+            }
+        }
+        assert_eq!(host.game.position, guest.game.position);
+
+        // Either side may offer a draw, even while the other is to move.
+        relay(&mut host, &mut guest, "draw").unwrap();
+        relay(&mut guest, &mut host, "draw").unwrap();
+        assert_eq!(host.game.outcome, Some(Outcome::DrawAgreed));
+        assert_eq!(guest.game.outcome, Some(Outcome::DrawAgreed));
+    }
+
+    #[test]
+    fn resign_and_disconnect() {
+        let (mut host, mut guest) = sessions();
+        relay(&mut guest, &mut host, "resign").unwrap();
+        assert_eq!(host.game.outcome, Some(Outcome::Resignation(Color::White)));
+        assert_eq!(guest.game.outcome, host.game.outcome);
+
+        drop(guest);
+        assert_eq!(host.connection.receive(), Ok(None));
+    }
+
+    #[test]
+    fn bad_moves_end_the_session() {
+        let (mut host, mut guest) = sessions();
+        guest
+            .connection
+            .send(&Message::Move("e2e4".to_string()))
+            .unwrap();
+        let message = host.connection.receive().unwrap().unwrap();
+        assert!(host.remote(message).is_break());
+        assert_eq!(
+            guest.connection.receive(),
+            Ok(Some(Message::Error(
+                "Unexpected message 'MOVE e2e4'".to_string()
+            )))
+        );
+    }
+}
@@ -0,0 +1,286 @@
+//! What is drawn beside the board during a game: the moves so far in two
+//! columns, the pieces each side has taken and who is ahead on material.
+//! On a terminal too narrow for that, a shorter summary goes underneath.
+
+use crate::{
+    board::{position::Position, Board, SPACE},
+    piece::{Color, Kind, Piece},
+};
+
+/// Screen column the panel starts at, clear of the board.
+pub const COLUMN: u16 = 24;
+/// Narrowest panel worth drawing beside the board.
+const MIN_WIDTH: u16 = 24;
+/// The usual terminal width, for when it cannot be read.
+const DEFAULT_WIDTH: u16 = 80;
+/// The screen row below the drawn board and the blank line after it.
+pub const STATUS_ROW: u16 = Board::height(SPACE.1) + 2;
+
+/// Pawns, knights, bishops, rooks and queens, in the order taken pieces
+/// are shown.
+const KINDS: [Kind; 5] = [
+    Kind::Pawn,
+    Kind::Knight,
+    Kind::Bishop,
+    Kind::Rook,
+    Kind::Queen,
+];
+
+/// Material in pawns, by the usual count.
+const fn value(kind: Kind) -> i32 {
+    match kind {
+        Kind::Pawn => 1,
+        Kind::Knight | Kind::Bishop => 3,
+        Kind::Rook => 5,
+        Kind::Queen => 9,
+        Kind::King => 0,
+    }
+}
+
+fn count(position: &Position, color: Color, kind: Kind) -> usize {
+    position
+        .board
+        .pieces()
+        .filter(|(_, piece)| *piece == Piece::new(color, kind))
+        .count()
+}
+
+/// The pieces of `color` missing since `start`, cheapest first. A piece
+/// beyond those it started with came from a pawn, so that pawn is not
+/// counted as taken.
+#[must_use]
+pub fn captured(start: &Position, position: &Position, color: Color) -> Vec<Piece> {
+    let promoted = KINDS[1..]
+        .iter()
+        .map(|&kind| count(position, color, kind).saturating_sub(count(start, color, kind)))
+        .sum::<usize>();
+    KINDS
+        .into_iter()
+        .flat_map(|kind| {
+            let mut missing =
+                count(start, color, kind).saturating_sub(count(position, color, kind));
+            if kind == Kind::Pawn {
+                missing = missing.saturating_sub(promoted);
+            }
+            std::iter::repeat_n(Piece::new(color, kind), missing)
+        })
+        .collect()
+}
+
+/// White's material less Black's, in pawns.
+#[must_use]
+pub fn balance(position: &Position) -> i32 {
+    position
+        .board
+        .pieces()
+        .map(|(_, piece)| match piece.color() {
+            Color::White => value(piece.kind()),
+            Color::Black => -value(piece.kind()),
+        })
+        .sum()
+}
+
+/// A side's name, the pieces it has taken and how far it is ahead.
+#[must_use]
+pub fn side_line(start: &Position, position: &Position, color: Color, name: &str) -> String {
+    let taken = captured(start, position, color.opposite())
+        .iter()
+        .map(ToString::to_string)
+        .collect::<String>();
+    let lead = match color {
+        Color::White => balance(position),
+        Color::Black => -balance(position),
+    };
+    let name = if name.is_empty() {
+        color.to_string()
+    } else {
+        name.to_string()
+    };
+    let mut line = name;
+    if !taken.is_empty() {
+        line = format!("{line} {taken}");
+    }
+    if lead > 0 {
+        line = format!("{line} +{lead}");
+    }
+    line
+}
+
+/// The moves played from `start` in SAN, a numbered row per move pair.
+#[must_use]
+pub fn move_rows(start: &Position, sans: &[String]) -> Vec<String> {
+    let mut rows = Vec::new();
+    let mut number = start.fullmove;
+    let mut sans = sans.iter().map(String::as_str);
+    if start.turn == Color::Black {
+        if let Some(black) = sans.next() {
+            rows.push(format!("{number:>3}. {:<8}{black}", "..."));
+            number += 1;
+        }
+    }
+    loop {
+        match (sans.next(), sans.next()) {
+            (Some(white), Some(black)) => rows.push(format!("{number:>3}. {white:<8}{black}")),
+            (Some(white), None) => rows.push(format!("{number:>3}. {white}")),
+            _ => break,
+        }
+        number += 1;
+    }
+    rows
+}
+
+/// The last `height` entries of `rows`, so the newest move stays in view.
+fn scrolled(rows: Vec<String>, height: usize) -> Vec<String> {
+    let skip = rows.len().saturating_sub(height);
+    rows.into_iter().skip(skip).collect()
+}
+
+/// Where the panel goes on the terminal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Layout {
+    /// Lines for the rows beside the board, from the top.
+    Beside(Vec<String>),
+    /// Lines to print under the board.
+    Below(Vec<String>),
+}
+
+/// Lays the panel out for the game so far on a terminal `width` columns
+/// wide. The side whose pieces are at the bottom of the board is listed at
+/// the bottom.
+#[must_use]
+pub fn layout(
+    start: &Position,
+    position: &Position,
+    sans: &[String],
+    names: [&str; 2],
+    flipped: bool,
+    width: Option<u16>,
+) -> Layout {
+    let [white, black] = [Color::White, Color::Black].map(|color| {
+        side_line(
+            start,
+            position,
+            color,
+            names[usize::from(color == Color::Black)],
+        )
+    });
+    let (top, bottom) = if flipped {
+        (white, black)
+    } else {
+        (black, white)
+    };
+    let rows = move_rows(start, sans);
+    if let Some(width) = room_beside(width) {
+        let height = usize::from(Board::height(SPACE.1)) - 2;
+        let mut lines = vec![top];
+        lines.extend(scrolled(rows, height));
+        lines.resize(height + 1, String::new());
+        lines.push(bottom);
+        // Keep every line inside the terminal.
+        let fit = |line: String| line.chars().take(width).collect();
+        Layout::Beside(lines.into_iter().map(fit).collect())
+    } else {
+        let mut lines = vec![top, bottom];
+        lines.extend(
+            scrolled(rows, 1)
+                .into_iter()
+                .map(|row| row.trim().to_string()),
+        );
+        Layout::Below(lines)
+    }
+}
+
+/// The columns left beside the board on a terminal `width` columns wide,
+/// or `None` if that is too few for a panel.
+#[must_use]
+pub fn room_beside(width: Option<u16>) -> Option<usize> {
+    let width = width.unwrap_or(DEFAULT_WIDTH);
+    (width >= COLUMN + MIN_WIDTH).then(|| usize::from(width - COLUMN))
+}
+
+/// The terminal's width, if stdout is one.
+#[must_use]
+pub fn terminal_width() -> Option<u16> {
+    termion::terminal_size().ok().map(|(width, _)| width)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(moves: &str) -> (Position, Vec<String>) {
+        let mut position = Position::default();
+        let sans = moves
+            .split_whitespace()
+            .map(|text| {
+                let mv = position.parse_move(text).unwrap();
+                let san = position.san(mv);
+                position.make_move(mv);
+                san
+            })
+            .collect();
+        (position, sans)
+    }
+
+    #[test]
+    fn captures_and_balance() {
+        let (position, _) = play("e4 d5 exd5 Qxd5 Nc3 Qxg2 Bxg2");
+        let start = Position::default();
+        assert_eq!(
+            captured(&start, &position, Color::Black),
+            [
+                Piece::new(Color::Black, Kind::Pawn),
+                Piece::new(Color::Black, Kind::Queen)
+            ]
+        );
+        assert_eq!(balance(&position), 8);
+        assert_eq!(
+            side_line(&start, &position, Color::White, "Ann"),
+            "Ann ♟♛ +8"
+        );
+        assert_eq!(side_line(&start, &position, Color::Black, ""), "Black ♙♙");
+
+        // A promoted queen does not count its pawn as taken.
+        let promoted = Position::from_fen("1Q2k3/8/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        let bare = Position::from_fen("4k3/8/8/8/8/8/P7/4K3 w - - 0 1").unwrap();
+        assert_eq!(captured(&bare, &promoted, Color::White), []);
+        assert_eq!(balance(&promoted), 9);
+    }
+
+    #[test]
+    fn two_column_moves() {
+        let (_, sans) = play("e4 e5 Nf3");
+        assert_eq!(
+            move_rows(&Position::default(), &sans),
+            ["  1. e4      e5", "  2. Nf3"]
+        );
+        let black = Position::from_fen("4k3/8/8/8/8/8/8/4K3 b - - 0 30").unwrap();
+        let sans = ["Kd7", "Ke2", "Kc6"].map(ToString::to_string);
+        assert_eq!(
+            move_rows(&black, &sans),
+            [" 30. ...     Kd7", " 31. Ke2     Kc6"]
+        );
+    }
+
+    #[test]
+    fn adapts_to_the_terminal() {
+        let start = Position::default();
+        let (position, sans) = play(&"Nf3 Nf6 Ng1 Ng8 ".repeat(6));
+        let Layout::Beside(lines) = layout(&start, &position, &sans, ["Ann", ""], false, Some(80))
+        else {
+            panic!("wide enough to go beside the board");
+        };
+        assert_eq!(lines.len(), usize::from(Board::height(SPACE.1)));
+        assert_eq!(lines[0], "Black");
+        assert_eq!(lines[11], "Ann");
+        // Twelve move pairs, of which the last ten fit.
+        assert_eq!(lines[1], "  3. Nf3     Nf6");
+        assert_eq!(lines[10], " 12. Ng1     Ng8");
+
+        let Layout::Below(lines) = layout(&start, &position, &sans, ["Ann", ""], true, Some(40))
+        else {
+            panic!("too narrow to go beside the board");
+        };
+        assert_eq!(lines, ["Ann", "Black", "12. Ng1     Ng8"]);
+    }
+}
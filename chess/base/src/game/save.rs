@@ -0,0 +1,169 @@
+use super::Game;
+use crate::{board::position::Position, pgn::PgnGame};
+use std::time::Duration;
+
+/// Where a game is saved on quit when it was never saved or loaded.
+pub const AUTOSAVE: &str = "autosave.pgn";
+
+const WHITE_CLOCK: &str = "WhiteClock";
+const BLACK_CLOCK: &str = "BlackClock";
+const FLIPPED: &str = "Flipped";
+
+impl Game {
+    /// Loads a game saved with [`Game::save`], or any PGN or FEN file.
+    ///
+    /// Only a file that saving would write back unchanged becomes the one
+    /// [`Game::autosave`] uses, so a FEN file or a collection of games is
+    /// never overwritten by the one game played from it.
+    pub fn open(path: &str) -> Result<Self, String> {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| format!("Could not read {path}: {e}"))?;
+        if let Ok(position) = Position::from_fen(text.trim()) {
+            return Ok(Self::from_position(position));
+        }
+        let games = PgnGame::parse_all(&text)?;
+        let pgn = games
+            .first()
+            .ok_or_else(|| format!("No game found in {path}"))?;
+        let mut game = Self::from_pgn(pgn)?;
+        game.clocks.white = read_clock(pgn, WHITE_CLOCK)?;
+        game.clocks.black = read_clock(pgn, BLACK_CLOCK)?;
+        game.flipped = pgn.tag(FLIPPED) == Some("1");
+        if games.len() == 1 && game.saved_pgn().write() == pgn.write() {
+            game.path = Some(path.to_string());
+        }
+        Ok(game)
+    }
+
+    /// The game as [`Game::save`] writes it.
+    fn saved_pgn(&self) -> PgnGame {
+        let mut pgn = self.to_pgn();
+        pgn.set_tag(
+            WHITE_CLOCK,
+            format!("{:.3}", self.clocks.white.as_secs_f64()),
+        );
+        pgn.set_tag(
+            BLACK_CLOCK,
+            format!("{:.3}", self.clocks.black.as_secs_f64()),
+        );
+        if self.flipped {
+            pgn.set_tag(FLIPPED, "1");
+        }
+        pgn
+    }
+
+    /// Writes the game as PGN, with the clocks and board orientation as extra
+    /// tags so [`Game::open`] can pick up where it left off.
+    pub fn save(&mut self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.saved_pgn().write())
+            .map_err(|e| format!("Could not save {path}: {e}"))?;
+        self.path = Some(path.to_string());
+        Ok(())
+    }
+
+    /// Saves an unfinished game to the last used file, or [`AUTOSAVE`].
+    pub(super) fn autosave(&mut self) -> Option<Result<String, String>> {
+        self.autosave_to(AUTOSAVE)
+    }
+
+    /// Saves an unfinished game to the last used file, or `fallback` if it
+    /// has none, returning the path written.
+    pub(super) fn autosave_to(&mut self, fallback: &str) -> Option<Result<String, String>> {
+        if self.history.is_empty() || self.outcome.is_some() {
+            return None;
+        }
+        let path = self.path.clone().unwrap_or_else(|| fallback.to_string());
+        Some(self.save(&path).map(|()| path))
+    }
+}
+
+fn read_clock(pgn: &PgnGame, tag: &str) -> Result<Duration, String> {
+    pgn.tag(tag).map_or(Ok(Duration::ZERO), |seconds| {
+        seconds
+            .parse::<f64>()
+            .ok()
+            .and_then(|s| Duration::try_from_secs_f64(s).ok())
+            .ok_or_else(|| format!("Invalid {tag} '{seconds}'"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{game::command::Command, piece::Color};
+
+    #[test]
+    fn save_and_open() {
+        let path = std::env::temp_dir().join(format!("chess-resume-{}.pgn", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let mut game = Game::default();
+        for line in ["player white Alice", "e4", "e5", "flip"] {
+            game.execute(line.parse::<Command>().unwrap());
+        }
+        game.clocks.add(Color::White, Duration::from_millis(61_500));
+        game.save(path).unwrap();
+
+        let resumed = Game::open(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(resumed.position, game.position);
+        assert_eq!(resumed.history.len(), 2);
+        assert_eq!(resumed.white, "Alice");
+        assert_eq!(resumed.clocks, game.clocks);
+        assert!(resumed.flipped);
+        assert_eq!(resumed.path.as_deref(), Some(path));
+    }
+
+    #[test]
+    fn load_keeps_the_session_settings() {
+        let path = std::env::temp_dir().join(format!("chess-load-{}.pgn", std::process::id()));
+        let path = path.to_str().unwrap();
+        Game::default().save(path).unwrap();
+
+        let mut game = Game::default();
+        game.engine.depth = 3;
+        game.engine.multipv = 2;
+        game.execute(Command::Mouse);
+        game.execute(Command::Load(path.to_string()));
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(game.message, Some(format!("Loaded {path}")));
+        assert_eq!(game.engine.depth, 3);
+        assert_eq!(game.engine.multipv, 2);
+        assert!(game.mouse_off);
+    }
+
+    #[test]
+    fn autosave_leaves_other_files_alone() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("chess-collection-{}.pgn", std::process::id()));
+        let path = path.to_str().unwrap();
+        let collection = "[Event \"Club\"]\n\n1. e4 e5 *\n\n[Event \"Club\"]\n\n1. d4 d5 *\n";
+        std::fs::write(path, collection).unwrap();
+        let fen = dir.join(format!("chess-position-{}.fen", std::process::id()));
+        let fen = fen.to_str().unwrap();
+        let fallback = dir.join(format!("chess-autosave-{}.pgn", std::process::id()));
+        let fallback = fallback.to_str().unwrap();
+        std::fs::write(fen, "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1\n").unwrap();
+
+        let mut game = Game::open(path).unwrap();
+        assert_eq!(game.path, None);
+        game.execute(Command::Move("Nf3".to_string()));
+        let saved = game.autosave_to(fallback).unwrap().unwrap();
+        assert_eq!(saved, fallback);
+        std::fs::remove_file(fallback).unwrap();
+        assert_eq!(std::fs::read_to_string(path).unwrap(), collection);
+        std::fs::remove_file(path).unwrap();
+
+        let game = Game::open(fen).unwrap();
+        std::fs::remove_file(fen).unwrap();
+        assert_eq!(game.path, None);
+    }
+
+    #[test]
+    fn autosave_only_unfinished_games() {
+        let mut game = Game::default();
+        assert!(game.autosave().is_none());
+        game.execute(Command::Resign);
+        assert!(game.autosave().is_none());
+    }
+}
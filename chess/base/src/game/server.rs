@@ -0,0 +1,860 @@
+use super::{command::Start, Game, Outcome};
+use crate::{
+    board::position::Position,
+    pgn,
+    piece::{Color, Piece},
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::OpenOptions,
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    str::FromStr,
+    sync::{mpsc::Sender, Arc, Mutex, PoisonError},
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Where finished games are appended unless `--serve` names another file.
+pub const ARCHIVE: &str = "archive.pgn";
+
+pub const HELP: &str = "\
+Commands:
+  name <name>        choose the name others see you by
+  who                list everyone connected
+  seeks              list open seeks and challenges
+  seek [white|black] [960|variant]
+                     ask for a game with anyone
+  challenge <name> [white|black] [960|variant]
+                     ask one player for a game
+  accept <n>         take up seek or challenge n
+  decline <n>        withdraw your seek n, or turn down challenge n
+  games              list games in progress
+  watch <n>          follow game n as a spectator
+  unwatch <n>        stop following game n
+  board [n]          show your game, or game n
+  <move>             play a move in your game, e.g. e4, Nf3 or e2e4
+  resign             give up your game
+  draw               offer a draw, or accept the one on the table
+  quit               leave, forfeiting any game in progress";
+
+/// What a player asked for: a colour, or either, and the setup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Offer {
+    color: Option<Color>,
+    start: Start,
+}
+
+impl FromStr for Offer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut offer = Self {
+            color: None,
+            start: Start::Standard,
+        };
+        for word in s.split_whitespace() {
+            match word {
+                "white" => offer.color = Some(Color::White),
+                "black" => offer.color = Some(Color::Black),
+                "960" => offer.start = Start::Chess960(None),
+                variant => offer.start = Start::Variant(variant.parse()?),
+            }
+        }
+        Ok(offer)
+    }
+}
+
+/// A line sent by a client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Request {
+    Help,
+    Name(String),
+    Who,
+    Seeks,
+    Seek(Offer),
+    Challenge(String, Offer),
+    Accept(usize),
+    Decline(usize),
+    Games,
+    Watch(usize),
+    Unwatch(usize),
+    Board(Option<usize>),
+    Move(String),
+    Resign,
+    Draw,
+    Quit,
+}
+
+impl FromStr for Request {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (word, argument) = s
+            .split_once(char::is_whitespace)
+            .map_or((s, ""), |(word, argument)| (word, argument.trim()));
+        let number = || {
+            argument
+                .parse::<usize>()
+                .map_err(|_| format!("usage: {word} <n>"))
+        };
+
+        match word {
+            "" => Err("Type a command or 'help'".to_string()),
+            "help" | "?" => Ok(Self::Help),
+            "name" if !argument.is_empty() && !argument.contains(char::is_whitespace) => {
+                Ok(Self::Name(argument.to_string()))
+            }
+            "name" => Err("usage: name <name>, without spaces".to_string()),
+            "who" => Ok(Self::Who),
+            "seeks" => Ok(Self::Seeks),
+            "seek" => argument.parse().map(Self::Seek),
+            "challenge" => {
+                let (name, offer) = argument
+                    .split_once(char::is_whitespace)
+                    .unwrap_or((argument, ""));
+                if name.is_empty() {
+                    return Err("usage: challenge <name> [white|black] [960|variant]".to_string());
+                }
+                Ok(Self::Challenge(name.to_string(), offer.parse()?))
+            }
+            "accept" => number().map(Self::Accept),
+            "decline" => number().map(Self::Decline),
+            "games" => Ok(Self::Games),
+            "watch" => number().map(Self::Watch),
+            "unwatch" => number().map(Self::Unwatch),
+            "board" if argument.is_empty() => Ok(Self::Board(None)),
+            "board" => number().map(|n| Self::Board(Some(n))),
+            "resign" => Ok(Self::Resign),
+            "draw" => Ok(Self::Draw),
+            "q" | "quit" => Ok(Self::Quit),
+            _ if argument.is_empty() => Ok(Self::Move(s.to_string())),
+            _ => Err(format!("Unknown command '{word}'")),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Client {
+    name: String,
+    /// Lines waiting to be written to the client's socket.
+    outbox: Sender<String>,
+    /// The game they are playing, if any; one at a time.
+    table: Option<usize>,
+}
+
+/// An offer to play, open to anyone or made to one player.
+#[derive(Debug)]
+struct Seek {
+    by: usize,
+    to: Option<usize>,
+    offer: Offer,
+}
+
+/// A game in progress and who is following it.
+#[derive(Debug)]
+struct Table {
+    game: Game,
+    white: usize,
+    black: usize,
+    spectators: BTreeSet<usize>,
+}
+
+impl Table {
+    const fn color_of(&self, client: usize) -> Color {
+        if client == self.white {
+            Color::White
+        } else {
+            Color::Black
+        }
+    }
+
+    /// The players and spectators, who all hear about each move.
+    fn audience(&self) -> impl Iterator<Item = usize> + '_ {
+        [self.white, self.black]
+            .into_iter()
+            .chain(self.spectators.iter().copied())
+    }
+
+    fn title(&self, id: usize) -> String {
+        let game = &self.game;
+        let setup = if game.start.is_chess960() {
+            "Chess960".to_string()
+        } else {
+            game.start.variant.to_string()
+        };
+        format!(
+            "game {id}: {} vs {}, {setup}, {} moves played",
+            game.white,
+            game.black,
+            game.history.len()
+        )
+    }
+}
+
+/// Everyone connected, with their seeks and games. Seeks and games share
+/// one sequence of numbers so `accept 3` and `watch 3` cannot be confused.
+#[derive(Debug, Default)]
+pub struct Lobby {
+    clients: BTreeMap<usize, Client>,
+    seeks: BTreeMap<usize, Seek>,
+    tables: BTreeMap<usize, Table>,
+    next_id: usize,
+    /// PGN file finished games are appended to.
+    archive: Option<String>,
+}
+
+impl Lobby {
+    #[must_use]
+    pub fn new(archive: Option<String>) -> Self {
+        Self {
+            archive,
+            ..Self::default()
+        }
+    }
+
+    const fn next_id(&mut self) -> usize {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// Registers a client whose lines go to `outbox`, and greets them.
+    pub fn connect(&mut self, outbox: Sender<String>) -> usize {
+        let id = self.next_id();
+        let name = format!("guest{id}");
+        outbox
+            .send(format!("Welcome, {name}. Type 'help' for commands."))
+            .ok();
+        self.clients.insert(
+            id,
+            Client {
+                name,
+                outbox,
+                table: None,
+            },
+        );
+        id
+    }
+
+    /// Forgets a client, forfeiting their game and dropping their seeks.
+    pub fn disconnect(&mut self, id: usize) {
+        if let Some(table_id) = self.clients.get(&id).and_then(|client| client.table) {
+            if let Some(table) = self.tables.get_mut(&table_id) {
+                let color = table.color_of(id);
+                table.game.outcome = Some(Outcome::Abandoned(color.opposite()));
+                self.finish(table_id);
+            }
+        }
+        self.seeks
+            .retain(|_, seek| seek.by != id && seek.to != Some(id));
+        for table in self.tables.values_mut() {
+            table.spectators.remove(&id);
+        }
+        self.clients.remove(&id);
+    }
+
+    /// Runs one line from client `id`; false once they have asked to leave.
+    pub fn handle(&mut self, id: usize, line: &str) -> bool {
+        let reply = match line.parse::<Request>() {
+            Ok(Request::Quit) => {
+                self.send(id, "Bye");
+                return false;
+            }
+            Ok(request) => self.request(id, request),
+            Err(e) => Err(e),
+        };
+        match reply {
+            Ok(reply) if reply.is_empty() => {}
+            Ok(reply) => self.send(id, reply),
+            Err(e) => self.send(id, format!("error: {e}")),
+        }
+        true
+    }
+
+    fn send(&self, id: usize, line: impl Into<String>) {
+        if let Some(client) = self.clients.get(&id) {
+            // A client that has gone is cleaned up by its own thread.
+            client.outbox.send(line.into()).ok();
+        }
+    }
+
+    fn name(&self, id: usize) -> &str {
+        self.clients.get(&id).map_or("?", |client| &client.name)
+    }
+
+    fn request(&mut self, id: usize, request: Request) -> Result<String, String> {
+        match request {
+            Request::Help => Ok(HELP.to_string()),
+            Request::Name(name) => self.rename(id, name),
+            Request::Who => Ok(self
+                .clients
+                .values()
+                .map(|client| {
+                    client.table.map_or_else(
+                        || client.name.clone(),
+                        |table| format!("{} (playing game {table})", client.name),
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")),
+            Request::Seeks => Ok(self.list_seeks(id)),
+            Request::Seek(offer) => self.seek(id, None, offer),
+            Request::Challenge(name, offer) => {
+                let to = self
+                    .clients
+                    .iter()
+                    .find(|(_, client)| client.name == name)
+                    .map(|(&to, _)| to)
+                    .ok_or_else(|| format!("Nobody called {name} is here"))?;
+                if to == id {
+                    return Err("You cannot challenge yourself".to_string());
+                }
+                self.seek(id, Some(to), offer)
+            }
+            Request::Accept(seek) => self.accept(id, seek),
+            Request::Decline(seek) => self.decline(id, seek),
+            Request::Games if self.tables.is_empty() => Ok("No games in progress".to_string()),
+            Request::Games => Ok(self
+                .tables
+                .iter()
+                .map(|(&table_id, table)| table.title(table_id))
+                .collect::<Vec<_>>()
+                .join("\n")),
+            Request::Watch(table_id) => {
+                let table = self.table_mut(table_id)?;
+                table.spectators.insert(id);
+                Ok(format!(
+                    "Watching {}\n{}",
+                    table.title(table_id),
+                    diagram(table.game.position())
+                ))
+            }
+            Request::Unwatch(table_id) => {
+                if self.table_mut(table_id)?.spectators.remove(&id) {
+                    Ok(format!("Stopped watching game {table_id}"))
+                } else {
+                    Err(format!("You are not watching game {table_id}"))
+                }
+            }
+            Request::Board(table_id) => {
+                let table_id = table_id.map_or_else(|| self.playing(id), Ok)?;
+                let table = self.table_mut(table_id)?;
+                Ok(format!(
+                    "{}\n{}",
+                    table.title(table_id),
+                    diagram(table.game.position())
+                ))
+            }
+            Request::Move(text) => self.play(id, &text),
+            Request::Resign => {
+                let table_id = self.playing(id)?;
+                let table = self.table_mut(table_id)?;
+                let color = table.color_of(id);
+                table.game.resign(color)?;
+                self.finish(table_id);
+                Ok(String::new())
+            }
+            Request::Draw => {
+                let table_id = self.playing(id)?;
+                let table = self.table_mut(table_id)?;
+                let color = table.color_of(id);
+                let offer = table.game.draw(color)?;
+                if table.game.outcome.is_some() {
+                    self.finish(table_id);
+                } else {
+                    let opponent = match color {
+                        Color::White => table.black,
+                        Color::Black => table.white,
+                    };
+                    self.send(opponent, format!("game {table_id}: {offer}"));
+                }
+                Ok(String::new())
+            }
+            Request::Quit => Ok(String::new()),
+        }
+    }
+
+    fn rename(&mut self, id: usize, name: String) -> Result<String, String> {
+        if self.clients.values().any(|client| client.name == name) {
+            return Err(format!("{name} is taken"));
+        }
+        let client = self.clients.get_mut(&id).ok_or("Not connected")?;
+        if let Some(table) = client.table {
+            return Err(format!("Finish game {table} first"));
+        }
+        let message = format!("You are now {name}");
+        client.name = name;
+        Ok(message)
+    }
+
+    fn table_mut(&mut self, id: usize) -> Result<&mut Table, String> {
+        self.tables
+            .get_mut(&id)
+            .ok_or_else(|| format!("There is no game {id}"))
+    }
+
+    /// The game `id` is playing.
+    fn playing(&self, id: usize) -> Result<usize, String> {
+        self.clients
+            .get(&id)
+            .and_then(|client| client.table)
+            .ok_or_else(|| "You are not playing a game".to_string())
+    }
+
+    fn describe_seek(&self, seek_id: usize, seek: &Seek) -> String {
+        let color = match seek.offer.color {
+            Some(Color::White) => "white",
+            Some(Color::Black) => "black",
+            None => "either colour",
+        };
+        let setup = match seek.offer.start {
+            Start::Standard => "standard".to_string(),
+            Start::Chess960(_) => "Chess960".to_string(),
+            Start::Variant(variant) => variant.to_string(),
+        };
+        let to = seek
+            .to
+            .map_or_else(String::new, |to| format!(" to {}", self.name(to)));
+        format!(
+            "{} {seek_id}: {}{to} plays {color}, {setup}",
+            if seek.to.is_some() {
+                "challenge"
+            } else {
+                "seek"
+            },
+            self.name(seek.by)
+        )
+    }
+
+    /// Open seeks, plus any challenges this client made or received.
+    fn list_seeks(&self, id: usize) -> String {
+        let lines = self
+            .seeks
+            .iter()
+            .filter(|(_, seek)| seek.to.is_none() || seek.by == id || seek.to == Some(id))
+            .map(|(&seek_id, seek)| self.describe_seek(seek_id, seek))
+            .collect::<Vec<_>>();
+        if lines.is_empty() {
+            "No open seeks".to_string()
+        } else {
+            lines.join("\n")
+        }
+    }
+
+    fn seek(&mut self, id: usize, to: Option<usize>, offer: Offer) -> Result<String, String> {
+        if let Ok(table) = self.playing(id) {
+            return Err(format!("Finish game {table} first"));
+        }
+        let seek_id = self.next_id();
+        let seek = Seek { by: id, to, offer };
+        let line = self.describe_seek(seek_id, &seek);
+        match to {
+            Some(to) => self.send(
+                to,
+                format!("{line}. Type 'accept {seek_id}' or 'decline {seek_id}'"),
+            ),
+            None => {
+                for &other in self.clients.keys().filter(|&&other| other != id) {
+                    self.send(other, format!("{line}. Type 'accept {seek_id}' to play"));
+                }
+            }
+        }
+        self.seeks.insert(seek_id, seek);
+        Ok(format!("Posted {line}"))
+    }
+
+    fn decline(&mut self, id: usize, seek_id: usize) -> Result<String, String> {
+        match self.seeks.get(&seek_id) {
+            Some(seek) if seek.by == id => {
+                self.seeks.remove(&seek_id);
+                Ok(format!("Withdrew {seek_id}"))
+            }
+            Some(seek) if seek.to == Some(id) => {
+                let by = seek.by;
+                self.seeks.remove(&seek_id);
+                self.send(
+                    by,
+                    format!("{} declined challenge {seek_id}", self.name(id)),
+                );
+                Ok(format!("Declined challenge {seek_id}"))
+            }
+            _ => Err(format!("No seek {seek_id} of yours or for you")),
+        }
+    }
+
+    fn accept(&mut self, id: usize, seek_id: usize) -> Result<String, String> {
+        let seek = self
+            .seeks
+            .get(&seek_id)
+            .filter(|seek| seek.to.is_none() || seek.to == Some(id))
+            .ok_or_else(|| format!("There is no seek {seek_id} for you"))?;
+        if seek.by == id {
+            return Err("You cannot accept your own seek".to_string());
+        }
+        if let Ok(table) = self.playing(id) {
+            return Err(format!("Finish game {table} first"));
+        }
+        let (by, offer) = (seek.by, seek.offer);
+        let color = offer.color.unwrap_or_else(|| {
+            if crate::rng::Rng::default().below(2) == 0 {
+                Color::White
+            } else {
+                Color::Black
+            }
+        });
+        let (white, black) = match color {
+            Color::White => (by, id),
+            Color::Black => (id, by),
+        };
+
+        let mut game = Game::default();
+        game.restart(offer.start)?;
+        game.white = self.name(white).to_string();
+        game.black = self.name(black).to_string();
+        // Both players are busy now, so their other seeks lapse.
+        self.seeks.retain(|_, seek| {
+            ![white, black].contains(&seek.by)
+                && seek.to.is_none_or(|to| ![white, black].contains(&to))
+        });
+        let table_id = self.next_id();
+        let table = Table {
+            game,
+            white,
+            black,
+            spectators: BTreeSet::new(),
+        };
+        let board = diagram(table.game.position());
+        let title = table.title(table_id);
+        for (player, color) in [(white, Color::White), (black, Color::Black)] {
+            if let Some(client) = self.clients.get_mut(&player) {
+                client.table = Some(table_id);
+            }
+            self.send(
+                player,
+                format!("Started {title}. You play {color}.\n{board}"),
+            );
+        }
+        self.tables.insert(table_id, table);
+        Ok(String::new())
+    }
+
+    /// Plays a move in `id`'s game and shows everyone following it the board.
+    fn play(&mut self, id: usize, text: &str) -> Result<String, String> {
+        let table_id = self.playing(id)?;
+        let table = self.table_mut(table_id)?;
+        let color = table.color_of(id);
+        if table.game.position.turn != color {
+            return Err(format!("It is {}'s move", color.opposite()));
+        }
+        let number = pgn::move_number(table.game.position());
+        table.game.try_move(text)?;
+        let san = table.game.history.last().map(|ply| ply.san.clone());
+        let update = format!(
+            "game {table_id}: {number} {}\n{}",
+            san.unwrap_or_default(),
+            diagram(table.game.position())
+        );
+        let table = &self.tables[&table_id];
+        for client in table.audience() {
+            self.send(client, update.clone());
+        }
+        if self.tables[&table_id].game.outcome.is_some() {
+            self.finish(table_id);
+        }
+        Ok(String::new())
+    }
+
+    /// Announces the result of a finished game, frees its players and
+    /// archives it.
+    fn finish(&mut self, table_id: usize) {
+        let Some(table) = self.tables.remove(&table_id) else {
+            return;
+        };
+        if let Some(outcome) = table.game.outcome {
+            let line = format!("game {table_id} over: {outcome} ({})", outcome.result());
+            for client in table.audience() {
+                self.send(client, line.clone());
+            }
+        }
+        for player in [table.white, table.black] {
+            if let Some(client) = self.clients.get_mut(&player) {
+                client.table = None;
+            }
+        }
+        if let Err(e) = self.archive(table_id, &table.game) {
+            eprintln!("{e}");
+        }
+    }
+
+    fn archive(&self, table_id: usize, game: &Game) -> Result<(), String> {
+        let Some(path) = &self.archive else {
+            return Ok(());
+        };
+        let mut pgn = game.to_pgn();
+        pgn.set_tag("Event", "Server game");
+        pgn.set_tag("Date", today());
+        pgn.set_tag("Round", table_id.to_string());
+        if let Some(outcome) = game.outcome {
+            pgn.set_tag("Termination", outcome.to_string());
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("Could not open {path}: {e}"))?;
+        writeln!(file, "{}", pgn.write()).map_err(|e| format!("Could not write {path}: {e}"))
+    }
+}
+
+/// The board as text, White at the bottom, for clients that only show lines.
+fn diagram(position: &Position) -> String {
+    let mut lines = position
+        .board
+        .0
+        .chunks(8)
+        .zip((1..=8).rev())
+        .map(|(row, rank)| {
+            let squares = row
+                .iter()
+                .map(|square| {
+                    square.map_or_else(|| ".".to_string(), |p: Piece| p.fen_char().to_string())
+                })
+                .collect::<Vec<_>>();
+            format!("{rank} {}", squares.join(" "))
+        })
+        .collect::<Vec<_>>();
+    lines.push("  a b c d e f g h".to_string());
+    lines.push(format!("{} to move", position.turn));
+    lines.join("\n")
+}
+
+/// Today's date as PGN writes it, from the system clock.
+fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() / 86_400);
+    date(days)
+}
+
+/// The `yyyy.mm.dd` date `days` after 1970-01-01, by Howard Hinnant's
+/// civil-from-days method.
+fn date(days: u64) -> String {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    format!("{year}.{month:02}.{day:02}")
+}
+
+/// Accepts clients on `port` until the process is stopped, appending
+/// finished games to `archive`.
+pub fn serve(port: u16, archive: &str) -> Result<(), String> {
+    let listener = TcpListener::bind(("0.0.0.0", port))
+        .map_err(|e| format!("Could not listen on port {port}: {e}"))?;
+    println!("Serving on port {port}, archiving games to {archive}");
+    run(
+        &listener,
+        &Arc::new(Mutex::new(Lobby::new(Some(archive.to_string())))),
+    );
+    Ok(())
+}
+
+fn run(listener: &TcpListener, lobby: &Arc<Mutex<Lobby>>) {
+    for stream in listener.incoming().flatten() {
+        let lobby = Arc::clone(lobby);
+        thread::spawn(move || client(stream, &lobby));
+    }
+}
+
+/// Serves one connection: a thread writes its outbox to the socket while
+/// this one reads its lines into the lobby.
+fn client(stream: TcpStream, lobby: &Mutex<Lobby>) {
+    let lock = || lobby.lock().unwrap_or_else(PoisonError::into_inner);
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    let (outbox, outgoing) = std::sync::mpsc::channel::<String>();
+    thread::spawn(move || {
+        for line in outgoing {
+            if writeln!(writer, "{line}").is_err() {
+                break;
+            }
+        }
+    });
+    let id = lock().connect(outbox);
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if !lock().handle(id, &line) {
+            break;
+        }
+    }
+    lock().disconnect(id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::{self, Receiver};
+
+    /// Connects a client and throws away the greeting.
+    fn join(lobby: &mut Lobby, name: &str) -> (usize, Receiver<String>) {
+        let (outbox, inbox) = mpsc::channel();
+        let id = lobby.connect(outbox);
+        assert!(lobby.handle(id, &format!("name {name}")));
+        inbox.try_iter().for_each(drop);
+        (id, inbox)
+    }
+
+    fn said(inbox: &Receiver<String>) -> String {
+        inbox.try_iter().collect::<Vec<_>>().join("\n")
+    }
+
+    #[test]
+    fn parse_requests() {
+        assert_eq!(
+            "seek".parse(),
+            Ok(Request::Seek(Offer {
+                color: None,
+                start: Start::Standard
+            }))
+        );
+        assert_eq!(
+            "challenge bob black 960".parse(),
+            Ok(Request::Challenge(
+                "bob".to_string(),
+                Offer {
+                    color: Some(Color::Black),
+                    start: Start::Chess960(None)
+                }
+            ))
+        );
+        assert_eq!("board 4".parse(), Ok(Request::Board(Some(4))));
+        assert_eq!("Nf3".parse(), Ok(Request::Move("Nf3".to_string())));
+        assert!("seek purple".parse::<Request>().is_err());
+        assert!("watch x".parse::<Request>().is_err());
+        assert!("name two words".parse::<Request>().is_err());
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(date(0), "1970.01.01");
+        assert_eq!(date(19_723), "2024.01.01");
+        assert_eq!(date(19_782), "2024.02.29");
+    }
+
+    #[test]
+    fn seek_play_and_watch() {
+        let path = std::env::temp_dir().join(format!("chess-archive-{}.pgn", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut lobby = Lobby::new(Some(path.to_string()));
+        let (alice, alice_inbox) = join(&mut lobby, "alice");
+        let (bob, bob_inbox) = join(&mut lobby, "bob");
+        let (carol, carol_inbox) = join(&mut lobby, "carol");
+
+        lobby.handle(alice, "seek white");
+        assert!(said(&bob_inbox).contains("seek 4: alice plays white, standard"));
+        said(&carol_inbox);
+        lobby.handle(carol, "seeks");
+        assert_eq!(said(&carol_inbox), "seek 4: alice plays white, standard");
+        lobby.handle(bob, "accept 4");
+        assert!(said(&alice_inbox).contains("Started game 5: alice vs bob"));
+        assert!(said(&bob_inbox).contains("You play Black"));
+
+        lobby.handle(carol, "watch 5");
+        assert!(said(&carol_inbox).starts_with("Watching game 5"));
+        lobby.handle(bob, "e5");
+        assert_eq!(said(&bob_inbox), "error: It is White's move");
+        for (player, line) in [(alice, "f3"), (bob, "e5"), (alice, "g4"), (bob, "Qh4#")] {
+            lobby.handle(player, line);
+        }
+        let seen = said(&carol_inbox);
+        assert!(seen.starts_with("game 5: 1. f3\n"));
+        assert!(seen.contains("game 5: 2... Qh4#\n"));
+        assert!(seen.ends_with("game 5 over: Checkmate, Black wins (0-1)"));
+        lobby.handle(alice, "games");
+        assert!(said(&alice_inbox).ends_with("No games in progress"));
+
+        let archived = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let games = pgn::PgnGame::parse_all(&archived).unwrap();
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].tag("White"), Some("alice"));
+        assert_eq!(games[0].result, "0-1");
+        assert_eq!(games[0].moves.len(), 4);
+    }
+
+    #[test]
+    fn challenges_draws_and_leaving() {
+        let mut lobby = Lobby::new(None);
+        let (alice, alice_inbox) = join(&mut lobby, "alice");
+        let (bob, bob_inbox) = join(&mut lobby, "bob");
+
+        lobby.handle(alice, "challenge bob");
+        assert!(said(&bob_inbox).starts_with("challenge 3: alice to bob"));
+        said(&alice_inbox);
+        lobby.handle(bob, "decline 3");
+        assert_eq!(said(&alice_inbox), "bob declined challenge 3");
+        assert_eq!(said(&bob_inbox), "Declined challenge 3");
+        lobby.handle(bob, "accept 3");
+        assert_eq!(said(&bob_inbox), "error: There is no seek 3 for you");
+
+        lobby.handle(alice, "challenge bob black koth");
+        lobby.handle(bob, "accept 4");
+        assert!(said(&alice_inbox).contains("King of the Hill"));
+        lobby.handle(alice, "draw");
+        assert!(said(&bob_inbox)
+            .ends_with("game 5: Black offers a draw. White can type 'draw' to accept"));
+        lobby.handle(alice, "name carol");
+        assert_eq!(said(&alice_inbox), "error: Finish game 5 first");
+
+        assert!(!lobby.handle(alice, "quit"));
+        lobby.disconnect(alice);
+        assert_eq!(
+            said(&bob_inbox),
+            "game 5 over: Black left, White wins (1-0)"
+        );
+        lobby.handle(bob, "who");
+        assert_eq!(said(&bob_inbox), "bob");
+    }
+
+    #[test]
+    fn serves_over_localhost() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || run(&listener, &Arc::new(Mutex::new(Lobby::new(None)))));
+
+        let connect = || {
+            let stream = TcpStream::connect(address).unwrap();
+            let mut lines = BufReader::new(stream.try_clone().unwrap()).lines();
+            assert!(lines.next().unwrap().unwrap().starts_with("Welcome"));
+            (stream, lines)
+        };
+        let (mut alice, mut alice_lines) = connect();
+        writeln!(alice, "name alice").unwrap();
+        assert_eq!(alice_lines.next().unwrap().unwrap(), "You are now alice");
+        let (mut bob, mut bob_lines) = connect();
+        writeln!(alice, "seek black").unwrap();
+        assert_eq!(
+            alice_lines.next().unwrap().unwrap(),
+            "Posted seek 3: alice plays black, standard"
+        );
+        assert!(bob_lines.next().unwrap().unwrap().starts_with("seek 3"));
+        writeln!(bob, "accept 3").unwrap();
+        writeln!(bob, "e4").unwrap();
+        let update = alice_lines.find(|line| line.as_ref().unwrap().starts_with("game 4: 1. e4"));
+        assert!(update.is_some());
+    }
+}
@@ -0,0 +1,469 @@
+//! Stepping through a recorded game, variations included. The board is
+//! drawn as in play with the move list in a panel beside it, the current
+//! move highlighted and its comment underneath. On a terminal too narrow
+//! for the panel, a few lines of it go under the board instead.
+
+use crate::{
+    board::{position::Position, Board, SPACE},
+    game::panel::{self, Layout, STATUS_ROW},
+    pgn::{move_number, PgnGame, PgnMove},
+    piece::Color,
+};
+use std::io::{stdin, stdout, Write};
+use termion::{clear, cursor, event::Key, input::TermRead, raw::IntoRawMode, style};
+
+const HELP: &str = "\
+left/right move  home/end first/last  down or 1-9 enter variation
+up leave variation  f flip  q quit";
+
+/// Most characters of moves per panel line, however wide the terminal.
+const PANEL_WIDTH: usize = 40;
+/// Panel lines beside the board; the list scrolls to keep the current move
+/// in view.
+const PANEL_HEIGHT: usize = Board::height(SPACE.1) as usize;
+/// Panel lines under the board when it does not fit beside it.
+const BELOW_HEIGHT: usize = 5;
+
+/// The symbol for the common move NAGs, `$1` to `$6`.
+fn nag_symbol(nag: u8) -> Option<&'static str> {
+    ["!", "?", "!!", "??", "!?", "?!"]
+        .get(usize::from(nag).checked_sub(1)?)
+        .copied()
+}
+
+/// A game and the place in it being looked at.
+#[derive(Debug, Clone)]
+pub struct Viewer {
+    game: PgnGame,
+    start: Position,
+    /// The variations entered, outermost first: the index of the move each
+    /// replaces in its line, and which of that move's variations it is.
+    branches: Vec<(usize, usize)>,
+    /// Moves played in the current line.
+    ply: usize,
+    flipped: bool,
+}
+
+/// Checks that every move in `line` and its variations is legal.
+fn check(position: &Position, line: &[PgnMove]) -> Result<(), String> {
+    let mut position = position.clone();
+    for pgn_move in line {
+        for variation in &pgn_move.variations {
+            check(&position, variation)?;
+        }
+        let number = move_number(&position);
+        position
+            .play(&pgn_move.san)
+            .map_err(|e| format!("{number} {}: {e}", pgn_move.san))?;
+    }
+    Ok(())
+}
+
+impl Viewer {
+    /// Opens the game at its start, once all its moves have been checked.
+    pub fn new(game: PgnGame, flipped: bool) -> Result<Self, String> {
+        let start = game.start_position()?;
+        check(&start, &game.moves)?;
+        Ok(Self {
+            game,
+            start,
+            branches: Vec::new(),
+            ply: 0,
+            flipped,
+        })
+    }
+
+    /// The line being stepped through, and the position it starts from.
+    fn line(&self) -> (&[PgnMove], Position) {
+        let mut line = self.game.moves.as_slice();
+        let mut position = self.start.clone();
+        for &(index, variation) in &self.branches {
+            play(&mut position, &line[..index]);
+            line = &line[index].variations[variation];
+        }
+        (line, position)
+    }
+
+    #[must_use]
+    pub fn position(&self) -> Position {
+        let (line, mut position) = self.line();
+        play(&mut position, &line[..self.ply]);
+        position
+    }
+
+    /// The move last played, which the panel highlights.
+    #[must_use]
+    pub fn current(&self) -> Option<&PgnMove> {
+        let (line, _) = self.line();
+        self.ply.checked_sub(1).map(|i| &line[i])
+    }
+
+    /// Handles one key press, returning whether to quit.
+    pub fn press(&mut self, key: Key) -> bool {
+        let length = self.line().0.len();
+        match key {
+            Key::Char('q') | Key::Esc | Key::Ctrl('c') => return true,
+            Key::Right => self.ply = (self.ply + 1).min(length),
+            Key::Left if self.ply == 0 => self.leave(),
+            Key::Left => self.ply -= 1,
+            Key::Home => {
+                self.branches.clear();
+                self.ply = 0;
+            }
+            Key::End => self.ply = length,
+            Key::Up => self.leave(),
+            Key::Down => self.enter(0),
+            Key::Char(c @ '1'..='9') => self.enter(usize::from(c as u8 - b'1')),
+            Key::Char('f') => self.flipped = !self.flipped,
+            _ => {}
+        }
+        false
+    }
+
+    /// Goes into a variation on the next move, playing its first move.
+    fn enter(&mut self, variation: usize) {
+        let (line, _) = self.line();
+        if line
+            .get(self.ply)
+            .is_some_and(|next| variation < next.variations.len())
+        {
+            self.branches.push((self.ply, variation));
+            self.ply = 1;
+        }
+    }
+
+    /// Goes back out to where the variation branched off.
+    fn leave(&mut self) {
+        if let Some((index, _)) = self.branches.pop() {
+            self.ply = index;
+        }
+    }
+
+    /// The current line as numbered moves, one panel line per entry of at
+    /// most `columns` characters, with the index of the line holding the
+    /// current move.
+    fn move_lines(&self, columns: usize) -> (Vec<String>, usize) {
+        let (line, mut position) = self.line();
+        let mut lines = vec![String::new()];
+        let mut width = 0;
+        let mut current = 0;
+        for (i, pgn_move) in line.iter().enumerate() {
+            let number = if i == 0 || position.turn == Color::White {
+                format!("{} ", move_number(&position))
+            } else {
+                String::new()
+            };
+            let san = pgn_move.san.clone()
+                + &pgn_move
+                    .nags
+                    .iter()
+                    .filter_map(|&nag| nag_symbol(nag))
+                    .collect::<String>();
+            let length = number.chars().count() + san.chars().count();
+            if width > 0 && width + length + 1 > columns {
+                lines.push(String::new());
+                width = 0;
+            }
+            let row = lines.len() - 1;
+            let text = &mut lines[row];
+            if width > 0 {
+                text.push(' ');
+                width += 1;
+            }
+            let entry = if i + 1 == self.ply {
+                current = row;
+                // Only the move itself is highlighted, not its number.
+                format!("{number}{}{san}{}", style::Invert, style::Reset)
+            } else {
+                number + &san
+            };
+            text.push_str(&entry);
+            width += length;
+            play(&mut position, std::slice::from_ref(pgn_move));
+        }
+        (lines, current)
+    }
+
+    /// The panel, `columns` wide and `height` lines high: who played, where
+    /// in the game this is, and the move list scrolled to the current move.
+    #[must_use]
+    pub fn panel(&self, columns: usize, height: usize) -> Vec<String> {
+        let tag = |name| self.game.tag(name).unwrap_or("?");
+        let header = format!("{} - {}  {}", tag("White"), tag("Black"), self.game.result);
+        let label = match self.branches.len() {
+            0 => "Main line".to_string(),
+            1 => "Variation".to_string(),
+            n => format!("Variation, {n} deep"),
+        };
+        let mut panel = [header, label]
+            .map(|line| line.chars().take(columns).collect::<String>())
+            .to_vec();
+        let (lines, current) = self.move_lines(columns);
+        let height = height - panel.len();
+        let first = (current + 1).saturating_sub(height);
+        panel.extend(lines.into_iter().skip(first).take(height));
+        panel
+    }
+
+    /// What goes under the board: the current move's comment, the
+    /// variations that branch off the next move, and the keys.
+    #[must_use]
+    pub fn status(&self) -> Vec<String> {
+        let mut status = Vec::new();
+        let comment = match self.current() {
+            Some(pgn_move) => pgn_move.comment.as_ref(),
+            None if self.branches.is_empty() => self.game.comment.as_ref(),
+            None => None,
+        };
+        status.extend(comment.cloned());
+        let (line, _) = self.line();
+        if let Some(next) = line
+            .get(self.ply)
+            .filter(|next| !next.variations.is_empty())
+        {
+            let position = self.position();
+            let choices = next
+                .variations
+                .iter()
+                .enumerate()
+                .filter_map(|(i, variation)| {
+                    let first = variation.first()?;
+                    Some(format!(
+                        "{}) {} {}",
+                        i + 1,
+                        move_number(&position),
+                        first.san
+                    ))
+                })
+                .collect::<Vec<_>>();
+            status.push(format!(
+                "Instead of {} {}: {}",
+                move_number(&position),
+                next.san,
+                choices.join("  ")
+            ));
+        }
+        status.extend(HELP.lines().map(ToString::to_string));
+        status
+    }
+
+    /// Lays the panel out on a terminal `width` columns wide: beside the
+    /// board if there is room, otherwise its first lines under it.
+    #[must_use]
+    pub fn layout(&self, width: Option<u16>) -> Layout {
+        panel::room_beside(width).map_or_else(
+            || {
+                let columns = width.map_or(PANEL_WIDTH, usize::from);
+                Layout::Below(self.panel(columns.min(PANEL_WIDTH), BELOW_HEIGHT))
+            },
+            |room| Layout::Beside(self.panel(room.min(PANEL_WIDTH), PANEL_HEIGHT)),
+        )
+    }
+
+    fn draw(&self) {
+        self.position().board.print(self.flipped);
+        let mut out = stdout();
+        let mut below = Vec::new();
+        match self.layout(panel::terminal_width()) {
+            Layout::Beside(lines) => {
+                for (row, line) in (1..).zip(lines) {
+                    write!(
+                        out,
+                        "{}{}{line}",
+                        cursor::Goto(panel::COLUMN, row),
+                        clear::UntilNewline
+                    )
+                    .unwrap();
+                }
+            }
+            Layout::Below(lines) => below = lines,
+        }
+        write!(out, "{}", cursor::Goto(1, STATUS_ROW)).unwrap();
+        for line in below.into_iter().chain(self.status()) {
+            write!(out, "{}{line}\r\n", clear::CurrentLine).unwrap();
+        }
+        out.flush().unwrap();
+    }
+}
+
+/// Plays moves already known to be legal.
+fn play(position: &mut Position, moves: &[PgnMove]) {
+    for pgn_move in moves {
+        position
+            .play(&pgn_move.san)
+            .expect("moves are checked when the game is opened");
+    }
+}
+
+/// Runs the viewer on the terminal until the player quits.
+pub fn view(mut viewer: Viewer) {
+    let raw = stdout().into_raw_mode().unwrap();
+    loop {
+        viewer.draw();
+        let Some(Ok(key)) = stdin().keys().next() else {
+            break;
+        };
+        if viewer.press(key) {
+            break;
+        }
+    }
+    drop(raw);
+    println!();
+}
+
+/// Entry point for `--view <games.pgn> [n]`; returns the process exit code.
+pub fn main(args: &[String]) -> i32 {
+    let result = (|| {
+        let [path, rest @ ..] = args else {
+            return Err("usage: base --view <games.pgn> [n]".to_string());
+        };
+        let number = rest.first().map_or(Ok(1), |n| {
+            n.parse::<usize>()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| format!("Invalid game number '{n}'"))
+        })?;
+        let text =
+            std::fs::read_to_string(path).map_err(|e| format!("Could not read {path}: {e}"))?;
+        let games = PgnGame::parse_all(&text)?;
+        let count = games.len();
+        let game = games
+            .into_iter()
+            .nth(number - 1)
+            .ok_or_else(|| format!("{path} has {count} games"))?;
+        view(Viewer::new(game, false).map_err(|e| format!("Game {number}: {e}"))?);
+        Ok(())
+    })();
+    result.map_or_else(
+        |e| {
+            eprintln!("{e}");
+            1
+        },
+        |()| 0,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAME: &str = r#"[White "Ann"]
+[Black "Bob"]
+[Result "1-0"]
+{A short one} 1. e4 e5 2. Nf3 {develops} Nc6 (2... d6 3. d4 (3. Bc4 Be7) exd4)
+(2... Nf6) 3. Bc4 $1 Nd4 $2 4. Nxe5 1-0"#;
+
+    fn viewer() -> Viewer {
+        let game = PgnGame::parse_all(GAME).unwrap().remove(0);
+        Viewer::new(game, false).unwrap()
+    }
+
+    fn panel(viewer: &Viewer) -> Vec<String> {
+        viewer.panel(PANEL_WIDTH, PANEL_HEIGHT)
+    }
+
+    fn highlighted(san: &str) -> String {
+        format!("{}{san}{}", style::Invert, style::Reset)
+    }
+
+    #[test]
+    fn steps_through_the_main_line() {
+        let mut viewer = viewer();
+        assert_eq!(viewer.position(), Position::default());
+        assert_eq!(viewer.status()[0], "A short one");
+        assert_eq!(panel(&viewer)[..2], ["Ann - Bob  1-0", "Main line"]);
+
+        for _ in 0..3 {
+            viewer.press(Key::Right);
+        }
+        assert_eq!(viewer.current().unwrap().san, "Nf3");
+        assert_eq!(viewer.status()[0], "develops");
+        assert_eq!(
+            viewer.status()[1],
+            "Instead of 2... Nc6: 1) 2... d6  2) 2... Nf6"
+        );
+        assert_eq!(
+            panel(&viewer)[2],
+            format!(
+                "1. e4 e5 2. {} Nc6 3. Bc4! Nd4? 4. Nxe5",
+                highlighted("Nf3")
+            )
+        );
+
+        viewer.press(Key::End);
+        assert_eq!(viewer.current().unwrap().san, "Nxe5");
+        viewer.press(Key::Right);
+        assert_eq!(viewer.current().unwrap().san, "Nxe5");
+        viewer.press(Key::Left);
+        assert_eq!(viewer.current().unwrap().san, "Nd4");
+        viewer.press(Key::Home);
+        assert_eq!(viewer.current(), None);
+        assert!(viewer.press(Key::Char('q')));
+    }
+
+    #[test]
+    fn enters_and_leaves_variations() {
+        let mut viewer = viewer();
+        // Nothing branches off the first move.
+        viewer.press(Key::Down);
+        assert_eq!(panel(&viewer)[1], "Main line");
+        for _ in 0..3 {
+            viewer.press(Key::Right);
+        }
+        viewer.press(Key::Char('2'));
+        assert_eq!(viewer.current().unwrap().san, "Nf6");
+        assert_eq!(panel(&viewer)[2], format!("2... {}", highlighted("Nf6")));
+
+        viewer.press(Key::Up);
+        viewer.press(Key::Down);
+        viewer.press(Key::Down);
+        assert_eq!(panel(&viewer)[1], "Variation, 2 deep");
+        assert_eq!(viewer.current().unwrap().san, "Bc4");
+        viewer.press(Key::End);
+        let mut expected = Position::default();
+        for san in ["e4", "e5", "Nf3", "d6", "Bc4", "Be7"] {
+            expected.play(san).unwrap();
+        }
+        assert_eq!(viewer.position(), expected);
+
+        // Stepping back past a variation's start leaves it.
+        for _ in 0..3 {
+            viewer.press(Key::Left);
+        }
+        assert_eq!(panel(&viewer)[1], "Variation");
+        assert_eq!(viewer.current().unwrap().san, "d6");
+        viewer.press(Key::Home);
+        assert_eq!(panel(&viewer)[1], "Main line");
+    }
+
+    #[test]
+    fn fits_the_terminal() {
+        let mut viewer = viewer();
+        viewer.press(Key::End);
+        let Layout::Beside(lines) = viewer.layout(Some(48)) else {
+            panic!("wide enough to go beside the board");
+        };
+        // Wrapped at the 24 columns left beside the board.
+        assert_eq!(lines[2], "1. e4 e5 2. Nf3 Nc6");
+        assert_eq!(lines[3], format!("3. Bc4! Nd4? 4. {}", highlighted("Nxe5")));
+        assert_eq!(lines.len(), 4);
+
+        let Layout::Below(lines) = viewer.layout(Some(20)) else {
+            panic!("too narrow to go beside the board");
+        };
+        assert_eq!(lines[0], "Ann - Bob  1-0");
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[3], format!("3. Bc4! Nd4? 4. {}", highlighted("Nxe5")));
+    }
+
+    #[test]
+    fn refuses_illegal_moves() {
+        let game = PgnGame::parse_all("1. e4 e5 (1... e4) 1-0")
+            .unwrap()
+            .remove(0);
+        assert_eq!(
+            Viewer::new(game, false).unwrap_err(),
+            "1... e4: Illegal move 'e4'"
+        );
+    }
+}
@@ -8,64 +8,222 @@
     clippy::nursery
 )]
 
+mod batch;
+mod bitbase;
 mod board;
+mod book;
+mod eco;
+mod engine;
+mod epd;
+mod explorer;
+mod game;
+mod pgn;
 mod piece;
+mod puzzle;
+mod review;
+mod rng;
+mod selfplay;
+mod suite;
+mod tune;
+mod uci;
+#[allow(dead_code)]
 mod point {
     pub struct Point(u16, u16);
 
     // impl From<(usize, usize)> for Point {}
 }
-mod game {
-    use std::io::{stdin, stdout, Write};
 
-    use crate::{board::Board, piece::Color};
+use game::{net, server, viewer, Game};
 
-    #[derive(Debug, Default)]
-    pub struct Game {
-        turn: Color,
-        board: Board,
-    }
-
-    impl Game {
-        pub fn play(&mut self) {
-            let mut flag: bool = true;
-            // while self.board.win() // TODO: check win stat (should board or game do it?)
-            while flag {
-                self.board.print();
+const USAGE: &str = "\
+usage: base [saved game]
+       base --batch [move file]   play a move list from a file or stdin
+       base --uci                 talk UCI on stdin and stdout, for chess GUIs; this is
+                                  the only mode where the engine ponders on the
+                                  opponent's time (go ponder, ponderhit)
+       base --host [port] [white|black]
+                                  wait for an opponent to join over TCP (port 7878)
+       base --join <host[:port]>  play against someone running --host
+       base --serve [port] [archive.pgn]
+                                  run a server for many games, with seeks and spectators
+       base --make-book <games.pgn> <book.bin> [plies]
+                                  build a Polyglot book from a PGN collection
+       base --index <games.pgn> [db] [plies]
+                                  index every position in a PGN collection for the
+                                  'explore' command (default positions.db, all plies)
+       base --make-bitbases [dir]  generate the endgame bitbases (default ./bitbases)
+       base --bench-suite <suite.epd> [depth | <n>s]
+                                  score the engine on an EPD suite such as WAC
+                                  (default 1s per position)
+       base --match <engine> <engine> [games] [openings] [out.pgn]
+                                  play two engine setups against each other, e.g.
+                                  depth=5 or time=0.2s,book=book.bin (100 games)
+       base --puzzles <file> [name]
+                                  solve puzzles from a CSV (Lichess layout) or EPD file,
+                                  keeping a rating and streak for each player
+       base --review <games.pgn> [out.pgn] [depth | <n>s]
+                                  annotate games with the engine's judgement of every move
+                                  and summarise each player's accuracy (default 1s per move)
+       base --view <games.pgn> [n]
+                                  step through game n (1) with the arrow keys,
+                                  variations and comments included
+       base --tune <data> [out] [iterations]
+                                  fit the evaluation to positions labelled with results,
+                                  one FEN and 1-0, 0-1 or 1/2-1/2 per line
+                                  (default params.txt, 500 iterations)";
 
-                let mut notation = String::with_capacity(10);
-                print!("{} move >>> ", self.turn);
-                stdout().flush().unwrap();
-                stdin().read_line(&mut notation).unwrap();
-                let notation = notation.trim();
-                if notation == "q" {
-                    println!("END");
-                    break;
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let mut game = match args.next().as_deref() {
+        Some("--batch") => std::process::exit(batch::main(args.next().as_deref())),
+        Some("--make-book") => {
+            let args = args.collect::<Vec<_>>();
+            std::process::exit(make_book(&args).map_or_else(
+                |e| {
+                    eprintln!("{e}");
+                    1
+                },
+                |()| 0,
+            ));
+        }
+        Some("--make-bitbases") => {
+            std::process::exit(make_bitbases(args.next().as_deref()).map_or_else(
+                |e| {
+                    eprintln!("{e}");
+                    1
+                },
+                |()| 0,
+            ));
+        }
+        Some("--index") => {
+            let args = args.collect::<Vec<_>>();
+            std::process::exit(explorer::main(&args));
+        }
+        Some("--bench-suite") => {
+            let Some(path) = args.next() else {
+                eprintln!("{USAGE}");
+                std::process::exit(2);
+            };
+            std::process::exit(suite::main(&path, args.next().as_deref()));
+        }
+        Some("--match") => {
+            let args = args.collect::<Vec<_>>();
+            std::process::exit(selfplay::main(&args));
+        }
+        Some("--uci") => std::process::exit(uci::main()),
+        Some("--puzzles") => {
+            let args = args.collect::<Vec<_>>();
+            std::process::exit(puzzle::main(&args));
+        }
+        Some("--review") => {
+            let args = args.collect::<Vec<_>>();
+            std::process::exit(review::main(&args));
+        }
+        Some("--view") => {
+            let args = args.collect::<Vec<_>>();
+            std::process::exit(viewer::main(&args));
+        }
+        Some("--tune") => {
+            let args = args.collect::<Vec<_>>();
+            std::process::exit(tune::main(&args));
+        }
+        Some("--host") => {
+            let args = args.collect::<Vec<_>>();
+            match host(&args) {
+                Ok(session) => session.play(),
+                Err(e) => {
+                    eprintln!("{e}");
+                    std::process::exit(1);
                 }
-
-                let Ok(thing) = self.board.move_piece(self.turn, notation) else {
-                    println!("ERROR");
-                    break
-                };
-
-                // println!(
-                //     "{} {} moves to ({}, {})",
-                //     self.turn, thing.0, thing.1, thing.2
-                // );
-
-                std::thread::sleep(std::time::Duration::from_secs(5));
-                self.next_turn();
             }
+            return;
         }
-        fn next_turn(&mut self) {
-            self.turn.flip();
+        Some("--join") => {
+            let session = args
+                .next()
+                .ok_or_else(|| USAGE.to_string())
+                .and_then(|address| net::join(&address, &net::local_name()));
+            match session {
+                Ok(session) => session.play(),
+                Err(e) => {
+                    eprintln!("{e}");
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        Some("--serve") => {
+            let args = args.collect::<Vec<_>>();
+            std::process::exit(serve(&args).map_or_else(
+                |e| {
+                    eprintln!("{e}");
+                    1
+                },
+                |()| 0,
+            ));
+        }
+        Some("-h" | "--help") => {
+            println!("{USAGE}");
+            return;
+        }
+        Some(path) => Game::open(path).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }),
+        None => Game::default(),
+    };
+    game.play();
+}
+
+fn make_book(args: &[String]) -> Result<(), String> {
+    let [pgn, out, rest @ ..] = args else {
+        return Err(USAGE.to_string());
+    };
+    let plies = rest.first().map_or(Ok(16), |n| {
+        n.parse().map_err(|_| format!("Invalid ply count '{n}'"))
+    })?;
+    let text = std::fs::read_to_string(pgn).map_err(|e| format!("Could not read {pgn}: {e}"))?;
+    let book = book::Book::from_pgn(&pgn::PgnGame::parse_all(&text)?, plies)?;
+    book.save(out)?;
+    println!("Wrote {} entries to {out}", book.len());
+    Ok(())
+}
+
+fn host(args: &[String]) -> Result<net::Session, String> {
+    let mut port = net::DEFAULT_PORT;
+    let mut color = None;
+    for arg in args {
+        if let Some(c) = net::parse_color(arg) {
+            color = Some(c);
+        } else {
+            port = arg.parse().map_err(|_| format!("Invalid port '{arg}'"))?;
         }
     }
+    let color = color.unwrap_or_else(|| {
+        if rng::Rng::default().below(2) == 0 {
+            piece::Color::White
+        } else {
+            piece::Color::Black
+        }
+    });
+    net::host(port, color, &net::local_name())
 }
 
-use game::Game;
+fn serve(args: &[String]) -> Result<(), String> {
+    let port = args.first().map_or(Ok(net::DEFAULT_PORT), |port| {
+        port.parse().map_err(|_| format!("Invalid port '{port}'"))
+    })?;
+    let archive = args.get(1).map_or(server::ARCHIVE, String::as_str);
+    server::serve(port, archive)
+}
 
-fn main() {
-    let mut game = Game::default();
-    game.play();
+fn make_bitbases(dir: Option<&str>) -> Result<(), String> {
+    let dir = dir.unwrap_or(bitbase::DIR);
+    let mut bitbases = bitbase::Bitbases::default();
+    for material in bitbase::Material::ALL {
+        let start = std::time::Instant::now();
+        bitbases.ensure(material, Some(dir))?;
+        println!("{material} done in {:.1}s", start.elapsed().as_secs_f64());
+    }
+    Ok(())
 }
@@ -0,0 +1,444 @@
+use crate::board::{movement::Move, position::Position};
+use std::fmt::Write;
+
+/// One move of PGN movetext with whatever annotations followed it.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PgnMove {
+    pub san: String,
+    pub nags: Vec<u8>,
+    pub comment: Option<String>,
+    pub variations: Vec<Vec<Self>>,
+}
+
+impl PgnMove {
+    #[must_use]
+    pub fn new(san: impl Into<String>) -> Self {
+        Self {
+            san: san.into(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub comment: Option<String>,
+    pub moves: Vec<PgnMove>,
+    pub result: String,
+}
+
+const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+impl PgnGame {
+    #[must_use]
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: impl Into<String>) {
+        let value = value.into();
+        match self.tags.iter_mut().find(|(key, _)| key == name) {
+            Some((_, old)) => *old = value,
+            None => self.tags.push((name.to_string(), value)),
+        }
+    }
+
+    /// The position the movetext starts from, honouring `FEN` and `Variant` tags.
+    pub fn start_position(&self) -> Result<Position, String> {
+        // The setup is checked once the variant is known, as atomic kings
+        // may touch.
+        let mut position = self
+            .tag("FEN")
+            .map_or_else(|| Ok(Position::default()), Position::parse_fen)?;
+        // Chess960 and unknown variants play by the standard rules.
+        if let Some(variant) = self.tag("Variant").and_then(|v| v.parse().ok()) {
+            position.variant = variant;
+        }
+        position.checked()
+    }
+
+    /// Replays the main line, naming the move number of the first bad move.
+    pub fn replay(&self) -> Result<(Position, Vec<Move>), String> {
+        let start = self.start_position()?;
+        let mut position = start.clone();
+        let mut moves = Vec::with_capacity(self.moves.len());
+        for pgn_move in &self.moves {
+            let number = move_number(&position);
+            let mv = position
+                .parse_move(&pgn_move.san)
+                .map_err(|e| format!("move {number}: {e}"))?;
+            position.make_move(mv);
+            moves.push(mv);
+        }
+        Ok((start, moves))
+    }
+
+    /// Parses every game in a PGN file.
+    pub fn parse_all(text: &str) -> Result<Vec<Self>, String> {
+        let mut tokens = Tokens::new(text);
+        let mut games = Vec::new();
+        while tokens.peek().is_some() {
+            games.push(Self::parse_tokens(&mut tokens)?);
+        }
+        Ok(games)
+    }
+
+    fn parse_tokens(tokens: &mut Tokens) -> Result<Self, String> {
+        let mut game = Self::default();
+        while let Some(Token::Tag(key, value)) = tokens.peek() {
+            game.tags.push((key.clone(), value.clone()));
+            tokens.next();
+        }
+        if let Some(Token::Comment(comment)) = tokens.peek() {
+            game.comment = Some(comment.clone());
+            tokens.next();
+        }
+        let (moves, result) = parse_line(tokens, 0)?;
+        game.moves = moves;
+        game.result = result
+            .or_else(|| game.tag("Result").map(str::to_string))
+            .unwrap_or_else(|| "*".to_string());
+        Ok(game)
+    }
+
+    /// Writes the game as PGN, tags first, with movetext wrapped at 80 columns.
+    #[must_use]
+    pub fn write(&self) -> String {
+        let mut out = String::new();
+        for name in SEVEN_TAG_ROSTER {
+            let value = match name {
+                "Result" => self.result.as_str(),
+                _ => self.tag(name).unwrap_or("?"),
+            };
+            writeln!(out, "[{name} \"{}\"]", escape(value)).unwrap();
+        }
+        for (key, value) in &self.tags {
+            if !SEVEN_TAG_ROSTER.contains(&key.as_str()) {
+                writeln!(out, "[{key} \"{}\"]", escape(value)).unwrap();
+            }
+        }
+        out.push('\n');
+
+        let mut words = Vec::new();
+        if let Some(comment) = &self.comment {
+            words.push(format!("{{{comment}}}"));
+        }
+        let (white_first, number) = self.start_position().map_or((true, 1), |p| {
+            (p.turn == crate::piece::Color::White, p.fullmove)
+        });
+        write_line(&self.moves, white_first, number, &mut words);
+        words.push(self.result.clone());
+
+        let mut line = String::new();
+        for word in words {
+            if !line.is_empty() && line.len() + word.len() + 1 > 80 {
+                out.push_str(&line);
+                out.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&word);
+        }
+        out.push_str(&line);
+        out.push('\n');
+        out
+    }
+}
+
+/// `12.` for White to move or `12...` for Black.
+#[must_use]
+pub fn move_number(position: &Position) -> String {
+    match position.turn {
+        crate::piece::Color::White => format!("{}.", position.fullmove),
+        crate::piece::Color::Black => format!("{}...", position.fullmove),
+    }
+}
+
+/// Moves played on from `position` in SAN, numbered: `12... Qd7 13. Nf3`.
+#[must_use]
+pub fn san_line(position: &Position, moves: &[Move]) -> String {
+    let mut position = position.clone();
+    let mut words = Vec::new();
+    for (i, &mv) in moves.iter().enumerate() {
+        if i == 0 || position.turn == crate::piece::Color::White {
+            words.push(move_number(&position));
+        }
+        words.push(position.san(mv));
+        position.make_move(mv);
+    }
+    words.join(" ")
+}
+
+fn write_line(moves: &[PgnMove], mut white: bool, mut number: u16, words: &mut Vec<String>) {
+    let mut need_number = true;
+    for pgn_move in moves {
+        if white {
+            words.push(format!("{number}."));
+        } else if need_number {
+            words.push(format!("{number}..."));
+        }
+        need_number = false;
+        words.push(pgn_move.san.clone());
+        words.extend(pgn_move.nags.iter().map(|nag| format!("${nag}")));
+        if let Some(comment) = &pgn_move.comment {
+            words.push(format!("{{{comment}}}"));
+            need_number = true;
+        }
+        for variation in &pgn_move.variations {
+            let mut inner = Vec::new();
+            write_line(variation, white, number, &mut inner);
+            if let Some(first) = inner.first_mut() {
+                first.insert(0, '(');
+            }
+            if let Some(last) = inner.last_mut() {
+                last.push(')');
+            }
+            words.extend(inner);
+            need_number = true;
+        }
+        if !white {
+            number += 1;
+        }
+        white = !white;
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn parse_line(tokens: &mut Tokens, depth: usize) -> Result<(Vec<PgnMove>, Option<String>), String> {
+    let mut moves: Vec<PgnMove> = Vec::new();
+    while let Some(token) = tokens.peek() {
+        match token {
+            Token::Tag(..) if depth == 0 => break,
+            Token::Tag(key, _) => return Err(format!("tag '{key}' inside a variation")),
+            Token::Result(result) => {
+                let result = result.clone();
+                tokens.next();
+                return Ok((moves, Some(result)));
+            }
+            Token::Close if depth > 0 => {
+                tokens.next();
+                return Ok((moves, None));
+            }
+            Token::Close => return Err("unmatched ')'".to_string()),
+            Token::Open => {
+                tokens.next();
+                let (variation, _) = parse_line(tokens, depth + 1)?;
+                moves
+                    .last_mut()
+                    .ok_or("variation before any move")?
+                    .variations
+                    .push(variation);
+            }
+            Token::Comment(comment) => {
+                if let Some(last) = moves.last_mut() {
+                    last.comment = Some(comment.clone());
+                }
+                tokens.next();
+            }
+            Token::Nag(nag) => {
+                if let Some(last) = moves.last_mut() {
+                    last.nags.push(*nag);
+                }
+                tokens.next();
+            }
+            Token::Number => {
+                tokens.next();
+            }
+            Token::San(san) => {
+                moves.push(PgnMove::new(san.clone()));
+                tokens.next();
+            }
+        }
+    }
+    if depth > 0 {
+        return Err("unterminated variation".to_string());
+    }
+    Ok((moves, None))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    Open,
+    Close,
+    Nag(u8),
+    Number,
+    San(String),
+    Result(String),
+}
+
+struct Tokens {
+    tokens: Vec<Token>,
+    index: usize,
+}
+
+impl Tokens {
+    fn new(text: &str) -> Self {
+        Self {
+            tokens: tokenize(text),
+            index: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index)
+    }
+
+    const fn next(&mut self) {
+        self.index += 1;
+    }
+}
+
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            '[' => {
+                chars.next();
+                let inner = chars.by_ref().take_while(|&c| c != ']').collect::<String>();
+                let (key, value) = inner
+                    .trim()
+                    .split_once(' ')
+                    .unwrap_or_else(|| (inner.trim(), ""));
+                let value = value.trim().trim_matches('"').replace("\\\"", "\"");
+                tokens.push(Token::Tag(key.to_string(), value.replace("\\\\", "\\")));
+            }
+            '{' => {
+                chars.next();
+                let comment = chars.by_ref().take_while(|&c| c != '}').collect::<String>();
+                tokens.push(Token::Comment(comment.trim().to_string()));
+            }
+            ';' => {
+                let comment = chars
+                    .by_ref()
+                    .take_while(|&c| c != '\n')
+                    .collect::<String>();
+                tokens.push(Token::Comment(comment[1..].trim().to_string()));
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "[]{}();".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.extend(word_tokens(&word));
+            }
+        }
+    }
+    tokens
+}
+
+/// Splits a bare word such as `12.e4` or `Nf3!` into tokens.
+fn word_tokens(word: &str) -> Vec<Token> {
+    match word {
+        "1-0" | "0-1" | "1/2-1/2" | "*" => return vec![Token::Result(word.to_string())],
+        _ => {}
+    }
+    if let Some(nag) = word.strip_prefix('$') {
+        return nag.parse().map(Token::Nag).into_iter().collect();
+    }
+
+    let mut tokens = Vec::new();
+    let digits = word.trim_start_matches(|c: char| c.is_ascii_digit());
+    let san = if digits.len() < word.len() && digits.starts_with('.') {
+        tokens.push(Token::Number);
+        digits.trim_start_matches('.')
+    } else {
+        word
+    };
+    if san.is_empty() {
+        return tokens;
+    }
+
+    let bare = san.trim_end_matches(['!', '?']);
+    tokens.push(Token::San(bare.to_string()));
+    let nag = match &san[bare.len()..] {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    };
+    tokens.extend(nag.map(Token::Nag));
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAME: &str = r#"[Event "Casual"]
+[White "Alice"]
+[Black "Bob"]
+[Result "1-0"]
+
+{Opening} 1. e4 e5 2. Nf3 {develops} Nc6 (2... d6 3. d4) 3. Bc4 $1 Nd4?
+4. Nxe5 Qg5 5. Nxf7 Qxg2 6. Rf1 Qxe4+ 7. Be2 Nf3# 1-0
+
+[Event "Second"]
+1. d4 d5 *
+"#;
+
+    #[test]
+    fn parse_games() {
+        let games = PgnGame::parse_all(GAME).unwrap();
+        assert_eq!(games.len(), 2);
+
+        let game = &games[0];
+        assert_eq!(game.tag("White"), Some("Alice"));
+        assert_eq!(game.comment.as_deref(), Some("Opening"));
+        assert_eq!(game.moves.len(), 14);
+        assert_eq!(game.moves[2].comment.as_deref(), Some("develops"));
+        assert_eq!(game.moves[3].variations[0][1].san, "d4");
+        assert_eq!(game.moves[4].nags, vec![1]);
+        assert_eq!(game.moves[5].nags, vec![2]);
+        assert_eq!(game.result, "1-0");
+
+        let (_, moves) = game.replay().unwrap();
+        assert_eq!(moves.len(), 14);
+        assert_eq!(games[1].result, "*");
+    }
+
+    #[test]
+    fn replay_reports_move_number() {
+        let game = &PgnGame::parse_all("1. e4 e5 2. Ke3 *").unwrap()[0];
+        let error = game.replay().unwrap_err();
+        assert!(error.starts_with("move 2."), "{error}");
+    }
+
+    #[test]
+    fn write_round_trip() {
+        let game = PgnGame::parse_all(GAME).unwrap().remove(0);
+        let written = game.write();
+        assert!(written.starts_with("[Event \"Casual\"]\n[Site \"?\"]"));
+        assert!(written.contains("2. Nf3 {develops} 2... Nc6 (2... d6 3. d4) 3. Bc4 $1"));
+        assert_eq!(PgnGame::parse_all(&written).unwrap()[0].moves, game.moves);
+    }
+}
@@ -1,6 +1,6 @@
 use core::{fmt::Display, str::FromStr};
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Color {
     #[default]
     White,
@@ -8,10 +8,33 @@ pub enum Color {
 }
 
 impl Color {
-    pub fn flip(&mut self) {
+    pub const fn flip(&mut self) {
+        *self = self.opposite();
+    }
+
+    #[must_use]
+    pub const fn opposite(self) -> Self {
         match self {
-            Color::White => *self = Color::Black,
-            Color::Black => *self = Color::White,
+            Self::White => Self::Black,
+            Self::Black => Self::White,
+        }
+    }
+
+    /// Direction pawns of this color advance in, in ranks.
+    #[must_use]
+    pub const fn forward(self) -> i8 {
+        match self {
+            Self::White => 1,
+            Self::Black => -1,
+        }
+    }
+
+    /// Rank index pieces of this color start on.
+    #[must_use]
+    pub const fn home_rank(self) -> u8 {
+        match self {
+            Self::White => 0,
+            Self::Black => 7,
         }
     }
 }
@@ -39,7 +62,7 @@ const WHITE_ROOK: char = '♖';
 const WHITE_QUEEN: char = '♕';
 const WHITE_KING: char = '♔';
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub enum Kind {
     #[default]
     Pawn,
@@ -50,6 +73,34 @@ pub enum Kind {
     King,
 }
 
+impl Kind {
+    /// Letter used by algebraic notation; pawns have none.
+    #[must_use]
+    pub const fn letter(self) -> Option<char> {
+        match self {
+            Self::Pawn => None,
+            Self::Knight => Some('N'),
+            Self::Bishop => Some('B'),
+            Self::Rook => Some('R'),
+            Self::Queen => Some('Q'),
+            Self::King => Some('K'),
+        }
+    }
+
+    /// Material value in centipawns.
+    #[must_use]
+    pub const fn value(self) -> i32 {
+        match self {
+            Self::Pawn => 100,
+            Self::Knight => 320,
+            Self::Bishop => 330,
+            Self::Rook => 500,
+            Self::Queen => 900,
+            Self::King => 0,
+        }
+    }
+}
+
 impl Display for Kind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match *self {
@@ -78,7 +129,7 @@ impl FromStr for Kind {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Piece {
     color: Color,
     kind: Kind,
@@ -92,6 +143,52 @@ impl Piece {
             kind: piece_kind,
         }
     }
+
+    #[must_use]
+    pub const fn color(self) -> Color {
+        self.color
+    }
+
+    #[must_use]
+    pub const fn kind(self) -> Kind {
+        self.kind
+    }
+
+    /// FEN letter: uppercase for White, lowercase for Black.
+    #[must_use]
+    pub const fn fen_char(self) -> char {
+        let c = match self.kind {
+            Kind::Pawn => 'P',
+            Kind::Knight => 'N',
+            Kind::Bishop => 'B',
+            Kind::Rook => 'R',
+            Kind::Queen => 'Q',
+            Kind::King => 'K',
+        };
+        match self.color {
+            Color::White => c,
+            Color::Black => c.to_ascii_lowercase(),
+        }
+    }
+
+    #[must_use]
+    pub const fn from_fen_char(c: char) -> Option<Self> {
+        let kind = match c.to_ascii_uppercase() {
+            'P' => Kind::Pawn,
+            'N' => Kind::Knight,
+            'B' => Kind::Bishop,
+            'R' => Kind::Rook,
+            'Q' => Kind::Queen,
+            'K' => Kind::King,
+            _ => return None,
+        };
+        let color = if c.is_ascii_uppercase() {
+            Color::White
+        } else {
+            Color::Black
+        };
+        Some(Self::new(color, kind))
+    }
 }
 
 impl Piece {
@@ -133,6 +230,18 @@ mod tests {
         assert_eq!(Piece::new(Color::Black, Kind::King).symbol(), BLACK_KING);
     }
 
+    #[test]
+    fn fen_char() {
+        let knight = Piece::new(Color::Black, Kind::Knight);
+        assert_eq!(knight.fen_char(), 'n');
+        assert_eq!(Piece::from_fen_char('n'), Some(knight));
+        assert_eq!(
+            Piece::from_fen_char('Q'),
+            Some(Piece::new(Color::White, Kind::Queen))
+        );
+        assert_eq!(Piece::from_fen_char('x'), None);
+    }
+
     #[test]
     fn parse_piecekind() {
         assert_eq!("n".parse::<Kind>(), Ok(Kind::Knight));
@@ -0,0 +1,569 @@
+use crate::{
+    board::{movement::Move, position::Position},
+    epd,
+    game::net,
+    pgn::san_line,
+    piece::Color,
+    rng::Rng,
+};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Display,
+    io::{stdin, stdout, Write},
+    str::FromStr,
+};
+
+/// Where players' puzzle ratings are kept.
+pub const RATINGS: &str = "puzzle-ratings.txt";
+
+/// Rating for a new player, and for puzzles that do not give one.
+const START_RATING: i32 = 1500;
+/// How far one result moves a rating.
+const K: f64 = 32.0;
+
+/// A position with the line that solves it, alternating between the
+/// solver's moves and the opponent's replies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Puzzle {
+    pub id: String,
+    pub position: Position,
+    pub solution: Vec<Move>,
+    pub rating: i32,
+    /// The opponent's move that set the puzzle, in SAN, when the file gives it.
+    pub setup: Option<String>,
+}
+
+impl Puzzle {
+    /// The side that has to find the moves.
+    #[must_use]
+    pub const fn solver(&self) -> Color {
+        self.position.turn
+    }
+}
+
+/// Reads the columns `id,fen,moves[,rating,...]`, the layout of the Lichess
+/// puzzle database. The moves are in UCI, and the first is the opponent's,
+/// played before the puzzle starts. A header line is skipped.
+pub fn parse_csv(text: &str) -> Result<Vec<Puzzle>, String> {
+    let mut puzzles = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let columns = line.split(',').map(str::trim).collect::<Vec<_>>();
+        if line.trim().is_empty() || (i == 0 && columns[0].eq_ignore_ascii_case("PuzzleId")) {
+            continue;
+        }
+        let error = |e: String| format!("line {}: {e}", i + 1);
+        let [id, fen, moves, rest @ ..] = columns.as_slice() else {
+            return Err(error(format!("Expected id,fen,moves: '{line}'")));
+        };
+        let mut position = Position::from_fen(fen).map_err(error)?;
+        let mut moves = moves.split_whitespace();
+        let setup = moves.next().ok_or_else(|| error("No moves".to_string()))?;
+        let setup_move = position.parse_move(setup).map_err(error)?;
+        let setup = position.san(setup_move);
+        position.make_move(setup_move);
+        let solution = line_moves(&position, moves).map_err(error)?;
+        let rating = rest
+            .first()
+            .map_or(Ok(START_RATING), |rating| {
+                rating
+                    .parse()
+                    .map_err(|_| format!("Invalid rating '{rating}'"))
+            })
+            .map_err(error)?;
+        puzzles.push(Puzzle {
+            id: (*id).to_string(),
+            position,
+            solution,
+            rating,
+            setup: Some(setup),
+        });
+    }
+    Ok(puzzles)
+}
+
+/// Reads EPD with the solution as a `pv` operation, in SAN or UCI, or just
+/// its first move as `bm`. A `rating` operation is optional.
+pub fn parse_epd(text: &str) -> Result<Vec<Puzzle>, String> {
+    epd::parse_all(text)?
+        .into_iter()
+        .enumerate()
+        .map(|(i, epd)| {
+            let error = |e: String| format!("position {}: {e}", i + 1);
+            let line = epd
+                .operation("pv")
+                .or_else(|| {
+                    epd.operation("bm")
+                        .map(|moves| &moves[..moves.len().min(1)])
+                })
+                .ok_or_else(|| error("No pv or bm operation".to_string()))?;
+            let solution =
+                line_moves(&epd.position, line.iter().map(String::as_str)).map_err(error)?;
+            let rating = epd
+                .operation("rating")
+                .and_then(|rating| rating.first())
+                .map_or(Ok(START_RATING), |rating| {
+                    rating
+                        .parse()
+                        .map_err(|_| error(format!("Invalid rating '{rating}'")))
+                })?;
+            Ok(Puzzle {
+                id: epd
+                    .id()
+                    .map_or_else(|| (i + 1).to_string(), ToString::to_string),
+                position: epd.position,
+                solution,
+                rating,
+                setup: None,
+            })
+        })
+        .collect()
+}
+
+/// Plays out a line of moves from `position`, each in SAN or UCI.
+fn line_moves<'a>(
+    position: &Position,
+    moves: impl Iterator<Item = &'a str>,
+) -> Result<Vec<Move>, String> {
+    let mut position = position.clone();
+    let line = moves
+        .map(|text| position.play(text))
+        .collect::<Result<Vec<_>, _>>()?;
+    if line.is_empty() {
+        return Err("No solution".to_string());
+    }
+    Ok(line)
+}
+
+/// Reads a puzzle file, as CSV if it is named so and as EPD otherwise.
+pub fn open(path: &str) -> Result<Vec<Puzzle>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Could not read {path}: {e}"))?;
+    let puzzles = if path.to_lowercase().ends_with(".csv") {
+        parse_csv(&text)
+    } else {
+        parse_epd(&text)
+    }
+    .map_err(|e| format!("{path}: {e}"))?;
+    if puzzles.is_empty() {
+        return Err(format!("No puzzles in {path}"));
+    }
+    Ok(puzzles)
+}
+
+/// What became of a move played against a puzzle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// Right, and the opponent answers with this.
+    Continue(Move),
+    Solved,
+    Wrong,
+}
+
+/// A puzzle being solved, move by move.
+#[derive(Debug, Clone)]
+pub struct Attempt<'a> {
+    puzzle: &'a Puzzle,
+    pub position: Position,
+    ply: usize,
+}
+
+impl<'a> Attempt<'a> {
+    #[must_use]
+    pub fn new(puzzle: &'a Puzzle) -> Self {
+        Self {
+            puzzle,
+            position: puzzle.position.clone(),
+            ply: 0,
+        }
+    }
+
+    /// The move the solution expects next.
+    #[must_use]
+    pub fn expected(&self) -> Move {
+        self.puzzle.solution[self.ply]
+    }
+
+    /// The rest of the solution in SAN, from the move expected now.
+    #[must_use]
+    pub fn rest(&self) -> String {
+        san_line(&self.position, &self.puzzle.solution[self.ply..])
+    }
+
+    /// Plays the solver's move and the opponent's answer. A mate other
+    /// than the one in the solution counts as solving the puzzle too.
+    pub fn play(&mut self, mv: Move) -> Verdict {
+        let next = self.position.after(mv);
+        let mates = next.is_checkmate();
+        if mv != self.expected() && !mates {
+            return Verdict::Wrong;
+        }
+        self.position = next;
+        self.ply += 1;
+        let Some(&reply) = self.puzzle.solution.get(self.ply).filter(|_| !mates) else {
+            return Verdict::Solved;
+        };
+        self.position.make_move(reply);
+        self.ply += 1;
+        if self.ply == self.puzzle.solution.len() {
+            return Verdict::Solved;
+        }
+        Verdict::Continue(reply)
+    }
+}
+
+/// A player's puzzle record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Record {
+    pub rating: i32,
+    pub streak: u32,
+    pub best_streak: u32,
+    pub solved: u32,
+    pub tried: u32,
+}
+
+impl Default for Record {
+    fn default() -> Self {
+        Self {
+            rating: START_RATING,
+            streak: 0,
+            best_streak: 0,
+            solved: 0,
+            tried: 0,
+        }
+    }
+}
+
+impl Record {
+    /// Scores an attempt at a puzzle rated `rating` as a game between the
+    /// player and the puzzle, returning the change in rating.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn update(&mut self, rating: i32, solved: bool) -> i32 {
+        let expected = 1.0 / (1.0 + 10f64.powf(f64::from(rating - self.rating) / 400.0));
+        let change = (K * (f64::from(u8::from(solved)) - expected)).round() as i32;
+        self.rating += change;
+        self.tried += 1;
+        if solved {
+            self.solved += 1;
+            self.streak += 1;
+            self.best_streak = self.best_streak.max(self.streak);
+        } else {
+            self.streak = 0;
+        }
+        change
+    }
+}
+
+impl Display for Record {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "rating {}, streak {} (best {}), solved {}/{}",
+            self.rating, self.streak, self.best_streak, self.solved, self.tried
+        )
+    }
+}
+
+/// Every player's record, kept one line each as `name rating streak best
+/// solved tried`. Names may have spaces in them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Ratings(pub BTreeMap<String, Record>);
+
+impl Display for Ratings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, r) in &self.0 {
+            writeln!(
+                f,
+                "{name} {} {} {} {} {}",
+                r.rating, r.streak, r.best_streak, r.solved, r.tried
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Ratings {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut ratings = Self::default();
+        for line in s.lines().filter(|line| !line.trim().is_empty()) {
+            let invalid = || format!("Invalid rating line '{line}'");
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            let [name @ .., rating, streak, best, solved, tried] = words.as_slice() else {
+                return Err(invalid());
+            };
+            let count = |word: &str| word.parse::<u32>().map_err(|_| invalid());
+            let record = Record {
+                rating: rating.parse().map_err(|_| invalid())?,
+                streak: count(streak)?,
+                best_streak: count(best)?,
+                solved: count(solved)?,
+                tried: count(tried)?,
+            };
+            ratings.0.insert(name.join(" "), record);
+        }
+        Ok(ratings)
+    }
+}
+
+impl Ratings {
+    /// Reads the file, or starts afresh if there is none yet.
+    pub fn open(path: &str) -> Result<Self, String> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => text.parse().map_err(|e| format!("{path}: {e}")),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("Could not read {path}: {e}")),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_string()).map_err(|e| format!("Could not save {path}: {e}"))
+    }
+}
+
+/// The next puzzle for a player rated `rating`: a random one within 200
+/// points if there are any left, or else the closest.
+fn pick(puzzles: &[Puzzle], done: &HashSet<usize>, rating: i32, rng: &mut Rng) -> Option<usize> {
+    let left = (0..puzzles.len())
+        .filter(|i| !done.contains(i))
+        .collect::<Vec<_>>();
+    let near = left
+        .iter()
+        .copied()
+        .filter(|&i| (puzzles[i].rating - rating).abs() <= 200)
+        .collect::<Vec<_>>();
+    if near.is_empty() {
+        return left
+            .into_iter()
+            .min_by_key(|&i| (puzzles[i].rating - rating).abs());
+    }
+    let roll = usize::try_from(rng.below(near.len() as u64)).unwrap_or(0);
+    Some(near[roll])
+}
+
+/// Reads a line at the prompt, or `None` at the end of input.
+fn prompt(text: &str) -> Option<String> {
+    print!("{text} >>> ");
+    stdout().flush().unwrap();
+    let mut line = String::new();
+    match stdin().read_line(&mut line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line.trim().to_string()),
+    }
+}
+
+/// Runs one puzzle at the terminal, returning whether it was solved, or
+/// `None` if the player quit.
+fn solve(puzzle: &Puzzle, record: &Record) -> Option<bool> {
+    let mut attempt = Attempt::new(puzzle);
+    let mut message = puzzle.setup.as_ref().map_or_else(
+        || format!("{} to play", puzzle.solver()),
+        |setup| {
+            format!(
+                "{} played {setup}, {} to play",
+                puzzle.solver().opposite(),
+                puzzle.solver()
+            )
+        },
+    );
+    loop {
+        attempt
+            .position
+            .board
+            .print(puzzle.solver() == Color::Black);
+        println!("Puzzle {} ({})  You: {record}", puzzle.id, puzzle.rating);
+        println!("{message}");
+        let line = prompt(&format!("{} move", attempt.position.turn))?;
+        let mv = match line.as_str() {
+            "q" | "quit" => return None,
+            "hint" => {
+                let from = attempt.expected().from;
+                message = attempt.position.board.0[from.index()].map_or_else(
+                    || format!("Hint: drop a piece on {}", attempt.expected().to),
+                    |piece| format!("Hint: move the {piece} on {from}"),
+                );
+                continue;
+            }
+            "skip" | "solution" => {
+                println!("Solution: {}", attempt.rest());
+                return Some(false);
+            }
+            text => match attempt.position.parse_move(text) {
+                Ok(mv) => mv,
+                Err(e) => {
+                    message = e;
+                    continue;
+                }
+            },
+        };
+        let san = attempt.position.san(mv);
+        let position = attempt.position.clone();
+        match attempt.play(mv) {
+            Verdict::Continue(reply) => {
+                message = format!(
+                    "{san} is right. {}",
+                    san_line(&position.after(mv), &[reply])
+                );
+            }
+            Verdict::Solved => {
+                println!("{san} solves it!");
+                return Some(true);
+            }
+            Verdict::Wrong => {
+                println!("{san} is not it. Solution: {}", attempt.rest());
+                return Some(false);
+            }
+        }
+    }
+}
+
+/// Entry point for `--puzzles <file> [name]`; returns the process exit code.
+pub fn main(args: &[String]) -> i32 {
+    let Some(path) = args.first() else {
+        eprintln!("usage: base --puzzles <puzzles.csv | puzzles.epd> [name]");
+        return 2;
+    };
+    let name = args.get(1).cloned().unwrap_or_else(|| {
+        let name = net::local_name();
+        if name.is_empty() {
+            "player".to_string()
+        } else {
+            name
+        }
+    });
+    let (puzzles, mut ratings) = match open(path).and_then(|p| Ok((p, Ratings::open(RATINGS)?))) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("{e}");
+            return 1;
+        }
+    };
+    let mut rng = Rng::default();
+    let mut done = HashSet::new();
+    loop {
+        let record = ratings.0.entry(name.clone()).or_default();
+        let Some(i) = pick(&puzzles, &done, record.rating, &mut rng) else {
+            println!("That was the last puzzle. {record}");
+            break;
+        };
+        done.insert(i);
+        let Some(solved) = solve(&puzzles[i], record) else {
+            break;
+        };
+        let change = record.update(puzzles[i].rating, solved);
+        println!("Rating {} ({change:+})", record.rating);
+        if let Err(e) = ratings.save(RATINGS) {
+            eprintln!("{e}");
+            return 1;
+        }
+        if prompt("Enter for the next puzzle, q to quit").is_none_or(|line| line == "q") {
+            break;
+        }
+    }
+    println!("END");
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CSV: &str = "\
+PuzzleId,FEN,Moves,Rating,RatingDeviation,Popularity,NbPlays,Themes,GameUrl
+00sHx,q3k1nr/1pp1nQpp/3p4/1P2p3/4P3/B1PP1b2/B5PP/5K2 b k - 0 17,e8d7 a2e6 d7d8 f7f8,1760,80,83,72,mate mateIn2,https://lichess.org/yyznGmXs/black#34
+";
+
+    #[test]
+    fn reads_csv_and_epd() {
+        let puzzles = parse_csv(CSV).unwrap();
+        assert_eq!(puzzles.len(), 1);
+        let puzzle = &puzzles[0];
+        assert_eq!((puzzle.id.as_str(), puzzle.rating), ("00sHx", 1760));
+        assert_eq!(puzzle.setup.as_deref(), Some("Kd7"));
+        assert_eq!(puzzle.solver(), Color::White);
+        assert_eq!(
+            san_line(&puzzle.position, &puzzle.solution),
+            "18. Be6+ Kd8 19. Qf8#"
+        );
+
+        let epd = "6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8; id \"back rank\";\n\
+                   r6k/6pp/8/4N3/8/BQ6/8/6K1 w - - pv Nf7+ Kg8 Nh6+ Kh8 Qg8+ Rxg8 Nf7#; rating 1900;";
+        let puzzles = parse_epd(epd).unwrap();
+        assert_eq!(puzzles[0].id, "back rank");
+        assert_eq!(puzzles[0].solution.len(), 1);
+        assert_eq!(puzzles[0].rating, START_RATING);
+        assert_eq!((puzzles[1].id.as_str(), puzzles[1].rating), ("2", 1900));
+        assert_eq!(puzzles[1].solution.len(), 7);
+
+        assert!(parse_csv("x,8/8/8/8/8/8/8/K1k5 w - - 0 1").is_err());
+        assert!(parse_epd("6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra7;").is_ok());
+        assert!(parse_epd("6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra9;").is_err());
+        assert!(parse_epd("6k1/5ppp/8/8/8/8/8/R5K1 w - - id \"none\";").is_err());
+    }
+
+    #[test]
+    fn plays_through_a_puzzle() {
+        let puzzle = &parse_csv(CSV).unwrap()[0];
+        let mut attempt = Attempt::new(puzzle);
+        assert_eq!(attempt.play("a3b4".parse().unwrap()), Verdict::Wrong);
+        assert_eq!(attempt.rest(), "18. Be6+ Kd8 19. Qf8#");
+        assert_eq!(
+            attempt.play("a2e6".parse().unwrap()),
+            Verdict::Continue("d7d8".parse().unwrap())
+        );
+        assert_eq!(attempt.play("f7f8".parse().unwrap()), Verdict::Solved);
+        assert!(attempt.position.is_checkmate());
+    }
+
+    #[test]
+    fn accepts_another_mate() {
+        // Either rook mates on the back rank; the file only knows one.
+        let epd = "6k1/5ppp/8/8/8/8/8/RR4K1 w - - bm Ra8;";
+        let puzzle = &parse_epd(epd).unwrap()[0];
+        let mut attempt = Attempt::new(puzzle);
+        assert_eq!(attempt.play("b1b8".parse().unwrap()), Verdict::Solved);
+        let mut attempt = Attempt::new(puzzle);
+        assert_eq!(attempt.play("b1b7".parse().unwrap()), Verdict::Wrong);
+    }
+
+    #[test]
+    fn ratings_and_streaks() {
+        let mut record = Record::default();
+        assert_eq!(record.update(START_RATING, true), 16);
+        assert_eq!(record.update(START_RATING + 400, true), 29);
+        assert_eq!(record.update(START_RATING, false), -18);
+        assert_eq!(
+            (
+                record.streak,
+                record.best_streak,
+                record.solved,
+                record.tried
+            ),
+            (0, 2, 2, 3)
+        );
+        assert_eq!(record.rating, START_RATING + 27);
+
+        let mut ratings = Ratings::default();
+        ratings.0.insert("Ann Lee".to_string(), record);
+        ratings.0.insert("bob".to_string(), Record::default());
+        let text = ratings.to_string();
+        assert_eq!(text, "Ann Lee 1527 0 2 2 3\nbob 1500 0 0 0 0\n");
+        assert_eq!(text.parse(), Ok(ratings));
+        assert!("bob 1500 0 0".parse::<Ratings>().is_err());
+    }
+
+    #[test]
+    fn picks_puzzles_near_the_rating() {
+        let puzzle = parse_csv(CSV).unwrap().remove(0);
+        let puzzles = [1000, 1500, 1650, 2400].map(|rating| Puzzle {
+            rating,
+            ..puzzle.clone()
+        });
+        let mut rng = Rng::new(7);
+        let mut done = HashSet::new();
+        assert!([1, 2].contains(&pick(&puzzles, &done, 1580, &mut rng).unwrap()));
+        done.extend([1, 2]);
+        assert_eq!(pick(&puzzles, &done, 1580, &mut rng), Some(0));
+        done.extend([0, 3]);
+        assert_eq!(pick(&puzzles, &done, 1580, &mut rng), None);
+    }
+}
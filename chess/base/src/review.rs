@@ -0,0 +1,403 @@
+use crate::{
+    board::{movement::Move, position::Position},
+    engine::{mate_moves, MATE},
+    pgn::{move_number, PgnGame, PgnMove},
+    piece::Color,
+    suite::Limit,
+};
+use std::{fmt::Display, time::Duration};
+
+/// A quick look, for reviewing a game from the game prompt.
+pub const QUICK: Limit = Limit::Time(Duration::from_millis(200));
+
+/// Plies of the engine's line given as the better move's variation.
+const VARIATION_PLIES: usize = 6;
+
+/// How a move compares with the engine's choice, judged by how much it
+/// lowered the mover's chance of winning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Class {
+    Best,
+    Good,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl Class {
+    /// Sorts a move that lost `drop` points of winning chance.
+    #[must_use]
+    pub fn of(drop: f64, best: bool) -> Self {
+        match drop {
+            _ if best || drop <= 0.0 => Self::Best,
+            d if d < 10.0 => Self::Good,
+            d if d < 20.0 => Self::Inaccuracy,
+            d if d < 30.0 => Self::Mistake,
+            _ => Self::Blunder,
+        }
+    }
+
+    /// The NAG marking the move: `?!`, `?` or `??`.
+    #[must_use]
+    pub const fn nag(self) -> Option<u8> {
+        match self {
+            Self::Best | Self::Good => None,
+            Self::Inaccuracy => Some(6),
+            Self::Mistake => Some(2),
+            Self::Blunder => Some(4),
+        }
+    }
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Best => "Best",
+            Self::Good => "Good",
+            Self::Inaccuracy => "Inaccuracy",
+            Self::Mistake => "Mistake",
+            Self::Blunder => "Blunder",
+        }
+    }
+}
+
+/// The chance in percent that the side scoring `score` centipawns wins,
+/// on the curve Lichess fitted to its games.
+#[must_use]
+pub fn win_chance(score: i32) -> f64 {
+    let cp = f64::from(score.clamp(-1000, 1000));
+    50.0f64.mul_add(2.0 / (1.0 + (-0.003_682_08 * cp).exp()) - 1.0, 50.0)
+}
+
+/// A move's accuracy in percent from the winning chance it gave away.
+#[must_use]
+pub fn accuracy(drop: f64) -> f64 {
+    103.166_8f64
+        .mul_add((-0.043_54 * drop.max(0.0)).exp(), -3.166_9)
+        .clamp(0.0, 100.0)
+}
+
+/// A score as a player reads it: `+0.35`, or `#3` for White mating in
+/// three and `#-2` for Black mating in two.
+#[must_use]
+pub fn eval_text(score: i32) -> String {
+    match mate_moves(score) {
+        Some(moves) if score > 0 => format!("#{moves}"),
+        Some(moves) => format!("#-{}", -moves),
+        None => format!("{:+.2}", f64::from(score) / 100.0),
+    }
+}
+
+/// What the engine made of one move.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveReport {
+    pub color: Color,
+    /// `12.` or `12...`.
+    pub number: String,
+    pub san: String,
+    /// Scores from White's side before and after the move.
+    pub before: i32,
+    pub after: i32,
+    pub class: Class,
+    pub accuracy: f64,
+    /// The engine's line from before the move, when the move was not its
+    /// choice.
+    pub better: Vec<Move>,
+    /// The position the move was played in.
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Review {
+    pub moves: Vec<MoveReport>,
+    /// The game with the judgements, scores and better lines added.
+    pub pgn: PgnGame,
+}
+
+/// The score from White's side and the engine's line, best move first.
+fn evaluate(limit: Limit, position: &Position) -> (i32, Vec<Move>) {
+    if position.legal_moves().is_empty() {
+        let score = if position.in_check() { -MATE } else { 0 };
+        return (white_view(position.turn, score), Vec::new());
+    }
+    let result = limit.engine().search(position);
+    let line = result
+        .lines
+        .into_iter()
+        .next()
+        .map(|line| line.pv)
+        .unwrap_or_default();
+    (white_view(position.turn, result.score), line)
+}
+
+const fn white_view(turn: Color, score: i32) -> i32 {
+    match turn {
+        Color::White => score,
+        Color::Black => -score,
+    }
+}
+
+/// Runs the engine on every position of the game's main line, telling
+/// `progress` how many of them are done.
+pub fn review(
+    pgn: &PgnGame,
+    limit: Limit,
+    mut progress: impl FnMut(usize, usize),
+) -> Result<Review, String> {
+    let (start, moves) = pgn.replay()?;
+    let mut positions = vec![start];
+    for &mv in &moves {
+        positions.push(positions[positions.len() - 1].after(mv));
+    }
+    let mut evals = Vec::with_capacity(positions.len());
+    for (i, position) in positions.iter().enumerate() {
+        evals.push(evaluate(limit, position));
+        progress(i + 1, positions.len());
+    }
+
+    let mut annotated = pgn.clone();
+    let mut reports = Vec::with_capacity(moves.len());
+    for (i, &mv) in moves.iter().enumerate() {
+        let position = &positions[i];
+        let color = position.turn;
+        let ((before, line), (after, _)) = (&evals[i], &evals[i + 1]);
+        let (before, after) = (*before, *after);
+        let drop = win_chance(white_view(color, before)) - win_chance(white_view(color, after));
+        let best = line.first() == Some(&mv);
+        let class = Class::of(drop, best);
+        let report = MoveReport {
+            color,
+            number: move_number(position),
+            san: position.san(mv),
+            before,
+            after,
+            class,
+            accuracy: accuracy(drop),
+            better: if best { Vec::new() } else { line.clone() },
+            position: position.clone(),
+        };
+        annotate(&mut annotated.moves[i], &report);
+        reports.push(report);
+    }
+    annotated.set_tag("Annotator", "base");
+    Ok(Review {
+        moves: reports,
+        pgn: annotated,
+    })
+}
+
+/// Marks a weak move with its NAG, a comment on the scores and the line the
+/// engine preferred.
+fn annotate(pgn_move: &mut PgnMove, report: &MoveReport) {
+    let Some(nag) = report.class.nag() else {
+        return;
+    };
+    // A judgement already in the file stands.
+    if !pgn_move.nags.iter().any(|nag| (1..=6).contains(nag)) {
+        pgn_move.nags.push(nag);
+    }
+    let best = report.better.first().map_or_else(String::new, |&best| {
+        format!(". {} was best", report.position.san(best))
+    });
+    let comment = format!(
+        "{} ({} → {}){best}",
+        report.class.name(),
+        eval_text(report.before),
+        eval_text(report.after)
+    );
+    pgn_move.comment = Some(match pgn_move.comment.take() {
+        Some(old) => format!("{old} {comment}"),
+        None => comment,
+    });
+    if !report.better.is_empty() {
+        let mut position = report.position.clone();
+        let variation = report
+            .better
+            .iter()
+            .take(VARIATION_PLIES)
+            .map(|&mv| {
+                let san = position.san(mv);
+                position.make_move(mv);
+                PgnMove::new(san)
+            })
+            .collect();
+        pgn_move.variations.push(variation);
+    }
+}
+
+impl Review {
+    /// The player's average accuracy, or `None` if they made no moves.
+    #[must_use]
+    pub fn accuracy(&self, color: Color) -> Option<f64> {
+        let moves = self.moves.iter().filter(|m| m.color == color);
+        let (sum, count) = moves.fold((0.0, 0), |(sum, count), m| (sum + m.accuracy, count + 1));
+        (count > 0).then(|| sum / f64::from(count))
+    }
+
+    #[must_use]
+    pub fn count(&self, color: Color, class: Class) -> usize {
+        self.moves
+            .iter()
+            .filter(|m| m.color == color && m.class == class)
+            .count()
+    }
+}
+
+impl Display for Review {
+    /// A line on each player, then every inaccuracy, mistake and blunder.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (color, tag) in [(Color::White, "White"), (Color::Black, "Black")] {
+            let name = self
+                .pgn
+                .tag(tag)
+                .filter(|name| !name.is_empty() && *name != "?");
+            write!(f, "{color}")?;
+            if let Some(name) = name {
+                write!(f, " ({name})")?;
+            }
+            match self.accuracy(color) {
+                Some(accuracy) => write!(f, ": accuracy {accuracy:.1}%")?,
+                None => write!(f, ": no moves")?,
+            }
+            writeln!(
+                f,
+                ", inaccuracies {}, mistakes {}, blunders {}",
+                self.count(color, Class::Inaccuracy),
+                self.count(color, Class::Mistake),
+                self.count(color, Class::Blunder)
+            )?;
+        }
+        for m in self.moves.iter().filter(|m| m.class >= Class::Inaccuracy) {
+            let nag = match m.class {
+                Class::Blunder => "??",
+                Class::Mistake => "?",
+                _ => "?!",
+            };
+            write!(
+                f,
+                "{} {}{nag} {} ({} → {})",
+                m.number,
+                m.san,
+                m.class.name().to_lowercase(),
+                eval_text(m.before),
+                eval_text(m.after)
+            )?;
+            if let Some(&best) = m.better.first() {
+                write!(f, ", {} was best", m.position.san(best))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Entry point for `--review <games.pgn> [out.pgn] [depth | <n>s]`; returns
+/// the process exit code.
+pub fn main(args: &[String]) -> i32 {
+    let Some(path) = args.first() else {
+        eprintln!("usage: base --review <games.pgn> [out.pgn] [depth | <n>s]");
+        return 2;
+    };
+    let mut out = None;
+    let mut limit = Ok(Limit::default());
+    for arg in &args[1..] {
+        if arg.to_lowercase().ends_with(".pgn") {
+            out = Some(arg);
+        } else {
+            limit = arg.parse();
+        }
+    }
+    let games = limit.and_then(|limit| {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| format!("Could not read {path}: {e}"))?;
+        Ok((limit, PgnGame::parse_all(&text)?))
+    });
+    let (limit, games) = match games {
+        Ok(games) => games,
+        Err(e) => {
+            eprintln!("{e}");
+            return 2;
+        }
+    };
+    let mut written = String::new();
+    for (i, game) in games.iter().enumerate() {
+        let review = review(game, limit, |done, total| {
+            eprint!("\rGame {}: position {done}/{total}", i + 1);
+        });
+        eprintln!();
+        match review {
+            Ok(review) => {
+                eprint!("{review}");
+                written.push_str(&review.pgn.write());
+                written.push('\n');
+            }
+            Err(e) => eprintln!("Game {}: {e}", i + 1),
+        }
+    }
+    match out {
+        Some(out) => {
+            if let Err(e) = std::fs::write(out, written) {
+                eprintln!("Could not write {out}: {e}");
+                return 1;
+            }
+            eprintln!("Wrote {out}");
+        }
+        None => print!("{written}"),
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classes_and_accuracy() {
+        assert_eq!(Class::of(25.0, true), Class::Best);
+        assert_eq!(Class::of(-3.0, false), Class::Best);
+        assert_eq!(Class::of(4.0, false), Class::Good);
+        assert_eq!(Class::of(12.0, false), Class::Inaccuracy);
+        assert_eq!(Class::of(20.0, false), Class::Mistake);
+        assert_eq!(Class::of(45.0, false), Class::Blunder);
+        assert_eq!(Class::Mistake.nag(), Some(2));
+
+        assert!((win_chance(0) - 50.0).abs() < 1e-9);
+        assert!((win_chance(300) + win_chance(-300) - 100.0).abs() < 1e-9);
+        assert!(win_chance(MATE) > 97.0);
+        assert!((accuracy(0.0) - 100.0).abs() < 1e-3);
+        assert!(accuracy(20.0) < 50.0 && accuracy(80.0) < 1.0);
+
+        assert_eq!(eval_text(35), "+0.35");
+        assert_eq!(eval_text(-120), "-1.20");
+        assert_eq!(eval_text(MATE - 5), "#3");
+        assert_eq!(eval_text(2 - MATE), "#-1");
+    }
+
+    #[test]
+    fn finds_the_blunder() {
+        let pgn = "[White \"Ann\"]\n\n1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0";
+        let game = PgnGame::parse_all(pgn).unwrap().remove(0);
+        let mut seen = Vec::new();
+        let review = review(&game, Limit::Depth(3), |done, total| {
+            seen.push((done, total));
+        })
+        .unwrap();
+        assert_eq!(seen.last(), Some(&(8, 8)));
+        assert_eq!(review.moves.len(), 7);
+
+        let nf6 = &review.moves[5];
+        assert_eq!((nf6.number.as_str(), nf6.san.as_str()), ("3...", "Nf6"));
+        assert_eq!(nf6.class, Class::Blunder);
+        assert_eq!(eval_text(nf6.after), "#1");
+        assert_eq!(review.moves[6].class, Class::Best);
+        assert_eq!(review.count(Color::Black, Class::Blunder), 1);
+        assert!(review.accuracy(Color::White) > review.accuracy(Color::Black));
+
+        let written = review.pgn.write();
+        assert!(written.contains("[Annotator \"base\"]"));
+        let better = nf6.position.san(nf6.better[0]);
+        assert!(written.contains("Bc4 Nf6 $4 {Blunder ("));
+        assert!(written.contains(&format!("{better} was best}} (3... {better}")));
+        assert!(review.to_string().starts_with("White (Ann): accuracy"));
+        assert!(review.to_string().contains("3... Nf6?? blunder"));
+    }
+}
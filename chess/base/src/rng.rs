@@ -0,0 +1,35 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A small xorshift generator; good enough for picking moves, not for secrets.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Default for Rng {
+    fn default() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos());
+        #[allow(clippy::cast_possible_truncation)]
+        Self::new(nanos as u64)
+    }
+}
+
+impl Rng {
+    #[must_use]
+    pub const fn new(seed: u64) -> Self {
+        // Zero is a fixed point of xorshift, so never start there.
+        Self(seed | 1)
+    }
+
+    pub const fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A number in `0..bound`; `bound` must not be zero.
+    pub const fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+}
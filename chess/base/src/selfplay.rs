@@ -0,0 +1,420 @@
+pub mod stats;
+
+use self::stats::{Score, Sprt, Verdict};
+use crate::{
+    bitbase::{self, Bitbases},
+    board::variant::side,
+    book::Book,
+    engine::{params::Params, Engine, MAX_DEPTH, MAX_HASH, MAX_THREADS},
+    epd,
+    game::{Game, Outcome},
+    pgn::PgnGame,
+    piece::Color,
+};
+use std::{fs::OpenOptions, io::Write, str::FromStr, sync::Arc, time::Duration};
+
+/// Where match games go unless another file is named.
+pub const PGN: &str = "match.pgn";
+
+/// One side of a match: an engine set up from text such as
+/// `depth=5,book=book.bin` or `time=0.2s,threads=4,hash=64,bitbases`, and
+/// `params=params.txt` for tuned weights.
+/// Built with the `nnue` feature, `nnue=path` loads a network too.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub name: String,
+    pub engine: Engine,
+}
+
+impl FromStr for Config {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut engine = Engine::default();
+        for setting in s.split(',').filter(|setting| !setting.is_empty()) {
+            let (key, value) = setting.split_once('=').unwrap_or((setting, ""));
+            let invalid = || format!("Invalid {key} '{value}'");
+            match key {
+                "depth" => {
+                    engine.depth = value
+                        .parse()
+                        .ok()
+                        .filter(|depth| (1..=MAX_DEPTH).contains(depth))
+                        .ok_or_else(invalid)?;
+                }
+                "time" => {
+                    let seconds = value.strip_suffix('s').unwrap_or(value);
+                    let time = seconds
+                        .parse()
+                        .ok()
+                        .and_then(|s| Duration::try_from_secs_f64(s).ok())
+                        .ok_or_else(invalid)?;
+                    engine.time = Some(time);
+                    engine.depth = MAX_DEPTH;
+                }
+                "threads" => {
+                    engine.threads = value
+                        .parse()
+                        .ok()
+                        .filter(|threads| (1..=MAX_THREADS).contains(threads))
+                        .ok_or_else(invalid)?;
+                }
+                "hash" => {
+                    engine.hash = value
+                        .parse()
+                        .ok()
+                        .filter(|hash| (1..=MAX_HASH).contains(hash))
+                        .ok_or_else(invalid)?;
+                }
+                "book" => engine.book = Some(Book::open(value)?),
+                "params" => engine.params = Params::open(value)?,
+                #[cfg(feature = "nnue")]
+                "nnue" => {
+                    let network = crate::engine::nnue::Network::open(value)?;
+                    engine.network = Some(Arc::new(network));
+                }
+                "bitbases" => {
+                    let dir = if value.is_empty() {
+                        bitbase::DIR
+                    } else {
+                        value
+                    };
+                    engine.bitbases = Some(Arc::new(Bitbases::open(dir)));
+                }
+                _ => return Err(format!("Unknown engine setting '{key}'")),
+            }
+        }
+        Ok(Self {
+            name: s.to_string(),
+            engine,
+        })
+    }
+}
+
+/// When to call a game before the rules end it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Adjudication {
+    /// A side resigns once its own score has been this bad...
+    pub resign_score: i32,
+    /// ...for this many of its moves in a row.
+    pub resign_moves: u32,
+    /// A draw is agreed once both sides have scored within this of zero...
+    pub draw_score: i32,
+    /// ...for this many plies in a row...
+    pub draw_plies: u32,
+    /// ...from this move on.
+    pub draw_from: u16,
+    /// Games still going after this many moves are drawn.
+    pub max_moves: u16,
+}
+
+impl Default for Adjudication {
+    fn default() -> Self {
+        Self {
+            resign_score: 800,
+            resign_moves: 3,
+            draw_score: 10,
+            draw_plies: 10,
+            draw_from: 40,
+            max_moves: 200,
+        }
+    }
+}
+
+/// Plays one game out from `opening`, moving with the engine of the side to move.
+#[must_use]
+pub fn play_game(opening: &Game, engines: [&Engine; 2], rules: Adjudication) -> Game {
+    let mut game = opening.clone();
+    let mut engines = engines.map(|engine| {
+        let engine = engine.clone();
+        engine.new_game();
+        engine
+    });
+    let mut losing = [0; 2];
+    let mut quiet = 0;
+    while game.outcome().is_none() {
+        let position = game.position().clone();
+        let mover = side(position.turn);
+        let engine = &mut engines[mover];
+        let (mv, score) = engine.book_move(&position).map_or_else(
+            || {
+                let result = engine.search(&position);
+                (result.best, Some(result.score))
+            },
+            |mv| (Some(mv), None),
+        );
+        let Some(mv) = mv else {
+            break;
+        };
+        if let Some(score) = score {
+            losing[mover] = if score <= -rules.resign_score {
+                losing[mover] + 1
+            } else {
+                0
+            };
+            quiet = if score.abs() <= rules.draw_score {
+                quiet + 1
+            } else {
+                0
+            };
+        }
+        if losing[mover] >= rules.resign_moves {
+            game.end(Outcome::Resignation(position.turn.opposite()));
+        } else if quiet >= rules.draw_plies && position.fullmove >= rules.draw_from
+            || position.fullmove > rules.max_moves
+        {
+            game.end(Outcome::DrawAgreed);
+        } else if let Err(e) = game.try_move(&mv.to_string()) {
+            unreachable!("the engine played an illegal move: {e}");
+        }
+    }
+    game
+}
+
+/// The positions to start games from: EPD or FEN lines, or PGN games whose
+/// moves are played into each game first.
+pub fn openings(text: &str) -> Result<Vec<Game>, String> {
+    if let Ok(positions) = epd::parse_all(text) {
+        return Ok(positions
+            .into_iter()
+            .map(|epd| Game::from_position(epd.position))
+            .collect());
+    }
+    PgnGame::parse_all(text)?
+        .iter()
+        .map(|pgn| {
+            let (start, moves) = pgn.replay()?;
+            let mut game = Game::from_position(start);
+            for mv in moves {
+                game.try_move(&mv.to_string())?;
+            }
+            Ok(game)
+        })
+        .collect()
+}
+
+/// A match between two engines, `first` being the one under test.
+#[derive(Debug)]
+pub struct Match {
+    pub first: Config,
+    pub second: Config,
+    pub games: u32,
+    pub openings: Vec<Game>,
+    pub rules: Adjudication,
+    pub sprt: Sprt,
+    /// PGN file games are appended to as they finish.
+    pub pgn: Option<String>,
+}
+
+impl Match {
+    /// Plays each opening twice with colours swapped, until `games` have
+    /// been played or the SPRT has decided. `report` hears about every game.
+    pub fn run(&self, mut report: impl FnMut(u32, &Game, Score)) -> Result<Score, String> {
+        let start = [Game::default()];
+        let openings = if self.openings.is_empty() {
+            &start[..]
+        } else {
+            &self.openings
+        };
+        let mut score = Score::default();
+        for round in 1..=self.games {
+            let opening = &openings[(round as usize - 1) / 2 % openings.len()];
+            // The first engine takes White in odd rounds.
+            let first_color = if round % 2 == 1 {
+                Color::White
+            } else {
+                Color::Black
+            };
+            let (white, black) = match first_color {
+                Color::White => (&self.first, &self.second),
+                Color::Black => (&self.second, &self.first),
+            };
+            let game = play_game(opening, [&white.engine, &black.engine], self.rules);
+            match game.outcome().and_then(Outcome::winner) {
+                Some(winner) if winner == first_color => score.wins += 1,
+                Some(_) => score.losses += 1,
+                None => score.draws += 1,
+            }
+            self.write(round, &game, white, black)?;
+            report(round, &game, score);
+            if round % 2 == 0 && self.sprt.verdict(score) != Verdict::Continue {
+                break;
+            }
+        }
+        Ok(score)
+    }
+
+    fn write(&self, round: u32, game: &Game, white: &Config, black: &Config) -> Result<(), String> {
+        let Some(path) = &self.pgn else {
+            return Ok(());
+        };
+        let mut pgn = game.to_pgn();
+        pgn.set_tag("Event", "Engine match");
+        pgn.set_tag("Round", round.to_string());
+        pgn.set_tag("White", white.name.as_str());
+        pgn.set_tag("Black", black.name.as_str());
+        if let Some(outcome) = game.outcome() {
+            pgn.set_tag("Termination", outcome.to_string());
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("Could not open {path}: {e}"))?;
+        writeln!(file, "{}", pgn.write()).map_err(|e| format!("Could not write {path}: {e}"))
+    }
+}
+
+/// Entry point for `--match <first> <second> [games] [openings] [out.pgn]`;
+/// returns the process exit code.
+pub fn main(args: &[String]) -> i32 {
+    let result = setup(args).and_then(|contest| {
+        println!("{} vs {}", contest.first.name, contest.second.name);
+        let score = contest.run(|round, game, score| {
+            let result = game.outcome().map_or("*", Outcome::result);
+            let reason = game.outcome().map(|o| o.to_string()).unwrap_or_default();
+            println!("Game {round}: {result} ({reason}), score {score}");
+        })?;
+        println!("Score: {score}");
+        match score.elo() {
+            Some((elo, error)) => println!("Elo: {elo:+.1} +/- {error:.1}"),
+            None => println!("Elo: not measurable yet"),
+        }
+        let (lower, upper) = contest.sprt.bounds();
+        println!(
+            "SPRT [{}, {}]: LLR {:.2} ({lower:.2}, {upper:.2}), {}",
+            contest.sprt.elo0,
+            contest.sprt.elo1,
+            contest.sprt.llr(score),
+            contest.sprt.verdict(score)
+        );
+        Ok(())
+    });
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{e}");
+            2
+        }
+    }
+}
+
+fn setup(args: &[String]) -> Result<Match, String> {
+    let [first, second, rest @ ..] = args else {
+        return Err(
+            "usage: base --match <engine> <engine> [games] [openings] [out.pgn]".to_string(),
+        );
+    };
+    let games = rest.first().map_or(Ok(100), |n| {
+        n.parse::<u32>()
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or_else(|| format!("Invalid game count '{n}'"))
+    })?;
+    let openings = match rest.get(1) {
+        Some(path) => openings(
+            &std::fs::read_to_string(path).map_err(|e| format!("Could not read {path}: {e}"))?,
+        )?,
+        None => Vec::new(),
+    };
+    Ok(Match {
+        first: first.parse()?,
+        second: second.parse()?,
+        games,
+        openings,
+        rules: Adjudication::default(),
+        sprt: Sprt::default(),
+        pgn: Some(rest.get(2).map_or(PGN, String::as_str).to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::position::Position;
+
+    #[test]
+    fn configs() {
+        let config = "depth=3".parse::<Config>().unwrap();
+        assert_eq!(config.engine.depth, 3);
+        assert_eq!(config.engine.time, None);
+        let config = "time=0.25s".parse::<Config>().unwrap();
+        assert_eq!(config.engine.time, Some(Duration::from_millis(250)));
+        assert!("depth=0".parse::<Config>().is_err());
+        assert!("speed=3".parse::<Config>().is_err());
+        assert!("params=missing.txt".parse::<Config>().is_err());
+        let config = "threads=4,hash=64".parse::<Config>().unwrap();
+        assert_eq!((config.engine.threads, config.engine.hash), (4, 64));
+        assert!("threads=0".parse::<Config>().is_err());
+    }
+
+    #[test]
+    fn openings_from_epd_or_pgn() {
+        let games = openings("4k3/8/8/8/8/8/8/R3K3 w Q - bm O-O-O;\n").unwrap();
+        assert_eq!(games[0].position().fen(), "4k3/8/8/8/8/8/8/R3K3 w Q - 0 1");
+        let games = openings("1. e4 e5 2. Nf3 *\n\n1. d4 d5 *").unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].to_pgn().moves.len(), 3);
+    }
+
+    #[test]
+    fn adjudicates() {
+        let rules = Adjudication {
+            resign_score: 500,
+            resign_moves: 1,
+            ..Adjudication::default()
+        };
+        // Black is a queen down whatever it plays, so gives up at once.
+        let opening =
+            Game::from_position(Position::from_fen("3qk3/8/8/8/8/8/8/2QQK3 b - - 0 1").unwrap());
+        let engine = Engine::new(1);
+        let game = play_game(&opening, [&engine, &engine], rules);
+        assert_eq!(game.outcome(), Some(Outcome::Resignation(Color::White)));
+
+        let rules = Adjudication {
+            draw_score: 1000,
+            draw_plies: 2,
+            draw_from: 1,
+            ..Adjudication::default()
+        };
+        let opening =
+            Game::from_position(Position::from_fen("4k3/8/8/3p4/3P4/8/8/4K3 w - - 0 1").unwrap());
+        let game = play_game(&opening, [&engine, &engine], rules);
+        assert_eq!(game.outcome(), Some(Outcome::DrawAgreed));
+    }
+
+    #[test]
+    fn paired_match() {
+        let path = std::env::temp_dir().join(format!("chess-match-{}.pgn", std::process::id()));
+        let path = path.to_str().unwrap();
+        // Whoever has White mates at once, so the pair splits 1-1.
+        let contest = Match {
+            first: "depth=2".parse().unwrap(),
+            second: "depth=1".parse().unwrap(),
+            games: 4,
+            openings: openings("6k1/5ppp/8/8/8/8/8/R5K1 w - -\n").unwrap(),
+            rules: Adjudication::default(),
+            sprt: Sprt::default(),
+            pgn: Some(path.to_string()),
+        };
+        let mut rounds = Vec::new();
+        let score = contest.run(|round, _, _| rounds.push(round)).unwrap();
+        assert_eq!(rounds, [1, 2, 3, 4]);
+        assert_eq!(
+            score,
+            Score {
+                wins: 2,
+                draws: 0,
+                losses: 2
+            }
+        );
+
+        let written = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let games = PgnGame::parse_all(&written).unwrap();
+        assert_eq!(games.len(), 4);
+        assert_eq!(games[0].tag("White"), Some("depth=2"));
+        assert_eq!(games[1].tag("White"), Some("depth=1"));
+        assert!(games.iter().all(|game| game.result == "1-0"));
+    }
+}
@@ -0,0 +1,201 @@
+use std::fmt::Display;
+
+/// Games won, drawn and lost, from the first engine's point of view.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Score {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+/// Expected score against an opponent `elo` points weaker.
+fn expected(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// The Elo difference that gives an expected score of `score`.
+fn elo(score: f64) -> f64 {
+    400.0 * (score / (1.0 - score)).log10()
+}
+
+impl Score {
+    #[must_use]
+    pub const fn games(self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Points per game, counting a draw as half.
+    fn mean(self) -> f64 {
+        (f64::from(self.wins) + f64::from(self.draws) / 2.0) / f64::from(self.games())
+    }
+
+    /// Variance of a single game's result about the mean.
+    fn variance(self) -> f64 {
+        let mean = self.mean();
+        let spread = |result: f64, count: u32| f64::from(count) * (result - mean).powi(2);
+        (spread(1.0, self.wins) + spread(0.5, self.draws) + spread(0.0, self.losses))
+            / f64::from(self.games())
+    }
+
+    /// The Elo difference and the half-width of its 95% confidence
+    /// interval, or `None` while every game has gone one way.
+    #[must_use]
+    pub fn elo(self) -> Option<(f64, f64)> {
+        if self.games() == 0 {
+            return None;
+        }
+        let mean = self.mean();
+        if mean <= 0.0 || mean >= 1.0 {
+            return None;
+        }
+        let error = 1.96 * (self.variance() / f64::from(self.games())).sqrt();
+        let low = elo((mean - error).max(f64::EPSILON));
+        let high = elo((mean + error).min(1.0 - f64::EPSILON));
+        Some((elo(mean), (high - low) / 2.0))
+    }
+}
+
+impl Display for Score {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "+{} ={} -{}", self.wins, self.draws, self.losses)
+    }
+}
+
+/// Where a sequential test stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// The first engine is at least `elo1` stronger.
+    Accept,
+    /// It is no more than `elo0` stronger.
+    Reject,
+    Continue,
+}
+
+impl Display for Verdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Accept => write!(f, "H1 accepted"),
+            Self::Reject => write!(f, "H0 accepted"),
+            Self::Continue => write!(f, "undecided"),
+        }
+    }
+}
+
+/// A sequential probability ratio test of `elo0` against `elo1`, with the
+/// chances `alpha` and `beta` of wrongly accepting or rejecting.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Default for Sprt {
+    fn default() -> Self {
+        Self {
+            elo0: 0.0,
+            elo1: 5.0,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+}
+
+impl Sprt {
+    /// The log-likelihood ratio, using the normal approximation to the
+    /// game results.
+    #[must_use]
+    pub fn llr(self, score: Score) -> f64 {
+        if score.games() == 0 {
+            return 0.0;
+        }
+        let variance = score.variance();
+        if variance <= 0.0 {
+            return 0.0;
+        }
+        let (s0, s1) = (expected(self.elo0), expected(self.elo1));
+        let (mean, games) = (score.mean(), f64::from(score.games()));
+        games * (s1 - s0) * (mean - f64::midpoint(s0, s1)) / variance
+    }
+
+    /// The LLR values at which H0 and H1 are accepted.
+    #[must_use]
+    pub fn bounds(self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    #[must_use]
+    pub fn verdict(self, score: Score) -> Verdict {
+        let llr = self.llr(score);
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            Verdict::Accept
+        } else if llr <= lower {
+            Verdict::Reject
+        } else {
+            Verdict::Continue
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elo_difference() {
+        let score = Score {
+            wins: 60,
+            draws: 20,
+            losses: 20,
+        };
+        let (diff, error) = score.elo().unwrap();
+        assert!((diff - 147.2).abs() < 0.1);
+        assert!(error > 50.0 && error < 100.0);
+
+        let even = Score {
+            wins: 10,
+            draws: 0,
+            losses: 10,
+        };
+        assert!(even.elo().unwrap().0.abs() < 1e-9);
+        let sweep = Score {
+            wins: 5,
+            draws: 0,
+            losses: 0,
+        };
+        assert_eq!(sweep.elo(), None);
+        assert_eq!(Score::default().elo(), None);
+    }
+
+    #[test]
+    fn sprt_verdicts() {
+        let sprt = Sprt::default();
+        let (lower, upper) = sprt.bounds();
+        assert!((upper - 2.944).abs() < 0.001);
+        assert!((lower + 2.944).abs() < 0.001);
+
+        let stronger = Score {
+            wins: 600,
+            draws: 400,
+            losses: 400,
+        };
+        assert_eq!(sprt.verdict(stronger), Verdict::Accept);
+        let equal = Score {
+            wins: 5000,
+            draws: 20_000,
+            losses: 5000,
+        };
+        assert_eq!(sprt.verdict(equal), Verdict::Reject);
+        let early = Score {
+            wins: 3,
+            draws: 2,
+            losses: 2,
+        };
+        assert_eq!(sprt.verdict(early), Verdict::Continue);
+    }
+}
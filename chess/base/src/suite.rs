@@ -0,0 +1,188 @@
+use crate::{
+    engine::{Engine, MAX_DEPTH},
+    epd::{self, Epd},
+};
+use std::{
+    fmt::Display,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+/// How long the engine may think about each position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Depth(u32),
+    Time(Duration),
+}
+
+impl Default for Limit {
+    fn default() -> Self {
+        Self::Time(Duration::from_secs(1))
+    }
+}
+
+impl Limit {
+    /// An engine that searches to this limit.
+    #[must_use]
+    pub fn engine(self) -> Engine {
+        match self {
+            Self::Depth(depth) => Engine::new(depth),
+            Self::Time(time) => {
+                let mut engine = Engine::new(MAX_DEPTH);
+                engine.time = Some(time);
+                engine
+            }
+        }
+    }
+}
+
+impl FromStr for Limit {
+    type Err = String;
+
+    /// A bare number is a depth; one ending in `s` is seconds per position.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid limit '{s}', use a depth like 6 or a time like 2s");
+        if let Some(seconds) = s.strip_suffix('s') {
+            return seconds
+                .parse::<f64>()
+                .ok()
+                .and_then(|s| Duration::try_from_secs_f64(s).ok())
+                .filter(|time| !time.is_zero())
+                .map(Self::Time)
+                .ok_or_else(invalid);
+        }
+        s.parse()
+            .ok()
+            .filter(|depth| (1..=MAX_DEPTH).contains(depth))
+            .map(Self::Depth)
+            .ok_or_else(invalid)
+    }
+}
+
+/// How the engine did on one position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solution {
+    pub id: String,
+    /// The engine's move in SAN.
+    pub played: String,
+    pub solved: bool,
+    pub depth: u32,
+    pub nodes: u64,
+    pub time: Duration,
+}
+
+impl Display for Solution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:<12} {:<8} {:<4} depth {:<3} {:>10} nodes {:>7.2}s",
+            self.id,
+            self.played,
+            if self.solved { "ok" } else { "--" },
+            self.depth,
+            self.nodes,
+            self.time.as_secs_f64()
+        )
+    }
+}
+
+/// Searches one position and checks the move against its `bm` and `am`
+/// operations.
+pub fn solve(epd: &Epd, limit: Limit) -> Result<Solution, String> {
+    let best = epd.moves("bm")?;
+    let avoid = epd.moves("am")?;
+    if best.is_empty() && avoid.is_empty() {
+        return Err("no bm or am operation".to_string());
+    }
+    let mut engine = limit.engine();
+    let started = Instant::now();
+    let result = engine.search(&epd.position);
+    let time = started.elapsed();
+    let played = result.best;
+    Ok(Solution {
+        id: epd.id().unwrap_or("?").to_string(),
+        played: played.map_or_else(|| "-".to_string(), |mv| epd.position.san(mv)),
+        solved: played
+            .is_some_and(|mv| (best.is_empty() || best.contains(&mv)) && !avoid.contains(&mv)),
+        depth: result.depth,
+        nodes: result.nodes,
+        time,
+    })
+}
+
+/// Runs every position in an EPD suite, handing each result to `report` as
+/// it comes in.
+pub fn run(
+    text: &str,
+    limit: Limit,
+    mut report: impl FnMut(&Solution),
+) -> Result<Vec<Solution>, String> {
+    let mut solutions = Vec::new();
+    for (i, epd) in epd::parse_all(text)?.iter().enumerate() {
+        let solution = solve(epd, limit).map_err(|e| format!("position {}: {e}", i + 1))?;
+        report(&solution);
+        solutions.push(solution);
+    }
+    Ok(solutions)
+}
+
+/// Entry point for `--bench-suite`; returns the process exit code.
+pub fn main(path: &str, limit: Option<&str>) -> i32 {
+    let limit = limit.map_or_else(|| Ok(Limit::default()), str::parse);
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Could not read {path}: {e}"));
+    match limit.and_then(|limit| text.and_then(|text| run(&text, limit, |s| println!("{s}")))) {
+        Ok(solutions) => {
+            let solved = solutions.iter().filter(|s| s.solved).count();
+            let time = solutions.iter().map(|s| s.time).sum::<Duration>();
+            println!(
+                "solved {solved}/{} in {:.1}s",
+                solutions.len(),
+                time.as_secs_f64()
+            );
+            0
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            2
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits() {
+        assert_eq!("6".parse(), Ok(Limit::Depth(6)));
+        assert_eq!("2s".parse(), Ok(Limit::Time(Duration::from_secs(2))));
+        assert_eq!("0.5s".parse(), Ok(Limit::Time(Duration::from_millis(500))));
+        assert!("0".parse::<Limit>().is_err());
+        assert!("0s".parse::<Limit>().is_err());
+        assert!("fast".parse::<Limit>().is_err());
+    }
+
+    #[test]
+    fn scores_a_suite() {
+        let suite = "\
+6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; id \"mate\";
+4k3/8/8/3q4/8/8/8/3RK3 w - - bm Rxd5; id \"queen\";
+4k3/8/8/3q4/8/8/8/3RK3 w - - am Rxd5; id \"avoid\";
+";
+        let mut seen = Vec::new();
+        let solutions = run(suite, Limit::Depth(2), |s| seen.push(s.id.clone())).unwrap();
+        assert_eq!(seen, ["mate", "queen", "avoid"]);
+        assert_eq!(
+            solutions.iter().map(|s| s.solved).collect::<Vec<_>>(),
+            [true, true, false]
+        );
+        assert_eq!(solutions[1].played, "Rxd5");
+        assert_eq!(solutions[1].depth, 2);
+
+        let unscored = "4k3/8/8/8/8/8/8/3RK3 w - - id \"none\";";
+        assert_eq!(
+            run(unscored, Limit::Depth(1), |_| {}),
+            Err("position 1: no bm or am operation".to_string())
+        );
+    }
+}
@@ -0,0 +1,358 @@
+use crate::{
+    board::position::Position,
+    engine::params::{self, Params, KINDS},
+    epd::Epd,
+    piece::Color,
+};
+use std::str::FromStr;
+
+/// A position and how the game it came from ended, from White's side: 1 for
+/// a win, 0.5 for a draw and 0 for a loss.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub position: Position,
+    pub result: f64,
+}
+
+/// Reads a result written as `1-0`, `0-1` or `1/2-1/2`, or as the bracketed
+/// `[1.0]`, `[0.5]` or `[0.0]` some datasets use.
+fn parse_result(token: &str) -> Option<f64> {
+    let quoted = token.trim_end_matches(';').trim_matches('"');
+    match quoted {
+        "1-0" => Some(1.0),
+        "0-1" => Some(0.0),
+        "1/2-1/2" => Some(0.5),
+        _ => quoted
+            .strip_prefix('[')
+            .and_then(|t| t.strip_suffix(']'))
+            .and_then(|t| t.parse().ok())
+            .filter(|result| [0.0, 0.5, 1.0].contains(result)),
+    }
+}
+
+impl FromStr for Sample {
+    type Err = String;
+
+    /// A FEN or EPD position followed by its result, or an EPD line with the
+    /// result in a `c9` operation.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some((fen, result)) = s
+            .rsplit_once(char::is_whitespace)
+            .and_then(|(fen, last)| Some((fen, parse_result(last)?)))
+        {
+            return Ok(Self {
+                position: fen.parse::<Epd>()?.position,
+                result,
+            });
+        }
+        let epd = s.parse::<Epd>()?;
+        let result = epd
+            .operation("c9")
+            .and_then(<[String]>::first)
+            .and_then(|result| parse_result(result))
+            .ok_or_else(|| format!("No game result in '{s}'"))?;
+        Ok(Self {
+            position: epd.position,
+            result,
+        })
+    }
+}
+
+/// Every sample in a dataset, skipping blank lines and `#` comments.
+pub fn parse_samples(text: &str) -> Result<Vec<Sample>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(i, line)| line.parse().map_err(|e| format!("line {}: {e}", i + 1)))
+        .collect()
+}
+
+/// Number of weights: a material value per kind, then a table per kind.
+const WEIGHTS: usize = 6 + 6 * 64;
+
+/// The evaluation is linear in its weights, so a position reduces to how
+/// many times it counts each one, White's uses less Black's.
+type Features = Vec<(usize, f64)>;
+
+fn features(position: &Position) -> Features {
+    let mut counts = [0i32; WEIGHTS];
+    for (coord, piece) in position.board.pieces() {
+        let (index, sign) = match piece.color() {
+            Color::White => (coord.index(), 1),
+            Color::Black => (coord.index() ^ 0b11_1000, -1),
+        };
+        let kind = piece.kind() as usize;
+        counts[kind] += sign;
+        counts[6 + kind * 64 + index] += sign;
+    }
+    let [white, black] = position.pockets;
+    for kind in KINDS {
+        counts[kind as usize] += i32::from(white.count(kind)) - i32::from(black.count(kind));
+    }
+    counts
+        .into_iter()
+        .enumerate()
+        .filter(|&(_, count)| count != 0)
+        .map(|(i, count)| (i, f64::from(count)))
+        .collect()
+}
+
+fn weights(params: &Params) -> Vec<f64> {
+    params
+        .material
+        .iter()
+        .chain(params.squares.iter().flatten())
+        .map(|&w| f64::from(w))
+        .collect()
+}
+
+/// Expected score for White at `score` centipawns, squashed by `scale`.
+fn sigmoid(score: f64, scale: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-scale * score / 400.0))
+}
+
+/// Fits the evaluation weights to game results by gradient descent on the
+/// mean squared difference between each result and the expected score
+/// the static evaluation predicts. Samples should be quiet positions,
+/// since no search is done on them.
+#[derive(Debug, Clone)]
+pub struct Tuner {
+    samples: Vec<(Features, f64)>,
+    weights: Vec<f64>,
+    /// How sharply scores map to results, fitted once to the starting weights.
+    scale: f64,
+    /// Centipawns a weight may move per step, roughly.
+    pub rate: f64,
+    // Adam's running averages of the gradient and its square.
+    momentum: Vec<f64>,
+    velocity: Vec<f64>,
+    steps: i32,
+}
+
+impl Tuner {
+    #[must_use]
+    pub fn new(samples: &[Sample], params: &Params) -> Self {
+        let mut tuner = Self {
+            samples: samples
+                .iter()
+                .map(|sample| (features(&sample.position), sample.result))
+                .collect(),
+            weights: weights(params),
+            scale: 1.0,
+            rate: 1.0,
+            momentum: vec![0.0; WEIGHTS],
+            velocity: vec![0.0; WEIGHTS],
+            steps: 0,
+        };
+        tuner.fit_scale();
+        tuner
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn count(&self) -> f64 {
+        self.samples.len().max(1) as f64
+    }
+
+    fn score(&self, features: &Features) -> f64 {
+        features
+            .iter()
+            .map(|&(i, count)| self.weights[i] * count)
+            .sum()
+    }
+
+    /// Mean squared error of the current weights over every sample.
+    #[must_use]
+    pub fn error(&self) -> f64 {
+        let total = self
+            .samples
+            .iter()
+            .map(|(features, result)| (result - sigmoid(self.score(features), self.scale)).powi(2))
+            .sum::<f64>();
+        total / self.count()
+    }
+
+    /// Picks the scale that best fits the current weights, so the descent
+    /// tunes the weights rather than how confident they are.
+    fn fit_scale(&mut self) {
+        let mut best = self.error();
+        for step in [0.5, 0.1, 0.01, 0.001] {
+            loop {
+                let scale = self.scale;
+                let tried = [scale + step, scale - step].map(|s| {
+                    self.scale = s;
+                    (self.error(), s)
+                });
+                self.scale = scale;
+                let (error, s) = tried
+                    .into_iter()
+                    .filter(|&(_, s)| s > 0.0)
+                    .min_by(|a, b| a.0.total_cmp(&b.0))
+                    .unwrap_or((best, scale));
+                if error >= best {
+                    break;
+                }
+                best = error;
+                self.scale = s;
+            }
+        }
+    }
+
+    /// One pass of Adam over every sample, returning the error from before
+    /// the step.
+    pub fn step(&mut self) -> f64 {
+        const BETA1: f64 = 0.9;
+        const BETA2: f64 = 0.999;
+        let mut gradient = vec![0.0; WEIGHTS];
+        let mut error = 0.0;
+        let slope = self.scale * std::f64::consts::LN_10 / 400.0;
+        for (features, result) in &self.samples {
+            let expected = sigmoid(self.score(features), self.scale);
+            error += (result - expected).powi(2);
+            let d = -2.0 * (result - expected) * expected * (1.0 - expected) * slope;
+            for &(i, count) in features {
+                gradient[i] += d * count;
+            }
+        }
+        let n = self.count();
+        self.steps += 1;
+        let (bias1, bias2) = (1.0 - BETA1.powi(self.steps), 1.0 - BETA2.powi(self.steps));
+        for (i, g) in gradient.into_iter().enumerate() {
+            let g = g / n;
+            self.momentum[i] = BETA1.mul_add(self.momentum[i], (1.0 - BETA1) * g);
+            self.velocity[i] = BETA2.mul_add(self.velocity[i], (1.0 - BETA2) * g * g);
+            let (m, v) = (self.momentum[i] / bias1, self.velocity[i] / bias2);
+            self.weights[i] -= self.rate * m / (v.sqrt() + 1e-12);
+        }
+        error / n
+    }
+
+    /// The weights rounded to whole centipawns.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn params(&self) -> Params {
+        let round = |w: f64| w.round() as i32;
+        let mut params = Params::default();
+        for (value, &w) in params.material.iter_mut().zip(&self.weights) {
+            *value = round(w);
+        }
+        for (value, &w) in params.squares.iter_mut().flatten().zip(&self.weights[6..]) {
+            *value = round(w);
+        }
+        params
+    }
+}
+
+/// Entry point for `--tune`; returns the process exit code.
+pub fn main(args: &[String]) -> i32 {
+    match tune(args) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{e}");
+            2
+        }
+    }
+}
+
+fn tune(args: &[String]) -> Result<(), String> {
+    let [data, rest @ ..] = args else {
+        return Err("usage: base --tune <data> [out] [iterations]".to_string());
+    };
+    let out = rest.first().map_or(params::FILE, String::as_str);
+    let iterations = rest.get(1).map_or(Ok(500), |n| {
+        n.parse::<u32>()
+            .map_err(|_| format!("Invalid iteration count '{n}'"))
+    })?;
+    let text = std::fs::read_to_string(data).map_err(|e| format!("Could not read {data}: {e}"))?;
+    let samples = parse_samples(&text)?;
+    if samples.is_empty() {
+        return Err(format!("No positions in {data}"));
+    }
+    let mut tuner = Tuner::new(&samples, &Params::default());
+    println!(
+        "{} positions, scale {:.3}, error {:.6}",
+        samples.len(),
+        tuner.scale,
+        tuner.error()
+    );
+    for iteration in 1..=iterations {
+        let error = tuner.step();
+        // Save as we go so stopping early keeps the progress.
+        if iteration % 50 == 0 || iteration == iterations {
+            println!("iteration {iteration} error {error:.6}");
+            tuner.params().save(out)?;
+        }
+    }
+    println!("error {:.6}, saved to {out}", tuner.error());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn sample_formats() {
+        let white = "4k3/8/8/8/8/8/8/3QK3 w - - 0 1 1-0"
+            .parse::<Sample>()
+            .unwrap();
+        assert_eq!(white.result, 1.0);
+        assert_eq!(white.position.fullmove, 1);
+        let draw = "4k3/8/8/8/8/8/8/4K3 b - - [0.5]".parse::<Sample>().unwrap();
+        assert_eq!(draw.result, 0.5);
+        assert_eq!(draw.position.turn, Color::Black);
+        let epd = "4k3/8/8/8/8/8/8/3qK3 w - - id \"x\"; c9 \"0-1\";"
+            .parse::<Sample>()
+            .unwrap();
+        assert_eq!(epd.result, 0.0);
+
+        // A FEN's move number is not a result.
+        assert!("4k3/8/8/8/8/8/8/4K3 w - - 0 1".parse::<Sample>().is_err());
+        let data = "# quiet positions\n\n4k3/8/8/8/8/8/8/4K3 w - - 1/2-1/2\nnonsense 1-0\n";
+        assert!(parse_samples(data).unwrap_err().starts_with("line 4:"));
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn features_match_the_evaluation() {
+        let params = Params::default();
+        let tuner = Tuner::new(&[], &params);
+        for fen in [
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 2 3",
+            "8/5k2/8/3p4/8/2N5/5K2/8 w - - 0 1",
+        ] {
+            let position = Position::from_fen(fen).unwrap();
+            let white = match position.turn {
+                Color::White => params.evaluate(&position),
+                Color::Black => -params.evaluate(&position),
+            };
+            assert_eq!(tuner.score(&features(&position)), f64::from(white));
+        }
+    }
+
+    #[test]
+    fn tuning_reduces_error() {
+        // An extra knight wins and an extra pawn only draws, so knights
+        // should end up worth more against pawns than the defaults say.
+        let data = "\
+4k3/8/8/8/8/8/3N4/4K3 w - - 1-0
+4k3/8/8/8/8/3N4/8/4K3 b - - 1-0
+4k3/3n4/8/8/8/8/8/4K3 w - - 0-1
+4k3/8/8/8/8/8/3P4/4K3 w - - 1/2-1/2
+4k3/3p4/8/8/8/8/8/4K3 b - - 1/2-1/2
+4k3/8/8/8/8/8/8/4K3 w - - 1/2-1/2
+";
+        let samples = parse_samples(data).unwrap();
+        let mut tuner = Tuner::new(&samples, &Params::default());
+        tuner.rate = 5.0;
+        let before = tuner.error();
+        for _ in 0..50 {
+            tuner.step();
+        }
+        assert!(tuner.error() < before);
+        let params = tuner.params();
+        assert!(params.material[1] - params.material[0] > 220);
+    }
+}
@@ -0,0 +1,468 @@
+use crate::{
+    board::{position::Position, variant::side},
+    engine::{
+        mate_moves, Engine, SearchResult, Stop, DEFAULT_HASH, MAX_DEPTH, MAX_HASH, MAX_LINES,
+        MAX_THREADS,
+    },
+    piece::Color,
+};
+use std::{
+    io::BufRead,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Sender},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+/// The limits of a `go` command.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Go {
+    depth: Option<u32>,
+    movetime: Option<Duration>,
+    /// Time left for White and Black.
+    clock: [Option<Duration>; 2],
+    increment: [Duration; 2],
+    movestogo: Option<u32>,
+    /// Keep searching, and hold the best move back, until told to stop.
+    infinite: bool,
+    /// Search the move the engine expects the opponent to play, on their
+    /// time, until `ponderhit` starts the clock or `stop` ends it.
+    ponder: bool,
+}
+
+impl FromStr for Go {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut go = Self::default();
+        let mut words = s.split_whitespace();
+        while let Some(word) = words.next() {
+            match word {
+                "infinite" => go.infinite = true,
+                "ponder" => go.ponder = true,
+                _ => {}
+            }
+            if matches!(word, "infinite" | "ponder") {
+                continue;
+            }
+            let value = words
+                .next()
+                .ok_or_else(|| format!("go {word} needs a value"))?;
+            let number = value
+                .parse::<u64>()
+                .map_err(|_| format!("Invalid {word} '{value}'"))?;
+            let millis = Duration::from_millis(number);
+            match word {
+                "depth" => go.depth = Some(u32::try_from(number).unwrap_or(MAX_DEPTH)),
+                "movetime" => go.movetime = Some(millis),
+                "wtime" => go.clock[0] = Some(millis),
+                "btime" => go.clock[1] = Some(millis),
+                "winc" => go.increment[0] = millis,
+                "binc" => go.increment[1] = millis,
+                "movestogo" => go.movestogo = Some(u32::try_from(number).unwrap_or(1)),
+                // Limits we do not support, such as nodes or mate, are
+                // ignored rather than refused.
+                _ => {}
+            }
+        }
+        Ok(go)
+    }
+}
+
+impl Go {
+    /// The depth and time to give the engine with `turn` to move. On a clock
+    /// it spends an even share of what is left plus most of the increment.
+    fn limits(self, turn: Color) -> (u32, Option<Duration>) {
+        let depth = self
+            .depth
+            .map_or(MAX_DEPTH, |depth| depth.clamp(1, MAX_DEPTH));
+        if self.infinite {
+            return (depth, None);
+        }
+        if let Some(time) = self.movetime {
+            return (depth, Some(time));
+        }
+        let Some(clock) = self.clock[side(turn)] else {
+            return (depth, None);
+        };
+        let share =
+            clock / self.movestogo.unwrap_or(30).max(1) + self.increment[side(turn)] * 3 / 4;
+        let time = share
+            .min(clock.saturating_sub(Duration::from_millis(50)))
+            .max(Duration::from_millis(10));
+        (depth, Some(time))
+    }
+}
+
+/// Reads `position startpos moves e2e4` or `position fen <fen> moves ...`.
+fn parse_position(s: &str) -> Result<Position, String> {
+    let (setup, moves) = s.split_once("moves").unwrap_or((s, ""));
+    let mut position = match setup.trim() {
+        "startpos" => Position::default(),
+        setup => Position::from_fen(
+            setup
+                .strip_prefix("fen")
+                .ok_or_else(|| format!("Unknown position '{setup}'"))?,
+        )?,
+    };
+    for mv in moves.split_whitespace() {
+        position.play(mv)?;
+    }
+    Ok(position)
+}
+
+/// A score as UCI gives it: centipawns, or moves to mate.
+fn score(score: i32) -> String {
+    mate_moves(score).map_or_else(|| format!("cp {score}"), |moves| format!("mate {moves}"))
+}
+
+/// An `info` line for each of the result's lines, numbered with
+/// `multipv` when there is more than one.
+fn info(result: &SearchResult) -> Vec<String> {
+    let numbered = result.lines.len() > 1;
+    result
+        .lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let pv = line.pv.iter().map(ToString::to_string).collect::<Vec<_>>();
+            let multipv = if numbered {
+                format!(" multipv {}", i + 1)
+            } else {
+                String::new()
+            };
+            format!(
+                "info depth {}{multipv} score {} nodes {} nps {} time {} pv {}",
+                result.depth,
+                score(line.score),
+                result.nodes,
+                result.nps(),
+                result.time.as_millis(),
+                pv.join(" ")
+            )
+        })
+        .collect()
+}
+
+/// The engine side of a UCI session.
+struct Uci {
+    /// Lent to the search thread while it runs.
+    engine: Option<Engine>,
+    searching: Option<JoinHandle<Engine>>,
+    stop: Arc<Stop>,
+    /// Set while the search is pondering and must not answer yet.
+    pondering: Arc<AtomicBool>,
+    /// The time the pondering search gets once its move is played.
+    ponder_time: Option<Duration>,
+    position: Position,
+    out: Sender<String>,
+}
+
+impl Uci {
+    fn new(out: Sender<String>) -> Self {
+        let engine = Engine::new(MAX_DEPTH);
+        Self {
+            stop: engine.stop_handle(),
+            engine: Some(engine),
+            searching: None,
+            pondering: Arc::default(),
+            ponder_time: None,
+            position: Position::default(),
+            out,
+        }
+    }
+
+    fn say(&self, line: impl Into<String>) {
+        // The printer only goes away once the session is over.
+        let _ = self.out.send(line.into());
+    }
+
+    /// Waits for a running search to finish and takes the engine back.
+    fn wait(&mut self) -> &mut Engine {
+        if let Some(search) = self.searching.take() {
+            self.engine = Some(search.join().expect("the search thread panicked"));
+        }
+        self.engine
+            .as_mut()
+            .expect("the engine is back once the search ends")
+    }
+
+    /// Handles one line, returning `false` on `quit`. Anything but `stop`,
+    /// `ponderhit`, `isready` and `quit` waits for a running search to finish first.
+    fn handle(&mut self, line: &str) -> bool {
+        let line = line.trim();
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let result = match command {
+            "" => Ok(()),
+            "uci" => {
+                self.say("id name base");
+                self.say("id author the base authors");
+                self.say(format!(
+                    "option name Hash type spin default {DEFAULT_HASH} min 1 max {MAX_HASH}"
+                ));
+                self.say(format!(
+                    "option name Threads type spin default 1 min 1 max {MAX_THREADS}"
+                ));
+                self.say(format!(
+                    "option name MultiPV type spin default 1 min 1 max {MAX_LINES}"
+                ));
+                self.say("option name Ponder type check default false");
+                self.say("uciok");
+                Ok(())
+            }
+            "isready" => {
+                self.say("readyok");
+                Ok(())
+            }
+            "setoption" => self.set_option(rest),
+            "ucinewgame" => {
+                self.wait().new_game();
+                self.position = Position::default();
+                Ok(())
+            }
+            "position" => {
+                self.wait();
+                parse_position(rest).map(|position| self.position = position)
+            }
+            "go" => rest.parse().map(|go| self.go(go)),
+            "ponderhit" => {
+                if let Some(time) = self.ponder_time {
+                    self.stop.stop_after(time);
+                }
+                self.pondering.store(false, Ordering::Relaxed);
+                Ok(())
+            }
+            "stop" => {
+                self.stop.stop();
+                self.wait();
+                Ok(())
+            }
+            "quit" => {
+                self.stop.stop();
+                self.wait();
+                return false;
+            }
+            _ => Err(format!("Unknown command '{line}'")),
+        };
+        if let Err(e) = result {
+            self.say(format!("info string {e}"));
+        }
+        true
+    }
+
+    /// Reads `name <id> value <x>`; option names are not case sensitive.
+    fn set_option(&mut self, rest: &str) -> Result<(), String> {
+        let usage = || format!("usage: setoption name <id> value <x>, not '{rest}'");
+        let rest = rest.strip_prefix("name").ok_or_else(usage)?;
+        let (name, value) = rest.split_once(" value ").ok_or_else(usage)?;
+        let (name, value) = (name.trim(), value.trim());
+        let spin = |max: usize| {
+            value
+                .parse()
+                .ok()
+                .filter(|n| (1..=max).contains(n))
+                .ok_or_else(|| format!("Invalid {name} '{value}'"))
+        };
+        match name.to_lowercase().as_str() {
+            "threads" => self.wait().threads = spin(MAX_THREADS)?,
+            "hash" => self.wait().hash = spin(MAX_HASH)?,
+            "multipv" => self.wait().multipv = spin(MAX_LINES)?,
+            // Pondering is up to the GUI, which only asks for it when this
+            // is on, so there is nothing to change here.
+            "ponder" => {
+                value
+                    .parse::<bool>()
+                    .map_err(|_| format!("Invalid {name} '{value}'"))?;
+            }
+            _ => return Err(format!("No option '{name}'")),
+        }
+        Ok(())
+    }
+
+    fn go(&mut self, go: Go) {
+        let position = self.position.clone();
+        self.wait();
+        let mut engine = self.engine.take().expect("no search is running");
+        (engine.depth, engine.time) = go.limits(position.turn);
+        self.stop.reset();
+        self.pondering.store(go.ponder, Ordering::Relaxed);
+        if go.ponder {
+            // The clock only starts on ponderhit.
+            self.ponder_time = engine.time.take();
+        }
+        let stop = Arc::clone(&self.stop);
+        let pondering = Arc::clone(&self.pondering);
+        let out = self.out.clone();
+        self.searching = Some(std::thread::spawn(move || {
+            let result = engine.search_with(&position, |result| {
+                for line in info(result) {
+                    let _ = out.send(line);
+                }
+            });
+            // An infinite or pondering search may only answer once it is
+            // stopped or its move is played.
+            while (go.infinite || pondering.load(Ordering::Relaxed)) && !stop.is_stopped() {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            let best = result
+                .best
+                .map_or_else(|| "0000".to_string(), |mv| mv.to_string());
+            let answer = result
+                .lines
+                .first()
+                .and_then(|line| line.pv.get(1))
+                .map_or_else(
+                    || format!("bestmove {best}"),
+                    |reply| format!("bestmove {best} ponder {reply}"),
+                );
+            let _ = out.send(answer);
+            engine
+        }));
+    }
+}
+
+/// Runs a session over `input`, sending each line of output to `out`.
+pub fn run(input: impl BufRead, out: Sender<String>) {
+    let mut uci = Uci::new(out);
+    for line in input.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if !uci.handle(&line) {
+            return;
+        }
+    }
+    uci.handle("quit");
+}
+
+/// Entry point for `--uci`; returns the process exit code.
+pub fn main() -> i32 {
+    let (out, lines) = mpsc::channel::<String>();
+    let printer = std::thread::spawn(move || {
+        for line in lines {
+            println!("{line}");
+        }
+    });
+    run(std::io::stdin().lock(), out);
+    let _ = printer.join();
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::MATE;
+    use std::io::Cursor;
+
+    fn session(input: &str) -> Vec<String> {
+        let (out, lines) = mpsc::channel();
+        run(Cursor::new(input.to_string()), out);
+        lines.into_iter().collect()
+    }
+
+    #[test]
+    fn go_limits() {
+        let go = "wtime 60000 btime 1000 winc 1000 binc 0"
+            .parse::<Go>()
+            .unwrap();
+        assert_eq!(
+            go.limits(Color::White),
+            (MAX_DEPTH, Some(Duration::from_millis(2750)))
+        );
+        assert_eq!(
+            go.limits(Color::Black),
+            (MAX_DEPTH, Some(Duration::from_secs(1) / 30))
+        );
+        let go = "depth 5 movetime 200".parse::<Go>().unwrap();
+        assert_eq!(
+            go.limits(Color::Black),
+            (5, Some(Duration::from_millis(200)))
+        );
+        assert_eq!(
+            "infinite".parse::<Go>().unwrap().limits(Color::White),
+            (MAX_DEPTH, None)
+        );
+        assert!("depth".parse::<Go>().is_err());
+        assert!("depth x".parse::<Go>().is_err());
+        let go = "ponder wtime 3000 btime 3000".parse::<Go>().unwrap();
+        assert!(go.ponder && !go.infinite);
+        assert_eq!(go.clock, [Some(Duration::from_secs(3)); 2]);
+    }
+
+    #[test]
+    fn positions_and_scores() {
+        let position = parse_position("startpos moves e2e4 e7e5 g1f3").unwrap();
+        assert_eq!(
+            position.fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+        let fen = "fen 4k3/8/8/8/8/8/8/4K2R w K - 0 1 moves e1g1";
+        assert_eq!(
+            parse_position(fen).unwrap().fen(),
+            "4k3/8/8/8/8/8/8/5RK1 b - - 1 1"
+        );
+        assert!(parse_position("startpos moves e2e5").is_err());
+        assert_eq!(score(MATE - 1), "mate 1");
+        assert_eq!(score(MATE - 3), "mate 2");
+        assert_eq!(score(2 - MATE), "mate -1");
+        assert_eq!(score(-35), "cp -35");
+    }
+
+    #[test]
+    fn plays_a_session() {
+        let lines = session(
+            "uci\nsetoption name Threads value 2\nsetoption name Hash value 0\nisready\n\
+             position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\ngo depth 3\n\
+             position startpos\nbogus\nquit\n",
+        );
+        assert!(lines.contains(&"uciok".to_string()));
+        assert!(lines.contains(&"readyok".to_string()));
+        assert!(lines.contains(&"info string Invalid Hash '0'".to_string()));
+        assert!(lines.contains(&"info string Unknown command 'bogus'".to_string()));
+        assert!(lines.iter().any(
+            |line| line.starts_with("info depth 1 score mate 1 ") && line.ends_with("pv a1a8")
+        ));
+        assert!(lines.contains(&"bestmove a1a8".to_string()));
+    }
+
+    #[test]
+    fn stop_ends_an_infinite_search() {
+        let lines = session("position startpos\ngo infinite\nisready\nstop\n");
+        assert!(lines.contains(&"readyok".to_string()));
+        let best = lines.last().unwrap().split_whitespace().nth(1).unwrap();
+        assert!(Position::default().parse_move(best).is_ok());
+    }
+
+    #[test]
+    fn ponders_until_the_move_is_played() {
+        let (out, lines) = mpsc::channel();
+        let mut uci = Uci::new(out);
+        for line in [
+            "setoption name MultiPV value 2",
+            "setoption name Ponder value true",
+            "position startpos",
+            "go ponder movetime 50",
+        ] {
+            uci.handle(line);
+        }
+        // Well past the move time, but nothing is answered before ponderhit.
+        std::thread::sleep(Duration::from_millis(300));
+        let pondered = lines.try_iter().collect::<Vec<_>>();
+        assert!(pondered.iter().any(|line| line.contains(" multipv 2 ")));
+        assert!(!pondered.iter().any(|line| line.starts_with("bestmove")));
+
+        uci.handle("ponderhit");
+        uci.wait();
+        drop(uci);
+        let last = lines.into_iter().last().unwrap();
+        let answer = last.split_whitespace().collect::<Vec<_>>();
+        assert_eq!((answer[0], answer[2]), ("bestmove", "ponder"));
+        let mut position = Position::default();
+        position.play(answer[1]).unwrap();
+        assert!(position.parse_move(answer[3]).is_ok());
+    }
+}